@@ -0,0 +1,47 @@
+//! `cargo bench --bench path_set`, compares repeated canonicalizing
+//! against a `PathSet` lookup for the same membership checks
+
+#[cfg(windows)]
+fn main() {
+    use std::time::Instant;
+
+    use win_canonicalize::{canonicalize, PathSet};
+
+    let paths: Vec<String> = (0..1000)
+        .map(|i| format!("/c/dedup/{}/file.txt", i))
+        .collect();
+    let probes: Vec<String> = (0..1000)
+        .map(|i| format!(r#"C:\DEDUP\{}\.\FILE.TXT"#, i))
+        .collect();
+    let rounds = 10;
+
+    let start = Instant::now();
+    let canonical: Vec<String> = paths
+        .iter()
+        .map(|p| canonicalize(p).unwrap().to_uppercase())
+        .collect();
+    for _ in 0..rounds {
+        for probe in probes.iter() {
+            let probe = canonicalize(probe).unwrap().to_uppercase();
+            assert!(canonical.iter().any(|c| *c == probe));
+        }
+    }
+    println!("canonicalize + scan: {:?}", start.elapsed());
+
+    let start = Instant::now();
+    let mut set = PathSet::new();
+    for path in paths.iter() {
+        set.insert(path).unwrap();
+    }
+    for _ in 0..rounds {
+        for probe in probes.iter() {
+            assert!(set.contains(probe).unwrap());
+        }
+    }
+    println!("PathSet:             {:?}", start.elapsed());
+}
+
+#[cfg(not(windows))]
+fn main() {
+    println!("path_set benchmark only runs on windows");
+}
@@ -0,0 +1,76 @@
+//! `cargo bench --bench scratch`, counts the allocations made by repeated
+//! `canonicalize_u16` calls into a reused buffer, steady state should be none
+
+#[cfg(windows)]
+mod counting {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    pub static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+    pub struct Counting;
+
+    unsafe impl GlobalAlloc for Counting {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: Counting = Counting;
+}
+
+#[cfg(windows)]
+fn main() {
+    use std::{sync::atomic::Ordering, time::Instant};
+
+    use counting::ALLOCATIONS;
+    use win_canonicalize::canonicalize_u16;
+
+    let inputs: Vec<Vec<u16>> = (0..100)
+        .map(|i| format!("/c/Users/bench/../Documents/./{}/report.txt", i))
+        .map(|path| path.encode_utf16().collect())
+        .collect();
+    let mut out = Vec::new();
+    let rounds = 1000;
+
+    // the first calls size the scratch buffer and `out`, and initialize COM
+    for input in inputs.iter() {
+        canonicalize_u16(input, &mut out).unwrap();
+    }
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for input in inputs.iter() {
+            canonicalize_u16(input, &mut out).unwrap();
+        }
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    println!(
+        "canonicalize_u16: {:?} for {} calls, {} allocations",
+        elapsed,
+        rounds * inputs.len(),
+        allocations
+    );
+    assert_eq!(allocations, 0);
+}
+
+#[cfg(not(windows))]
+fn main() {
+    println!("scratch benchmark only runs on windows");
+}
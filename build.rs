@@ -2,8 +2,83 @@ fn main() {
     windows::build!(
         Windows::Win32::Foundation::PWSTR,
         Windows::Win32::UI::Shell::PathCchCanonicalizeEx,
+        Windows::Win32::UI::Shell::PATHCCH_MAX_CCH,
         Windows::Win32::Storage::FileSystem::MoveFileExW,
         Windows::Win32::Storage::FileSystem::MOVE_FILE_FLAGS,
-        Windows::Win32::System::Com::CoInitialize
+        Windows::Win32::Storage::FileSystem::QueryDosDeviceW,
+        Windows::Win32::Storage::FileSystem::GetVolumePathNameW,
+        Windows::Win32::Storage::FileSystem::GetVolumeNameForVolumeMountPointW,
+        Windows::Win32::Storage::FileSystem::FindFirstFileW,
+        Windows::Win32::Storage::FileSystem::FindClose,
+        Windows::Win32::Storage::FileSystem::FindNextFileW,
+        Windows::Win32::Storage::FileSystem::WIN32_FIND_DATAW,
+        Windows::Win32::Storage::FileSystem::CreateDirectoryW,
+        Windows::Win32::Storage::FileSystem::DeleteFileW,
+        Windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW,
+        Windows::Win32::Storage::FileSystem::CopyFile2,
+        Windows::Win32::Storage::FileSystem::COPYFILE2_EXTENDED_PARAMETERS,
+        Windows::Win32::Storage::FileSystem::COPYFILE2_MESSAGE,
+        Windows::Win32::Storage::FileSystem::COPYFILE2_MESSAGE_ACTION,
+        Windows::Win32::Storage::FileSystem::COPYFILE2_MESSAGE_TYPE,
+        Windows::Win32::System::WindowsProgramming::COPY_FILE_RESTARTABLE,
+        Windows::Win32::System::WindowsProgramming::COPY_FILE_NO_BUFFERING,
+        Windows::Win32::Storage::FileSystem::GetFileAttributesExW,
+        Windows::Win32::Storage::FileSystem::WIN32_FILE_ATTRIBUTE_DATA,
+        Windows::Win32::Storage::FileSystem::GET_FILEEX_INFO_LEVELS,
+        Windows::Win32::Storage::FileSystem::GetDriveTypeW,
+        Windows::Win32::System::WindowsProgramming::DRIVE_REMOVABLE,
+        Windows::Win32::System::WindowsProgramming::DRIVE_FIXED,
+        Windows::Win32::System::WindowsProgramming::DRIVE_REMOTE,
+        Windows::Win32::System::WindowsProgramming::DRIVE_CDROM,
+        Windows::Win32::System::WindowsProgramming::DRIVE_RAMDISK,
+        Windows::Win32::System::WindowsProgramming::DRIVE_NO_ROOT_DIR,
+        Windows::Win32::Storage::FileSystem::CreateFileW,
+        Windows::Win32::Storage::FileSystem::GetFileInformationByHandleEx,
+        Windows::Win32::Storage::FileSystem::FILE_ID_INFO,
+        Windows::Win32::Storage::FileSystem::FILE_INFO_BY_HANDLE_CLASS,
+        Windows::Win32::Storage::FileSystem::FILE_ACCESS_FLAGS,
+        Windows::Win32::Storage::FileSystem::FILE_SHARE_MODE,
+        Windows::Win32::Storage::FileSystem::FILE_CREATION_DISPOSITION,
+        Windows::Win32::Storage::FileSystem::FILE_FLAGS_AND_ATTRIBUTES,
+        Windows::Win32::Foundation::CloseHandle,
+        Windows::Win32::Storage::FileSystem::GetFinalPathNameByHandleW,
+        Windows::Win32::Storage::FileSystem::FILE_NAME,
+        Windows::Win32::System::SystemServices::DeviceIoControl,
+        Windows::Win32::System::SystemServices::FSCTL_GET_REPARSE_POINT,
+        Windows::Win32::System::SystemServices::IO_REPARSE_TAG_APPEXECLINK,
+        Windows::Win32::System::SystemServices::FSCTL_QUERY_PERSISTENT_VOLUME_STATE,
+        Windows::Win32::System::SystemServices::FILE_FS_PERSISTENT_VOLUME_INFORMATION,
+        Windows::Win32::System::SystemServices::PERSISTENT_VOLUME_STATE_SHORT_NAME_CREATION_DISABLED,
+        Windows::Win32::Storage::FileSystem::GetShortPathNameW,
+        Windows::Win32::System::Registry::RegGetValueW,
+        Windows::Win32::System::Registry::HKEY_LOCAL_MACHINE,
+        Windows::Win32::System::Registry::RRF_RT,
+        Windows::Win32::System::LibraryLoader::GetModuleFileNameW,
+        Windows::Win32::System::Diagnostics::Debug::FormatMessageW,
+        Windows::Win32::System::Diagnostics::Debug::FORMAT_MESSAGE_OPTIONS,
+        Windows::Win32::Globalization::NormalizeString,
+        Windows::Win32::Globalization::NORM_FORM,
+        Windows::Win32::System::Com::CoInitializeEx,
+        Windows::Win32::System::Com::COINIT,
+        Windows::Win32::System::Com::CoUninitialize,
+        Windows::Win32::Foundation::RPC_E_CHANGED_MODE,
+        Windows::Win32::Storage::DistributedFileSystem::NetDfsGetClientInfo,
+        Windows::Win32::Storage::DistributedFileSystem::DFS_INFO_3,
+        Windows::Win32::Storage::DistributedFileSystem::DFS_STORAGE_INFO,
+        Windows::Win32::Storage::DistributedFileSystem::DFS_STORAGE_STATE_ACTIVE,
+        Windows::Win32::NetworkManagement::NetManagement::NetApiBufferFree,
+        Windows::Win32::UI::Shell::SHGetKnownFolderPath,
+        Windows::Win32::UI::Shell::FOLDERID_Desktop,
+        Windows::Win32::UI::Shell::FOLDERID_Documents,
+        Windows::Win32::UI::Shell::FOLDERID_Downloads,
+        Windows::Win32::UI::Shell::FOLDERID_RoamingAppData,
+        Windows::Win32::UI::Shell::FOLDERID_LocalAppData,
+        Windows::Win32::UI::Shell::FOLDERID_ProgramFiles,
+        Windows::Win32::UI::Shell::FOLDERID_ProgramFilesX86,
+        Windows::Win32::UI::Shell::FOLDERID_Profile,
+        Windows::Win32::UI::Shell::FOLDERID_Windows,
+        Windows::Win32::UI::Shell::FOLDERID_System,
+        Windows::Win32::System::Com::CoTaskMemFree,
+        Windows::Win32::Storage::FileSystem::GetFullPathNameW
     );
 }
@@ -0,0 +1,50 @@
+//! `cargo fuzz run canonicalize`, feeds arbitrary input through the regex
+//! stages and checks they never panic. Run with `-- -timeout=5` to catch
+//! a stage looping on pathological input.
+//!
+//! Off windows only the textual stages run (`only_cur_dir`), as the Win32
+//! calls behind `PathCchCanonicalizeEx` aren't available there.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use win_canonicalize::{
+    canonicalize_with, preview, CanonicalizeOptions, Case, TrailingSlash, UncCase,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let (flags, path) = match data.split_first() {
+        Some((flags, rest)) => (*flags, rest),
+        None => return,
+    };
+    let path = match std::str::from_utf8(path) {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(!cfg!(windows) || flags & 0x01 != 0)
+        .shell_escapes(flags & 0x02 != 0)
+        .shell_tilde_dirs(flags & 0x04 != 0)
+        .expand_tilde_anywhere(flags & 0x08 != 0)
+        .narrow_fullwidth(flags & 0x10 != 0)
+        .trailing_slash(if flags & 0x20 != 0 {
+            TrailingSlash::Never
+        } else {
+            TrailingSlash::Always
+        })
+        .unc_case(if flags & 0x40 != 0 {
+            UncCase::Upper
+        } else {
+            UncCase::Preserve
+        })
+        .case(if flags & 0x80 != 0 {
+            Case::UpperDrive
+        } else {
+            Case::AsIs
+        });
+
+    // an error is a fine answer, a panic is not
+    let _ = canonicalize_with(path, &options);
+    let _ = preview(path, &options);
+});
@@ -0,0 +1,101 @@
+use crate::{canonicalize_with, matchers, CanonicalizeError, CanonicalizeOptions};
+
+/// Splits a canonical path into cmd's `%~d0`, `%~p0`, `%~n0` and `%~x0` parts
+fn cmd_parts(path: &str) -> (&str, &str, &str, &str) {
+    let (drive, rest) = match path.find(':') {
+        Option::Some(index) => path.split_at(index + 1),
+        Option::None => ("", path),
+    };
+    let (dir, file) = match rest.rfind('\\') {
+        Option::Some(index) => rest.split_at(index + 1),
+        Option::None => ("", rest),
+    };
+    // a leading dot (`.gitignore`) is part of the name, not an extension
+    let (name, ext) = match file.rfind('.') {
+        Option::Some(index) if index > 0 => file.split_at(index),
+        _ => (file, ""),
+    };
+    (drive, dir, name, ext)
+}
+
+#[test]
+fn test_cmd_parts() {
+    assert_eq!(
+        cmd_parts(r#"C:\tools\bin\app.exe"#),
+        ("C:", r#"\tools\bin\"#, "app", ".exe")
+    );
+    assert_eq!(
+        cmd_parts(r#"C:\tools\app.tar.gz"#),
+        ("C:", r#"\tools\"#, "app.tar", ".gz")
+    );
+    assert_eq!(cmd_parts(r#"C:\.profile"#), ("C:", r#"\"#, ".profile", ""));
+    assert_eq!(
+        cmd_parts(r#"C:\tools\run"#),
+        ("C:", r#"\tools\"#, "run", "")
+    );
+}
+
+/// replaces every `%~<modifiers>0` in `path`, `base` is already canonical
+fn expand_arg0(path: &str, base: &str) -> String {
+    let (drive, dir, name, ext) = cmd_parts(base);
+    let mut out = String::with_capacity(path.len());
+    let mut last = 0;
+    for (whole, modifiers) in matchers::arg0_modifiers(path) {
+        out.push_str(&path[last..whole.start]);
+        let modifiers = path[modifiers].to_lowercase();
+        // cmd always emits the parts in this order, whatever order they were written in
+        for (letter, part) in [('d', drive), ('p', dir), ('n', name), ('x', ext)].iter() {
+            if modifiers.contains(*letter) {
+                out.push_str(part);
+            }
+        }
+        last = whole.end;
+    }
+    out.push_str(&path[last..]);
+    out
+}
+
+#[test]
+fn test_expand_arg0() {
+    let base = r#"C:\tools\bin\app.exe"#;
+    assert_eq!(
+        expand_arg0("%~dp0config.ini", base),
+        r#"C:\tools\bin\config.ini"#
+    );
+    assert_eq!(
+        expand_arg0(r#"%~dp0..\config"#, base),
+        r#"C:\tools\bin\..\config"#
+    );
+    assert_eq!(expand_arg0(r#"%~d0\data"#, base), r#"C:\data"#);
+    assert_eq!(expand_arg0("%~p0", base), r#"\tools\bin\"#);
+    assert_eq!(expand_arg0("%~n0.log", base), "app.log");
+    assert_eq!(expand_arg0("%~n0%~x0", base), "app.exe");
+    assert_eq!(expand_arg0("%~xn0", base), "app.exe");
+    assert_eq!(expand_arg0("%~DP0x", base), r#"C:\tools\bin\x"#);
+    // other batch variables are left alone
+    assert_eq!(expand_arg0("%~dp1x", base), "%~dp1x");
+}
+
+/// Expands cmd's arg0 path modifiers (`%~dp0`, `%~d0`, `%~p0`, `%~n0`, `%~x0`,
+/// and combinations like `%~nx0`) against `base_exe`, then canonicalizes.
+///
+/// Intended for paths handed over by a `.bat` wrapper, `base_exe` stands in
+/// for the batch file's own path.
+pub fn expand_cmd_macros(path: &str, base_exe: &str) -> Result<String, CanonicalizeError> {
+    let options = CanonicalizeOptions::default();
+    let base = canonicalize_with(base_exe, &options)?;
+    canonicalize_with(&expand_arg0(path, &base), &options)
+}
+
+#[cfg(windows)]
+#[test]
+fn test_expand_cmd_macros() {
+    assert_eq!(
+        expand_cmd_macros("%~dp0config.ini", "/c/tools/bin/app.bat").unwrap(),
+        r#"C:\tools\bin\config.ini"#
+    );
+    assert_eq!(
+        expand_cmd_macros(r#"%~dp0..\config"#, r#"C:\tools\bin\app.bat"#).unwrap(),
+        r#"C:\tools\config"#
+    );
+}
@@ -0,0 +1,455 @@
+use crate::{
+    canonicalize_pair, expand_short_names, file_id, handle::strip_verbatim, CanonicalizeError,
+};
+
+/// NTFS upcases names one UTF-16 unit at a time, so a character whose
+/// uppercase isn't a single unit (`ß` is `SS`) is compared as it is
+fn upcase(c: char) -> char {
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Option::Some(u), Option::None) if c.len_utf16() == 1 && u.len_utf16() == 1 => u,
+        _ => c,
+    }
+}
+
+/// case folds a path the way Windows compares names
+pub(crate) fn fold_case(path: &str) -> String {
+    path.chars().map(upcase).collect()
+}
+
+#[test]
+fn test_fold_case() {
+    assert_eq!(fold_case(r#"c:\Über\ǆ"#), r#"C:\ÜBER\Ǆ"#);
+    // two different files on NTFS
+    assert_eq!(fold_case("straße"), "STRAßE");
+    assert_ne!(fold_case("straße"), fold_case("STRASSE"));
+    // outside the BMP nothing is folded
+    assert_eq!(fold_case("\u{10428}"), "\u{10428}");
+}
+
+/// case-insensitive comparison of a single path component
+pub(crate) fn component_eq(a: &str, b: &str) -> bool {
+    a.chars().map(upcase).eq(b.chars().map(upcase))
+}
+
+/// the components of a `\` separated path, ignoring any trailing separator
+pub(crate) fn components(path: &str) -> Vec<&str> {
+    path.trim_end_matches('\\').split('\\').collect()
+}
+
+/// whole-component, case-insensitive prefix check on already canonical paths,
+/// a long path's `\\?\` prefix doesn't count against it
+fn has_component_prefix(path: &str, prefix: &str) -> bool {
+    let path = strip_verbatim(path);
+    let prefix = strip_verbatim(prefix);
+    let path = components(&path);
+    let prefix = components(&prefix);
+    prefix.len() <= path.len()
+        && prefix
+            .iter()
+            .zip(path.iter())
+            .all(|(a, b)| component_eq(a, b))
+}
+
+#[test]
+fn test_has_component_prefix() {
+    assert!(has_component_prefix(r#"C:\Users\Bob"#, r#"C:\Users\Bob"#));
+    assert!(has_component_prefix(r#"C:\Users\Bob\x"#, r#"C:\Users\Bob"#));
+    assert!(has_component_prefix(
+        r#"C:\Users\bob\x"#,
+        r#"c:\USERS\Bob\"#
+    ));
+    assert!(has_component_prefix(r#"C:\Users"#, r#"C:\"#));
+    assert!(!has_component_prefix(
+        r#"C:\Users\Bobby"#,
+        r#"C:\Users\Bob"#
+    ));
+    assert!(!has_component_prefix(r#"C:\Users"#, r#"C:\Users\Bob"#));
+    assert!(!has_component_prefix(r#"D:\Users\Bob"#, r#"C:\Users\Bob"#));
+    assert!(has_component_prefix(
+        r#"\\?\C:\Users\Bob\x"#,
+        r#"C:\Users\Bob"#
+    ));
+    assert!(has_component_prefix(
+        r#"\\?\UNC\server\share\x"#,
+        r#"\\server\share"#
+    ));
+    assert!(!has_component_prefix(
+        r#"\\?\C:\Users\Bobby"#,
+        r#"\\?\C:\Users\Bob"#
+    ));
+}
+
+/// Reports if `path` lives under `prefix`, comparing whole components.
+///
+/// Unlike `str::starts_with`, `C:\Users\Bobby` does not start with `C:\Users\Bob`.
+/// Both sides are canonicalized first and compared case-insensitively.
+pub fn starts_with_path(path: &str, prefix: &str) -> Result<bool, CanonicalizeError> {
+    let (path, prefix) = canonicalize_pair(path, prefix)?;
+    Ok(has_component_prefix(&path, &prefix))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_starts_with_path() {
+    assert!(starts_with_path(r#"C:\Users\Bob"#, r#"C:\Users\Bob"#).unwrap());
+    assert!(starts_with_path(r#"/c/users/bob/Documents"#, r#"C:\Users\Bob"#).unwrap());
+    assert!(!starts_with_path(r#"C:\Users\Bobby"#, r#"C:\Users\Bob"#).unwrap());
+    assert!(!starts_with_path(r#"C:\Users\Bob\..\Bobby"#, r#"C:\Users\Bob"#).unwrap());
+    // past MAX_PATH only `path` comes back with a `\\?\` prefix
+    let long = format!(r#"C:\Users\Bob\{}\a.txt"#, "x".repeat(300));
+    assert!(starts_with_path(&long, r#"C:\Users\Bob"#).unwrap());
+    assert!(!starts_with_path(&long, r#"C:\Users\Bobby"#).unwrap());
+}
+
+/// `path` with a leading `home` swapped for `~`, `None` when it isn't under
+/// `home`. Both already canonical, what follows `home` keeps its own casing.
+fn tilde_form(path: &str, home: &str) -> Option<String> {
+    if !has_component_prefix(path, home) {
+        return None;
+    }
+    let (path, home) = (strip_verbatim(path), strip_verbatim(home));
+    let rest: Vec<&str> = path.split('\\').skip(components(&home).len()).collect();
+    if rest.is_empty() {
+        Some("~".to_string())
+    } else {
+        Some(format!(r#"~\{}"#, rest.join("\\")))
+    }
+}
+
+#[test]
+fn test_tilde_form() {
+    let home = r#"C:\Users\Bob"#;
+    assert_eq!(
+        tilde_form(r#"C:\Users\Bob\proj"#, home),
+        Some(r#"~\proj"#.to_string())
+    );
+    assert_eq!(
+        tilde_form(r#"c:\users\BOB\Proj\a.rs"#, r#"C:\Users\Bob\"#),
+        Some(r#"~\Proj\a.rs"#.to_string())
+    );
+    assert_eq!(tilde_form(home, home), Some("~".to_string()));
+    assert_eq!(
+        tilde_form(r#"\\?\C:\Users\Bob\proj"#, home),
+        Some(r#"~\proj"#.to_string())
+    );
+    assert_eq!(
+        tilde_form(r#"C:\Users\Bob\"#, home),
+        Some(r#"~\"#.to_string())
+    );
+    assert_eq!(tilde_form(r#"C:\Users\Bobby\proj"#, home), None);
+    assert_eq!(tilde_form(r#"D:\proj"#, home), None);
+}
+
+/// Canonicalizes `path` and swaps a leading home directory (`HOME`) for `~`,
+/// the reverse of tilde expansion, for config files shared between machines.
+///
+/// The home directory is matched case-insensitively and by whole components,
+/// a path outside it comes back canonical but otherwise unchanged.
+pub fn contract_tilde(path: &str) -> Result<String, CanonicalizeError> {
+    let home = std::env::var("HOME").map_err(CanonicalizeError::Home)?;
+    let (path, home) = canonicalize_pair(path, &home)?;
+    Ok(tilde_form(&path, &home).unwrap_or(path))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_contract_tilde() {
+    let home = std::env::var("HOME").unwrap();
+    assert_eq!(
+        contract_tilde(&format!("{}/proj/./src", home)).unwrap(),
+        r#"~\proj\src"#
+    );
+
+    let outside = format!("{}/../someone_else", home);
+    assert_eq!(
+        contract_tilde(&outside).unwrap(),
+        crate::canonicalize_with(&outside, &crate::CanonicalizeOptions::default()).unwrap()
+    );
+}
+
+/// whole-path, case-insensitive equality of already canonical paths
+fn path_text_eq(a: &str, b: &str) -> bool {
+    let a = components(a);
+    let b = components(b);
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| component_eq(a, b))
+}
+
+#[test]
+fn test_path_text_eq() {
+    assert!(path_text_eq(r#"C:\Users\Bob"#, r#"c:\users\BOB\"#));
+    assert!(!path_text_eq(r#"C:\Users\Bob"#, r#"C:\Users\Bobby"#));
+    assert!(!path_text_eq(r#"C:\Users\Bob"#, r#"C:\Users\Bob\a"#));
+}
+
+/// two canonical paths name the same file, going by their text or, when both
+/// exist, their file ids (hard links, 8.3 names, mount points)
+fn same_file(a: &str, b: &str) -> bool {
+    if path_text_eq(a, b) {
+        return true;
+    }
+    match (file_id(a), file_id(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Reports if two paths are the same file.
+///
+/// Both are canonicalized and compared case-insensitively, when that doesn't
+/// settle it and both exist, their file ids are compared as well. So two hard
+/// links to one file, or a path and its 8.3 short name, are the same path.
+pub fn is_same_path(a: &str, b: &str) -> Result<bool, CanonicalizeError> {
+    let (a, b) = canonicalize_pair(a, b)?;
+    Ok(same_file(&a, &b))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_is_same_path() {
+    let dir = std::env::temp_dir().join("win_canonicalize_same_path");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let a = dir.join("a.txt");
+    let link = dir.join("link.txt");
+    std::fs::write(&a, b"a").unwrap();
+    std::fs::hard_link(&a, &link).unwrap();
+    let upper = format!(r#"{}\x\..\A.TXT"#, dir.to_str().unwrap());
+    let a = a.to_str().unwrap();
+
+    assert!(is_same_path(a, &upper).unwrap());
+    assert!(is_same_path(a, link.to_str().unwrap()).unwrap());
+    assert!(!is_same_path(a, dir.join("missing.txt").to_str().unwrap()).unwrap());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// the textual form `equivalent` compares, 8.3 names expanded as far as the
+/// path exists and any `\\?\` prefix dropped
+fn long_form(canonical: &str) -> Result<String, CanonicalizeError> {
+    Ok(strip_verbatim(&expand_short_names(canonical)?).into_owned())
+}
+
+/// Reports if two paths denote the same file system object, whatever their
+/// separators, casing, 8.3 names, mingw form, `\\?\` prefix or trailing `\`.
+///
+/// Goes further than `is_same_path`: short names are expanded even when the
+/// file itself doesn't exist yet, as long as the directory holding it does.
+/// When the text still differs and both exist, their file ids decide, which
+/// covers hard links and mount points.
+pub fn equivalent(a: &str, b: &str) -> Result<bool, CanonicalizeError> {
+    let (a, b) = canonicalize_pair(a, b)?;
+    if path_text_eq(&long_form(&a)?, &long_form(&b)?) {
+        return Ok(true);
+    }
+    Ok(same_file(&a, &b))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_equivalent() {
+    let dir = std::env::temp_dir().join("win_canonicalize equivalent dir");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("a.txt"), b"a").unwrap();
+    std::fs::hard_link(dir.join("a.txt"), dir.join("hard.txt")).unwrap();
+    let dir = crate::canonicalize_with(
+        dir.to_str().unwrap(),
+        &crate::CanonicalizeOptions::default(),
+    )
+    .unwrap();
+    let mingw = format!(
+        "/{}{}",
+        dir[..1].to_lowercase(),
+        dir[2..].replace('\\', "/")
+    );
+
+    let mut cases = vec![
+        // separators and dot segments
+        (
+            format!(r#"{}\a.txt"#, dir),
+            format!("{}/./x/../a.txt", dir),
+            true,
+        ),
+        (
+            format!(r#"{}\a.txt"#, dir),
+            format!(r#"{}\\\a.txt"#, dir),
+            true,
+        ),
+        // case
+        (
+            format!(r#"{}\a.txt"#, dir),
+            format!(r#"{}\A.TXT"#, dir.to_uppercase()),
+            true,
+        ),
+        // mingw
+        (
+            format!(r#"{}\a.txt"#, dir),
+            format!("{}/a.txt", mingw),
+            true,
+        ),
+        // trailing slashes
+        (dir.clone(), format!(r#"{}\"#, dir), true),
+        (dir.clone(), format!("{}//", mingw), true),
+        // `\\?\` prefix
+        (
+            format!(r#"{}\a.txt"#, dir),
+            format!(r#"\\?\{}\a.txt"#, dir),
+            true,
+        ),
+        // file identity
+        (
+            format!(r#"{}\a.txt"#, dir),
+            format!(r#"{}\hard.txt"#, dir),
+            true,
+        ),
+        // none of it exists, so only the text counts
+        (
+            r#"C:\nosuch\x"#.to_string(),
+            "/c/NOSUCH/x/".to_string(),
+            true,
+        ),
+        (
+            format!(r#"{}\missing.txt"#, dir),
+            format!(r#"{}/MISSING.txt"#, mingw),
+            true,
+        ),
+        // genuinely different
+        (
+            format!(r#"{}\a.txt"#, dir),
+            format!(r#"{}\b.txt"#, dir),
+            false,
+        ),
+        (
+            format!(r#"{}\a.txt"#, dir),
+            format!(r#"{}\a.txt\x"#, dir),
+            false,
+        ),
+        (
+            format!(r#"{}\a.txt"#, dir),
+            format!(r#"{}\missing.txt"#, dir),
+            false,
+        ),
+        (
+            r#"C:\nosuch\x"#.to_string(),
+            r#"D:\nosuch\x"#.to_string(),
+            false,
+        ),
+    ];
+
+    // only exercised where the volume generates 8.3 names
+    let found = crate::find::find_file(&dir).unwrap().unwrap();
+    if !found.short_name.is_empty() {
+        let short = format!(
+            r#"{}\{}"#,
+            &dir[..dir.rfind('\\').unwrap()],
+            found.short_name
+        );
+        cases.push((
+            format!(r#"{}\a.txt"#, dir),
+            format!(r#"{}\a.txt"#, short),
+            true,
+        ));
+        cases.push((
+            format!(r#"{}\new.txt"#, dir),
+            format!(r#"{}\NEW.TXT"#, short),
+            true,
+        ));
+        cases.push((
+            format!(r#"{}\a.txt"#, dir),
+            format!(r#"{}\b.txt"#, short),
+            false,
+        ));
+    }
+
+    for (a, b, expected) in &cases {
+        assert_eq!(equivalent(a, b).unwrap(), *expected, "{:?} {:?}", a, b);
+        assert_eq!(equivalent(b, a).unwrap(), *expected, "{:?} {:?}", b, a);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Moving canonical `src` onto `dst` wouldn't change anything, they're the
+/// same file. A rename which only changes the case does change something.
+pub(crate) fn is_noop_move(src: &str, dst: &str) -> bool {
+    src == dst || (!path_text_eq(src, dst) && same_file(src, dst))
+}
+
+/// the components of `path` after `base`, joined with `/` for use in a url
+fn component_remainder(path: &str, base: &str) -> Option<String> {
+    if !has_component_prefix(path, base) {
+        return None;
+    }
+    let (path, base) = (strip_verbatim(path), strip_verbatim(base));
+    let base = components(&base);
+    let rest: Vec<&str> = components(&path)
+        .into_iter()
+        .skip(base.len())
+        .filter(|part| !part.is_empty())
+        .collect();
+    Some(rest.join("/"))
+}
+
+#[test]
+fn test_component_remainder() {
+    let root = r#"C:\www\docs"#;
+    assert_eq!(
+        component_remainder(r#"C:\www\docs\a\b.html"#, root),
+        Some("a/b.html".to_string())
+    );
+    assert_eq!(
+        component_remainder(r#"c:\WWW\Docs\a"#, r#"C:\www\docs\"#),
+        Some("a".to_string())
+    );
+    assert_eq!(component_remainder(root, root), Some(String::new()));
+    assert_eq!(
+        component_remainder(r#"C:\www\docs\"#, root),
+        Some(String::new())
+    );
+    assert_eq!(component_remainder(r#"C:\www\docs2\a"#, root), None);
+    assert_eq!(component_remainder(r#"C:\www"#, root), None);
+    assert_eq!(
+        component_remainder(r#"C:\Users"#, r#"C:\"#),
+        Some("Users".to_string())
+    );
+    assert_eq!(
+        component_remainder(r#"\\?\C:\www\docs\a\b.html"#, root),
+        Some("a/b.html".to_string())
+    );
+    assert_eq!(
+        component_remainder(r#"\\?\UNC\server\share\a"#, r#"\\server\share"#),
+        Some("a".to_string())
+    );
+}
+
+/// Canonicalizes both paths and returns the part of `path` below `base`, as a
+/// `/` separated relative url path.
+///
+/// `None` if `path` is not under `base`, an empty string if they are the same.
+pub fn strip_prefix_path(path: &str, base: &str) -> Result<Option<String>, CanonicalizeError> {
+    let (path, base) = canonicalize_pair(path, base)?;
+    Ok(component_remainder(&path, &base))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_strip_prefix_path() {
+    let root = r#"C:\www\docs"#;
+    assert_eq!(
+        strip_prefix_path("/c/www/docs/a/./b.html", root).unwrap(),
+        Some("a/b.html".to_string())
+    );
+    assert_eq!(
+        strip_prefix_path(r#"C:\WWW\docs\"#, root).unwrap(),
+        Some(String::new())
+    );
+    assert_eq!(
+        strip_prefix_path(r#"C:\www\docs\..\secret.txt"#, root).unwrap(),
+        None
+    );
+    let name = "x".repeat(300);
+    assert_eq!(
+        strip_prefix_path(&format!(r#"{}\{}\a.html"#, root, name), root).unwrap(),
+        Some(format!("{}/a.html", name))
+    );
+}
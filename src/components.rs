@@ -0,0 +1,99 @@
+use std::{ffi::OsStr, path::Component};
+
+use crate::{canonicalize_with, CanonicalizeError, CanonicalizeOptions};
+
+/// the text of a single component, which on Windows must be valid UTF-16
+fn component_str(name: &OsStr) -> Result<String, CanonicalizeError> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        let wide: Vec<u16> = name.encode_wide().collect();
+        Ok(String::from_utf16(&wide)?)
+    }
+    // only reachable from tests, nothing else here runs off Windows
+    #[cfg(not(windows))]
+    {
+        Ok(name.to_string_lossy().into_owned())
+    }
+}
+
+/// joins components with `\`, except directly after a prefix (`C:` is
+/// drive relative, `C:\` isn't) or the root
+fn assemble<'a, I>(iter: I) -> Result<String, CanonicalizeError>
+where
+    I: IntoIterator<Item = Component<'a>>,
+{
+    let mut path = String::new();
+    let mut separate = false;
+    for component in iter {
+        match component {
+            Component::Prefix(prefix) => {
+                path.push_str(&component_str(prefix.as_os_str())?);
+                separate = false;
+            }
+            Component::RootDir => {
+                path.push('\\');
+                separate = false;
+            }
+            Component::CurDir | Component::ParentDir | Component::Normal(_) => {
+                if separate {
+                    path.push('\\');
+                }
+                path.push_str(&component_str(component.as_os_str())?);
+                separate = true;
+            }
+        }
+    }
+    Ok(path)
+}
+
+#[test]
+fn test_assemble() {
+    use std::path::Path;
+
+    assert_eq!(
+        assemble(Path::new("a/../b").components()).unwrap(),
+        r#"a\..\b"#
+    );
+    assert_eq!(
+        assemble(Path::new("/a/b.txt").components()).unwrap(),
+        r#"\a\b.txt"#
+    );
+    assert_eq!(
+        assemble(vec![Component::CurDir, Component::Normal("a".as_ref())]).unwrap(),
+        r#".\a"#
+    );
+    assert_eq!(assemble(vec![Component::RootDir]).unwrap(), r#"\"#);
+    assert_eq!(assemble(Vec::new()).unwrap(), "");
+}
+
+/// Renders `std::path::Component`s, e.g. from `Path::components` or built up
+/// by hand, as a canonical Windows path.
+///
+/// Fails with `CanonicalizeError::Utf16` if a component isn't valid unicode.
+pub fn from_components<'a, I>(iter: I) -> Result<String, CanonicalizeError>
+where
+    I: IntoIterator<Item = Component<'a>>,
+{
+    canonicalize_with(&assemble(iter)?, &CanonicalizeOptions::default())
+}
+
+#[cfg(windows)]
+#[test]
+fn test_from_components() {
+    use std::path::Path;
+
+    let prefix = Path::new("C:").components().next().unwrap();
+    let components = vec![
+        prefix,
+        Component::RootDir,
+        Component::Normal("a".as_ref()),
+        Component::ParentDir,
+        Component::Normal("b".as_ref()),
+    ];
+    assert_eq!(from_components(components).unwrap(), r#"C:\b"#);
+    assert_eq!(
+        from_components(Path::new(r#"C:\Users\.\Valarauca"#).components()).unwrap(),
+        r#"C:\Users\Valarauca"#
+    );
+}
@@ -0,0 +1,246 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use windows::HRESULT;
+
+use crate::{
+    bindings::Windows::Win32::{
+        Storage::FileSystem::{
+            CopyFile2, COPYFILE2_CALLBACK_CHUNK_FINISHED, COPYFILE2_EXTENDED_PARAMETERS,
+            COPYFILE2_MESSAGE, COPYFILE2_MESSAGE_ACTION, COPYFILE2_PROGRESS_CANCEL,
+            COPYFILE2_PROGRESS_CONTINUE, COPYFILE2_PROGRESS_STOP,
+        },
+        System::WindowsProgramming::{COPY_FILE_NO_BUFFERING, COPY_FILE_RESTARTABLE},
+    },
+    canonicalize_pair,
+    device::reject_devices,
+    CanonicalizeError,
+};
+
+const ERROR_REQUEST_ABORTED: u32 = 1235;
+
+/// Stops a `copy_file2` from another thread, clones share the same flag
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    /// asks any copy watching this token to stop after its current chunk
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Tunes `copy_file2`
+#[derive(Clone, Debug, Default)]
+pub struct CopyOptions {
+    restartable: bool,
+    no_buffering: bool,
+    cancel: Option<CancelToken>,
+}
+
+impl CopyOptions {
+    /// `COPY_FILE_RESTARTABLE`, progress is tracked in the destination so a
+    /// cancelled copy picks up where it left off when it's run again.
+    /// Slower, only worth it for very large files.
+    pub fn restartable(mut self, restartable: bool) -> Self {
+        self.restartable = restartable;
+        self
+    }
+
+    /// `COPY_FILE_NO_BUFFERING`, skip the system cache, which helps with files
+    /// much larger than memory and hurts with small ones
+    pub fn no_buffering(mut self, no_buffering: bool) -> Self {
+        self.no_buffering = no_buffering;
+        self
+    }
+
+    /// checked after every chunk, the copy stops once it's cancelled
+    pub fn cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    fn flags(&self) -> u32 {
+        let mut flags = 0;
+        if self.restartable {
+            flags |= COPY_FILE_RESTARTABLE;
+        }
+        if self.no_buffering {
+            flags |= COPY_FILE_NO_BUFFERING;
+        }
+        flags
+    }
+}
+
+/// How far a `copy_file2` got, in bytes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CopyProgress {
+    pub copied: u64,
+    pub total: u64,
+}
+
+/// How a `copy_file2` ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyStatus {
+    Completed(CopyProgress),
+    /// the cancel token fired, a restartable copy left its partial
+    /// destination in place to resume from, anything else removed it
+    Cancelled(CopyProgress),
+}
+
+/// what the progress routine shares with `copy_file2`
+struct CopyState<'a> {
+    options: &'a CopyOptions,
+    progress: CopyProgress,
+    cancelled: bool,
+}
+
+/// `PCOPYFILE2_PROGRESS_ROUTINE`, records progress and checks the cancel token
+unsafe extern "system" fn on_progress(
+    message: *const COPYFILE2_MESSAGE,
+    context: *mut std::ffi::c_void,
+) -> COPYFILE2_MESSAGE_ACTION {
+    let state = &mut *(context as *mut CopyState);
+    let message = &*message;
+    if message.Type == COPYFILE2_CALLBACK_CHUNK_FINISHED {
+        let chunk = message.Info.ChunkFinished;
+        state.progress = CopyProgress {
+            copied: chunk.uliTotalBytesTransferred,
+            total: chunk.uliTotalFileSize,
+        };
+    }
+    if !state
+        .options
+        .cancel
+        .as_ref()
+        .is_some_and(CancelToken::is_cancelled)
+    {
+        return COPYFILE2_PROGRESS_CONTINUE;
+    }
+    state.cancelled = true;
+    // stopping, unlike cancelling, keeps the partial copy for a restart
+    if state.options.restartable {
+        COPYFILE2_PROGRESS_STOP
+    } else {
+        COPYFILE2_PROGRESS_CANCEL
+    }
+}
+
+#[test]
+fn test_on_progress() {
+    use crate::bindings::Windows::Win32::Storage::FileSystem::{
+        COPYFILE2_MESSAGE_0, COPYFILE2_MESSAGE_0_0,
+    };
+
+    let message = COPYFILE2_MESSAGE {
+        Type: COPYFILE2_CALLBACK_CHUNK_FINISHED,
+        dwPadding: 0,
+        Info: COPYFILE2_MESSAGE_0 {
+            ChunkFinished: COPYFILE2_MESSAGE_0_0 {
+                uliTotalBytesTransferred: 512,
+                uliTotalFileSize: 2048,
+                ..Default::default()
+            },
+        },
+    };
+    let run = |options: &CopyOptions| {
+        let mut state = CopyState {
+            options,
+            progress: CopyProgress::default(),
+            cancelled: false,
+        };
+        let action = unsafe { on_progress(&message, &mut state as *mut CopyState as *mut _) };
+        (action, state.progress, state.cancelled)
+    };
+    let progress = CopyProgress {
+        copied: 512,
+        total: 2048,
+    };
+
+    let token = CancelToken::new();
+    let options = CopyOptions::default().cancel_token(token.clone());
+    assert_eq!(
+        run(&options),
+        (COPYFILE2_PROGRESS_CONTINUE, progress, false)
+    );
+    token.cancel();
+    assert_eq!(run(&options), (COPYFILE2_PROGRESS_CANCEL, progress, true));
+    let options = options.restartable(true);
+    assert_eq!(run(&options), (COPYFILE2_PROGRESS_STOP, progress, true));
+}
+
+/// Copies a file with `CopyFile2`, for large copies which need to be
+/// cancelled part way through, or resumed after that.
+///
+/// Both paths are canonicalized like `canonicalize` and devices are refused.
+/// An existing `dst` is overwritten. Cancelling isn't an error, it's reported
+/// as `CopyStatus::Cancelled` with how much was copied.
+pub fn copy_file2(
+    src: &str,
+    dst: &str,
+    options: &CopyOptions,
+) -> Result<CopyStatus, CanonicalizeError> {
+    reject_devices(src)?;
+    reject_devices(dst)?;
+    let (src, dst) = canonicalize_pair(src, dst)?;
+    reject_devices(&src)?;
+    reject_devices(&dst)?;
+
+    let mut state = CopyState {
+        options,
+        progress: CopyProgress::default(),
+        cancelled: false,
+    };
+    let mut params = COPYFILE2_EXTENDED_PARAMETERS {
+        dwSize: std::mem::size_of::<COPYFILE2_EXTENDED_PARAMETERS>() as u32,
+        dwCopyFlags: options.flags(),
+        pfCancel: std::ptr::null_mut(),
+        pProgressRoutine: Some(on_progress),
+        pvCallbackContext: &mut state as *mut CopyState as *mut std::ffi::c_void,
+    };
+    match unsafe { CopyFile2(src.as_str(), dst.as_str(), &mut params) } {
+        Ok(()) => Ok(CopyStatus::Completed(state.progress)),
+        Err(e) if state.cancelled && e.code() == HRESULT::from_win32(ERROR_REQUEST_ABORTED) => {
+            Ok(CopyStatus::Cancelled(state.progress))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(windows)]
+#[test]
+fn test_copy_file2() {
+    let dir = std::env::temp_dir().join("win_canonicalize_copy_file2");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let src = dir.join("src.bin");
+    let dst = dir.join("dst.bin");
+    let data: Vec<u8> = (0..8 * 1024 * 1024).map(|i| i as u8).collect();
+    std::fs::write(&src, &data).unwrap();
+
+    let token = CancelToken::new();
+    let options = CopyOptions::default().cancel_token(token.clone());
+    let status = copy_file2(src.to_str().unwrap(), dst.to_str().unwrap(), &options).unwrap();
+    let len = data.len() as u64;
+    assert_eq!(
+        status,
+        CopyStatus::Completed(CopyProgress {
+            copied: len,
+            total: len
+        })
+    );
+    assert!(!token.is_cancelled());
+    assert_eq!(std::fs::read(&dst).unwrap(), data);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
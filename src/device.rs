@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{compare::components, normalize_separators, CanonicalizeError};
+
+static ALLOW_DEVICES: AtomicBool = AtomicBool::new(false);
+
+/// Lets the functions which modify the filesystem operate on device paths
+/// (`\\.\PhysicalDrive0`, `CON`, ...), which they refuse by default.
+pub fn set_allow_devices(allow: bool) {
+    ALLOW_DEVICES.store(allow, Ordering::SeqCst);
+}
+
+/// names Win32 maps onto a device no matter what directory they appear in
+const RESERVED: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9", "CONIN$",
+    "CONOUT$",
+];
+
+/// `C:` or `C:\...`, what follows `\\?\` or `\\.\` in a plain drive path
+pub(crate) fn is_drive_root(rest: &str) -> bool {
+    let b = rest.as_bytes();
+    let drive = b.len() >= 2 && b[0].is_ascii_alphabetic() && b[1] == b':';
+    drive && (b.len() == 2 || b[2] == b'\\')
+}
+
+/// `\\?\C:` or `\\?\UNC\`, the only things a `\\?\` prefix may lead to
+/// without reaching into the NT object namespace (`\\?\GLOBALROOT`, `\\?\PhysicalDrive0`)
+fn is_file_namespace(rest: &str) -> bool {
+    is_drive_root(rest)
+        || rest
+            .get(..4)
+            .is_some_and(|unc| unc.eq_ignore_ascii_case(r#"UNC\"#))
+}
+
+/// Reports if a path lives in the `\\.\` device namespace, is a `\\?\` path
+/// which isn't a drive or UNC path, or names a reserved device (`CON`,
+/// `nul.txt`, `C:\dir\COM1`)
+pub(crate) fn is_device_path(path: &str) -> bool {
+    let path = normalize_separators(path);
+    if path.starts_with(r#"\\.\"#) {
+        return true;
+    }
+    if let Option::Some(rest) = path.strip_prefix(r#"\\?\"#) {
+        if !is_file_namespace(rest) {
+            return true;
+        }
+    }
+    match components(&path).pop() {
+        Option::Some(last) => is_reserved_name(last),
+        Option::None => false,
+    }
+}
+
+/// Reports if a single component names a reserved device, `CON`, `nul.txt`
+pub(crate) fn is_reserved_name(component: &str) -> bool {
+    // the extension and trailing spaces are ignored, `con .txt` is still `CON`
+    let stem = component
+        .split('.')
+        .next()
+        .unwrap_or("")
+        .trim_end_matches(' ');
+    RESERVED.iter().any(|name| name.eq_ignore_ascii_case(stem))
+}
+
+#[test]
+fn test_is_device_path() {
+    assert!(is_device_path(r#"\\.\PhysicalDrive0"#));
+    assert!(is_device_path("//./COM1"));
+    assert!(is_device_path("CON"));
+    assert!(is_device_path("nul.txt"));
+    assert!(is_device_path(r#"C:\dir\com1"#));
+    assert!(is_device_path(r#"C:\dir\Aux .log"#));
+    assert!(is_device_path(r#"\\?\PhysicalDrive0"#));
+    assert!(is_device_path(r#"\\?\GLOBALROOT\Device\HarddiskVolume1\x"#));
+    assert!(is_device_path("//?/pipe/x"));
+    assert!(is_device_path("CONIN$"));
+    assert!(is_device_path(r#"C:\dir\conout$"#));
+
+    assert!(!is_device_path(r#"C:\dir\console.txt"#));
+    assert!(!is_device_path(r#"C:\CON\file.txt"#));
+    assert!(!is_device_path(r#"\\?\C:\dir\file.txt"#));
+    assert!(!is_device_path(r#"\\?\c:"#));
+    assert!(!is_device_path(r#"\\?\UNC\server\share\x"#));
+    assert!(!is_device_path(r#"\\server\share\COM10"#));
+}
+
+/// fails with `CanonicalizeError::DeviceNotAllowed` on a device path,
+/// unless `set_allow_devices(true)` was called.
+///
+/// Callers check the canonical path they hand to Win32 as well as their
+/// input, `C:\dir\CON\.` only becomes `C:\dir\CON` on the way.
+pub(crate) fn reject_devices(path: &str) -> Result<(), CanonicalizeError> {
+    if !ALLOW_DEVICES.load(Ordering::SeqCst) && is_device_path(path) {
+        return Err(CanonicalizeError::DeviceNotAllowed(path.to_string()));
+    }
+    Ok(())
+}
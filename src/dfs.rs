@@ -0,0 +1,136 @@
+use crate::{
+    bindings::Windows::Win32::{
+        Foundation::PWSTR,
+        NetworkManagement::NetManagement::NetApiBufferFree,
+        Storage::DistributedFileSystem::{
+            NetDfsGetClientInfo, DFS_INFO_3, DFS_STORAGE_INFO, DFS_STORAGE_STATE_ACTIVE,
+        },
+    },
+    canonicalize_with, unc_parts, CanonicalizeError, CanonicalizeOptions,
+};
+
+/// Rewrites the `entry` prefix of `path` (matched ignoring case) to
+/// `\\server\share`, `None` if `path` doesn't start with `entry`
+fn splice_dfs_target(path: &str, entry: &str, server: &str, share: &str) -> Option<String> {
+    let prefix = path.get(..entry.len())?;
+    if !prefix.eq_ignore_ascii_case(entry) {
+        return None;
+    }
+    let rest = &path[entry.len()..];
+    // `\\dom\root\link` must not match `\\dom\root\linked`
+    if !rest.is_empty() && !rest.starts_with('\\') && !entry.ends_with('\\') {
+        return None;
+    }
+    Some(format!(r#"\\{}\{}{}"#, server, share, rest))
+}
+
+#[test]
+fn test_splice_dfs_target() {
+    assert_eq!(
+        splice_dfs_target(
+            r#"\\corp\files\Projects\a.txt"#,
+            r#"\\CORP\files\Projects"#,
+            "fs01",
+            "projects$"
+        )
+        .unwrap(),
+        r#"\\fs01\projects$\a.txt"#
+    );
+    assert_eq!(
+        splice_dfs_target(r#"\\corp\files"#, r#"\\corp\files"#, "fs01", "files").unwrap(),
+        r#"\\fs01\files"#
+    );
+    assert_eq!(
+        splice_dfs_target(
+            r#"\\corp\files\ProjectsOld\a"#,
+            r#"\\corp\files\Projects"#,
+            "fs01",
+            "p"
+        ),
+        None
+    );
+    assert_eq!(
+        splice_dfs_target(r#"\\other\x"#, r#"\\corp\files"#, "fs01", "p"),
+        None
+    );
+}
+
+/// reads a NUL terminated wide string Windows owns
+pub(crate) unsafe fn from_pwstr(p: PWSTR) -> Result<String, CanonicalizeError> {
+    if p.0.is_null() {
+        return Ok(String::new());
+    }
+    let mut len = 0;
+    while *p.0.add(len) != 0 {
+        len += 1;
+    }
+    Ok(String::from_utf16(std::slice::from_raw_parts(p.0, len))?)
+}
+
+/// the DFS entry `path` falls under and the server and share it refers to,
+/// preferring the active target. `None` when Windows has no referral for it.
+fn dfs_target(path: &str) -> Result<Option<(String, String, String)>, CanonicalizeError> {
+    let mut buffer: *mut u8 = std::ptr::null_mut();
+    let status = unsafe {
+        NetDfsGetClientInfo(
+            path,
+            PWSTR(std::ptr::null_mut()),
+            PWSTR(std::ptr::null_mut()),
+            3,
+            &mut buffer,
+        )
+    };
+    if status != 0 || buffer.is_null() {
+        return Ok(None);
+    }
+    let result = unsafe {
+        let info = &*(buffer as *const DFS_INFO_3);
+        let storages: &[DFS_STORAGE_INFO] = if info.Storage.is_null() {
+            &[]
+        } else {
+            std::slice::from_raw_parts(info.Storage, info.NumberOfStorages as usize)
+        };
+        match storages
+            .iter()
+            .find(|s| s.State & DFS_STORAGE_STATE_ACTIVE != 0)
+            .or_else(|| storages.first())
+        {
+            Option::None => Ok(None),
+            Option::Some(storage) => Ok(Some((
+                from_pwstr(info.EntryPath)?,
+                from_pwstr(storage.ServerName)?,
+                from_pwstr(storage.ShareName)?,
+            ))),
+        }
+    };
+    unsafe { NetApiBufferFree(buffer as *mut std::ffi::c_void) };
+    result
+}
+
+/// Canonicalizes a UNC path and, if it lies in a DFS namespace, rewrites it
+/// to the server and share the namespace currently refers it to.
+///
+/// Paths which aren't UNC, or which Windows has no DFS referral for (not
+/// DFS, or the namespace can't be reached), are only canonicalized.
+pub fn resolve_dfs(path: &str) -> Result<String, CanonicalizeError> {
+    let canonical = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    if unc_parts(&canonical).is_none() {
+        return Ok(canonical);
+    }
+    match dfs_target(&canonical)? {
+        Option::Some((entry, server, share)) => {
+            Ok(splice_dfs_target(&canonical, &entry, &server, &share).unwrap_or(canonical))
+        }
+        Option::None => Ok(canonical),
+    }
+}
+
+#[cfg(windows)]
+#[test]
+fn test_resolve_dfs_not_dfs() {
+    assert_eq!(
+        resolve_dfs(r#"\\localhost\C$\Windows\..\Users"#).unwrap(),
+        r#"\\localhost\C$\Users"#
+    );
+    assert_eq!(resolve_dfs("/c/Windows/../Users").unwrap(), r#"C:\Users"#);
+}
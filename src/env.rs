@@ -0,0 +1,102 @@
+use crate::{matchers, CanonicalizeError};
+
+/// What `expand_env_vars_with` does with a `%VAR%` which isn't set
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UndefinedVar {
+    /// fail with `CanonicalizeError::UndefinedEnvVar`, an empty substitution
+    /// silently turns `%ROOT%\x` into `\x`
+    #[default]
+    Error,
+    /// leave `%VAR%` in the path exactly as written, like cmd does
+    Literal,
+}
+
+fn expand_with<F>(
+    path: &str,
+    undefined: UndefinedVar,
+    lookup: F,
+) -> Result<String, CanonicalizeError>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut out = String::with_capacity(path.len());
+    let mut last = 0;
+    for (whole, name) in matchers::env_vars(path) {
+        let name = &path[name];
+        out.push_str(&path[last..whole.start]);
+        match (lookup(name), undefined) {
+            (Option::Some(value), _) => out.push_str(&value),
+            (Option::None, UndefinedVar::Literal) => out.push_str(&path[whole.clone()]),
+            (Option::None, UndefinedVar::Error) => {
+                return Err(CanonicalizeError::UndefinedEnvVar(name.to_string()))
+            }
+        }
+        last = whole.end;
+    }
+    out.push_str(&path[last..]);
+    Ok(out)
+}
+
+#[test]
+fn test_expand_with() {
+    let lookup = |name: &str| match name {
+        "ROOT" => Some(r#"C:\root"#.to_string()),
+        "ProgramFiles(x86)" => Some(r#"C:\Program Files (x86)"#.to_string()),
+        _ => None,
+    };
+
+    assert_eq!(
+        expand_with(r#"%ROOT%\x"#, UndefinedVar::Error, lookup).unwrap(),
+        r#"C:\root\x"#
+    );
+    assert_eq!(
+        expand_with(r#"%ProgramFiles(x86)%\app"#, UndefinedVar::Error, lookup).unwrap(),
+        r#"C:\Program Files (x86)\app"#
+    );
+    assert_eq!(
+        expand_with(r#"C:\100%\x"#, UndefinedVar::Error, lookup).unwrap(),
+        r#"C:\100%\x"#
+    );
+
+    match expand_with(r#"%ROOT%\%FOO%\x"#, UndefinedVar::Error, lookup) {
+        Err(CanonicalizeError::UndefinedEnvVar(name)) => assert_eq!(name, "FOO"),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(
+        expand_with(r#"%ROOT%\%FOO%\x"#, UndefinedVar::Literal, lookup).unwrap(),
+        r#"C:\root\%FOO%\x"#
+    );
+}
+
+/// Expands `%VAR%` references from the environment, failing with
+/// `CanonicalizeError::UndefinedEnvVar` on one that isn't set.
+///
+/// This only rewrites the text, pass the result to `canonicalize` as needed.
+pub fn expand_env_vars(path: &str) -> Result<String, CanonicalizeError> {
+    expand_env_vars_with(path, UndefinedVar::default())
+}
+
+/// Expands `%VAR%` references from the environment, `undefined` decides
+/// what happens to one that isn't set
+pub fn expand_env_vars_with(
+    path: &str,
+    undefined: UndefinedVar,
+) -> Result<String, CanonicalizeError> {
+    expand_with(path, undefined, |name| std::env::var(name).ok())
+}
+
+#[test]
+fn test_expand_env_vars() {
+    let name = "WIN_CANONICALIZE_TEST_UNDEFINED";
+    std::env::remove_var(name);
+    let path = format!(r#"%{}%\x"#, name);
+
+    match expand_env_vars(&path) {
+        Err(CanonicalizeError::UndefinedEnvVar(undefined)) => assert_eq!(undefined, name),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(
+        expand_env_vars_with(&path, UndefinedVar::Literal).unwrap(),
+        path
+    );
+}
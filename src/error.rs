@@ -0,0 +1,206 @@
+use std::{env::VarError, fmt, io, string::FromUtf16Error};
+
+use windows::HRESULT;
+
+use crate::bindings::Windows::Win32::{
+    Foundation::PWSTR,
+    System::Diagnostics::Debug::{
+        FormatMessageW, FORMAT_MESSAGE_FROM_SYSTEM, FORMAT_MESSAGE_IGNORE_INSERTS,
+    },
+};
+
+/// Errors which can arise while canonicalizing, or operating on, a path
+#[derive(Debug)]
+pub enum CanonicalizeError {
+    /// COM could not be initialized, carries the `HRESULT` it reported
+    Com(HRESULT),
+    /// A Win32 call failed, carries the `HRESULT` it reported
+    Win32(HRESULT),
+    /// Windows handed back a wide string which is not valid UTF-16
+    Utf16(FromUtf16Error),
+    /// `HOME` could not be read while expanding `~`
+    Home(VarError),
+    /// `OLDPWD` could not be read while expanding `~-`
+    OldPwd(VarError),
+    /// An IO operation from `std` failed
+    Io(io::Error),
+    /// The parent directory of a path about to be created doesn't exist
+    ParentMissing(String),
+    /// Input to `from_file_url` is not a `file:` url, or doesn't decode to UTF-8
+    NotFileUrl(String),
+    /// More symbolic links than `MAX_SYMLINKS` were followed, carries the last one
+    TooManySymlinks(String),
+    /// The path contains a NUL, which Win32 would treat as the end of the string
+    InteriorNul,
+    /// The path contains a control character, rejected by `reject_control_chars`
+    InvalidChar(char),
+    /// A function which modifies the filesystem was handed a device path
+    DeviceNotAllowed(String),
+    /// A single component is longer than `max_component_len`, carries the component
+    ComponentTooLong(String),
+    /// `expand_env_vars` found a `%VAR%` which isn't set, carries its name
+    UndefinedEnvVar(String),
+    /// Input to `canonicalize_bytes` is not WTF-8, carries the offending byte offset
+    InvalidWtf8(usize),
+    /// A member passed to `canonicalize_sandboxed` is absolute or names a drive
+    NotRelative(String),
+    /// `fix_tilde_for_sid` was handed something which isn't a SID string
+    InvalidSid(String),
+    /// `assert_no_dot_dot` found a `..` in the result, carries the result
+    UnresolvedDotDot(String),
+    /// `deny_unc` found a network path, carries the path
+    UncNotAllowed(String),
+    /// `strict` or `canonicalize_sandboxed` found a component naming a reserved
+    /// device, carries the component
+    ReservedName(String),
+    /// `strict` or `canonicalize_sandboxed` found a component ending in `.` or a
+    /// space, which Windows would silently drop, carries the component
+    TrailingDotOrSpace(String),
+    /// `strict` found a character Windows never allows in a name
+    ForbiddenChar(char),
+    /// The input is longer than `max_input_len` UTF-16 code units, carries the limit
+    InputTooLong(usize),
+    /// A drive letter was expected but this isn't one, e.g. `short_names_enabled('1')`
+    InvalidDrive(char),
+}
+
+impl CanonicalizeError {
+    /// The system's (localized) description of the failure, e.g. `Access is denied.`
+    ///
+    /// Only the `Com` and `Win32` variants carry a code Windows can describe,
+    /// everything else is `None`.
+    pub fn win32_message(&self) -> Option<String> {
+        match self {
+            CanonicalizeError::Com(code) | CanonicalizeError::Win32(code) => system_message(*code),
+            _ => None,
+        }
+    }
+}
+
+/// `FormatMessageW` for a system error code, `None` if Windows has no text for it
+fn system_message(code: HRESULT) -> Option<String> {
+    let mut v = vec![0u16; 1024];
+    let len = unsafe {
+        FormatMessageW(
+            FORMAT_MESSAGE_FROM_SYSTEM | FORMAT_MESSAGE_IGNORE_INSERTS,
+            std::ptr::null(),
+            code.0,
+            0,
+            PWSTR(v.as_mut_ptr()),
+            v.len() as u32,
+            std::ptr::null_mut(),
+        )
+    };
+    if len == 0 {
+        return None;
+    }
+    // messages end in a line break
+    let message = String::from_utf16(&v[..len as usize]).ok()?;
+    Some(message.trim_end().to_string())
+}
+
+#[test]
+fn test_win32_message_other_variants() {
+    let e = CanonicalizeError::ParentMissing(r#"C:\missing\"#.to_string());
+    assert_eq!(e.win32_message(), None);
+    assert_eq!(CanonicalizeError::InteriorNul.win32_message(), None);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_win32_message() {
+    // ERROR_ACCESS_DENIED
+    let e = CanonicalizeError::Win32(HRESULT::from_win32(5));
+    let message = e.win32_message().unwrap();
+    assert!(!message.is_empty());
+    assert!(!message.ends_with('\n'));
+}
+
+impl fmt::Display for CanonicalizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CanonicalizeError::Com(code) => write!(f, "failed to initialize com: 0x{:08X}", code.0),
+            CanonicalizeError::Win32(code) => write!(f, "win32 call failed: 0x{:08X}", code.0),
+            CanonicalizeError::Utf16(e) => write!(f, "invalid utf16 from win32: {}", e),
+            CanonicalizeError::Home(e) => write!(f, "cannot expand ~, HOME: {}", e),
+            CanonicalizeError::OldPwd(e) => write!(f, "cannot expand ~-, OLDPWD: {}", e),
+            CanonicalizeError::Io(e) => write!(f, "io error: {}", e),
+            CanonicalizeError::ParentMissing(parent) => {
+                write!(f, "parent directory does not exist: {}", parent)
+            }
+            CanonicalizeError::NotFileUrl(url) => write!(f, "not a file url: {}", url),
+            CanonicalizeError::TooManySymlinks(link) => {
+                write!(f, "too many levels of symbolic links: {}", link)
+            }
+            CanonicalizeError::InteriorNul => write!(f, "path contains an interior nul"),
+            CanonicalizeError::InvalidChar(c) => {
+                write!(f, "path contains a control character: {:?}", c)
+            }
+            CanonicalizeError::DeviceNotAllowed(path) => {
+                write!(f, "refusing to operate on a device: {}", path)
+            }
+            CanonicalizeError::ComponentTooLong(component) => {
+                write!(f, "path component is too long: {}", component)
+            }
+            CanonicalizeError::UndefinedEnvVar(name) => {
+                write!(f, "environment variable is not set: %{}%", name)
+            }
+            CanonicalizeError::InvalidWtf8(offset) => {
+                write!(f, "invalid wtf-8 at byte {}", offset)
+            }
+            CanonicalizeError::NotRelative(member) => {
+                write!(f, "path must be relative to the root: {}", member)
+            }
+            CanonicalizeError::InvalidSid(sid) => write!(f, "not a sid: {}", sid),
+            CanonicalizeError::UnresolvedDotDot(path) => {
+                write!(f, "path still contains `..`: {}", path)
+            }
+            CanonicalizeError::UncNotAllowed(path) => {
+                write!(f, "refusing a network path: {}", path)
+            }
+            CanonicalizeError::ReservedName(name) => {
+                write!(f, "path component is a reserved device name: {}", name)
+            }
+            CanonicalizeError::TrailingDotOrSpace(name) => {
+                write!(f, "path component ends in a dot or space: {:?}", name)
+            }
+            CanonicalizeError::ForbiddenChar(c) => {
+                write!(f, "path contains a forbidden character: {:?}", c)
+            }
+            CanonicalizeError::InputTooLong(max) => {
+                write!(f, "path is longer than {} UTF-16 code units", max)
+            }
+            CanonicalizeError::InvalidDrive(drive) => write!(f, "not a drive letter: {:?}", drive),
+        }
+    }
+}
+
+impl std::error::Error for CanonicalizeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CanonicalizeError::Utf16(e) => Some(e),
+            CanonicalizeError::Home(e) => Some(e),
+            CanonicalizeError::OldPwd(e) => Some(e),
+            CanonicalizeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<windows::Error> for CanonicalizeError {
+    fn from(e: windows::Error) -> Self {
+        CanonicalizeError::Win32(e.code())
+    }
+}
+
+impl From<FromUtf16Error> for CanonicalizeError {
+    fn from(e: FromUtf16Error) -> Self {
+        CanonicalizeError::Utf16(e)
+    }
+}
+
+impl From<io::Error> for CanonicalizeError {
+    fn from(e: io::Error) -> Self {
+        CanonicalizeError::Io(e)
+    }
+}
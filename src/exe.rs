@@ -0,0 +1,47 @@
+use windows::HRESULT;
+
+use crate::{
+    bindings::Windows::Win32::{
+        Foundation::{HINSTANCE, PWSTR},
+        System::LibraryLoader::GetModuleFileNameW,
+    },
+    canonicalize_with,
+    wide::from_wide_nul,
+    CanonicalizeError, CanonicalizeOptions, KIB32,
+};
+
+const ERROR_INSUFFICIENT_BUFFER: u32 = 122;
+
+/// Canonical path of the running executable, e.g. for finding bundled resources.
+///
+/// Reads `GetModuleFileNameW` for the process image, then runs it through
+/// `canonicalize`, so casing and `\\?\` handling match every other path here.
+pub fn current_exe_canonical() -> Result<String, CanonicalizeError> {
+    let mut v = vec![0u16; KIB32];
+    let len =
+        unsafe { GetModuleFileNameW(HINSTANCE::default(), PWSTR(v.as_mut_ptr()), v.len() as u32) };
+    if len == 0 {
+        return Err(CanonicalizeError::Win32(HRESULT::from_thread()));
+    }
+    // a full buffer means the name was truncated
+    if len as usize >= v.len() {
+        return Err(CanonicalizeError::Win32(HRESULT::from_win32(
+            ERROR_INSUFFICIENT_BUFFER,
+        )));
+    }
+    let exe = from_wide_nul(&v[..len as usize])?;
+    canonicalize_with(&exe, &CanonicalizeOptions::default())
+}
+
+#[cfg(windows)]
+#[test]
+fn test_current_exe_canonical() {
+    let exe = current_exe_canonical().unwrap();
+    let std_exe = std::env::current_exe().unwrap();
+    assert!(exe.eq_ignore_ascii_case(std_exe.to_str().unwrap()));
+    assert!(exe.to_lowercase().ends_with(".exe"));
+    assert_eq!(
+        canonicalize_with(&exe, &CanonicalizeOptions::default()).unwrap(),
+        exe
+    );
+}
@@ -0,0 +1,307 @@
+use std::io;
+
+use crate::{
+    canonicalize_with, names::root_len, reparse::app_exec_link_target, CanonicalizeError,
+    CanonicalizeOptions, FsProvider, Win32Fs,
+};
+
+/// How many symbolic links `canonicalize_existing` follows before giving up,
+/// the same limit as POSIX `SYMLOOP_MAX` on Linux
+pub const MAX_SYMLINKS: usize = 40;
+
+/// symbolic links are reparse points too, but `std` already handles those
+#[cfg(windows)]
+fn is_reparse_point(meta: &std::fs::Metadata) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+    meta.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0
+}
+
+#[cfg(not(windows))]
+fn is_reparse_point(_: &std::fs::Metadata) -> bool {
+    false
+}
+
+/// `Some(target)` if `path` is a symbolic link or an app execution alias,
+/// fails if `path` doesn't exist
+pub(crate) fn read_link(path: &str) -> Result<Option<String>, CanonicalizeError> {
+    let meta = std::fs::symlink_metadata(path)?;
+    if !meta.file_type().is_symlink() {
+        if is_reparse_point(&meta) {
+            return app_exec_link_target(path);
+        }
+        return Ok(None);
+    }
+    match std::fs::read_link(path)?.into_os_string().into_string() {
+        Ok(target) => Ok(Some(target)),
+        Err(_) => Err(CanonicalizeError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "symbolic link target is not valid unicode",
+        ))),
+    }
+}
+
+/// Walks a canonical path one component at a time, replacing each symbolic
+/// link with its target and starting over, at most `max` times.
+///
+/// A `..` is only applied once it's reached, when everything before it has
+/// been resolved, so `link\..` is the parent of the link's target (as POSIX
+/// has it) rather than the directory holding the link.
+///
+/// `canon` re-canonicalizes after every substitution and must leave `..` in
+/// place, `read_link` is `Some(target)` for a link and `None` for anything else.
+fn resolve_links<C, R>(
+    path: String,
+    max: usize,
+    canon: C,
+    mut read_link: R,
+) -> Result<String, CanonicalizeError>
+where
+    C: Fn(&str) -> Result<String, CanonicalizeError>,
+    R: FnMut(&str) -> Result<Option<String>, CanonicalizeError>,
+{
+    let mut path = path;
+    let mut followed = 0;
+    'restart: loop {
+        let parts: Vec<&str> = path.split('\\').collect();
+        for i in root_len(&parts)..parts.len() {
+            if parts[i].is_empty() || parts[i] == "." {
+                continue;
+            }
+            if parts[i] == ".." {
+                // the real parent, `..` at the root stays at the root
+                let root = root_len(&parts);
+                let parent = parts[root..i]
+                    .iter()
+                    .rposition(|part| !part.is_empty() && *part != ".")
+                    .map_or(i, |at| root + at);
+                let mut kept = parts[..parent].to_vec();
+                kept.extend_from_slice(&parts[i + 1..]);
+                path = canon(&kept.join("\\"))?;
+                continue 'restart;
+            }
+            let link = parts[..=i].join("\\");
+            let target = match read_link(&link)? {
+                Option::None => continue,
+                Option::Some(target) => target,
+            };
+            followed += 1;
+            if followed > max {
+                return Err(CanonicalizeError::TooManySymlinks(link));
+            }
+
+            let target = if target.starts_with(r#"\\"#) || target.get(1..2) == Some(":") {
+                target
+            } else if target.starts_with('\\') {
+                // rooted on the current drive
+                format!("{}{}", parts[0], target)
+            } else {
+                format!(r#"{}\{}"#, parts[..i].join("\\"), target)
+            };
+            let joined = match parts[i + 1..].join("\\") {
+                rest if rest.is_empty() => target,
+                rest => format!(r#"{}\{}"#, target, rest),
+            };
+            path = canon(&joined)?;
+            continue 'restart;
+        }
+        return Ok(path);
+    }
+}
+
+#[test]
+fn test_resolve_links() {
+    use std::collections::HashMap;
+
+    let canon = |s: &str| Ok(crate::normalize_separators(s).into_owned());
+    let links = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect()
+    };
+    let resolve = |path: &str, links: &HashMap<String, String>| {
+        resolve_links(path.to_string(), MAX_SYMLINKS, canon, |p: &str| {
+            Ok(links.get(p).cloned())
+        })
+    };
+
+    // no links
+    let none = links(&[]);
+    assert_eq!(resolve(r#"C:\a\b"#, &none).unwrap(), r#"C:\a\b"#);
+
+    // absolute, relative, and drive rooted targets
+    let map = links(&[
+        (r#"C:\abs"#, r#"D:\real"#),
+        (r#"C:\x\rel"#, r#"target"#),
+        (r#"C:\x\rooted"#, r#"\top"#),
+        (r#"\\server\share\unc"#, r#"\\other\share"#),
+    ]);
+    assert_eq!(
+        resolve(r#"C:\abs\f.txt"#, &map).unwrap(),
+        r#"D:\real\f.txt"#
+    );
+    assert_eq!(resolve(r#"C:\x\rel\f"#, &map).unwrap(), r#"C:\x\target\f"#);
+    assert_eq!(resolve(r#"C:\x\rooted"#, &map).unwrap(), r#"C:\top"#);
+    assert_eq!(
+        resolve(r#"\\server\share\unc\f"#, &map).unwrap(),
+        r#"\\other\share\f"#
+    );
+
+    // `..` after a link climbs out of its target, not out of the link's directory
+    let map = links(&[(r#"C:\x\link"#, r#"D:\real\sub"#)]);
+    assert_eq!(resolve(r#"C:\x\link\..\f"#, &map).unwrap(), r#"D:\real\f"#);
+    assert_eq!(resolve(r#"C:\x\link\.."#, &map).unwrap(), r#"D:\real"#);
+    assert_eq!(resolve(r#"C:\x\y\..\f"#, &map).unwrap(), r#"C:\x\f"#);
+    assert_eq!(resolve(r#"C:\..\..\f"#, &map).unwrap(), r#"C:\f"#);
+    assert_eq!(
+        resolve(r#"\\server\share\..\f"#, &none).unwrap(),
+        r#"\\server\share\f"#
+    );
+
+    // a chain through several links
+    let chain = links(&[(r#"C:\a"#, r#"C:\b"#), (r#"C:\b"#, r#"C:\c"#)]);
+    assert_eq!(resolve(r#"C:\a\f"#, &chain).unwrap(), r#"C:\c\f"#);
+
+    // a cycle is cut off with the link it was on
+    let cycle = links(&[(r#"C:\a"#, r#"C:\b"#), (r#"C:\b"#, r#"C:\a"#)]);
+    match resolve(r#"C:\a\f"#, &cycle) {
+        Err(CanonicalizeError::TooManySymlinks(_)) => {}
+        other => panic!("expected TooManySymlinks, got {:?}", other),
+    }
+}
+
+/// Canonicalizes a path which must exist, following any symbolic links in it.
+///
+/// App execution aliases (e.g. `python.exe` in `%LOCALAPPDATA%\Microsoft\WindowsApps`)
+/// are followed to the packaged executable they launch, like a symbolic link.
+///
+/// `..` is applied to where the path really is, so `link\..` is the parent of
+/// the link's target like POSIX `realpath`, and the same place
+/// `GetFinalPathNameByHandleW` would report. Each link is resolved before any
+/// `..` after it, which also means every component before a `..` must exist.
+///
+/// At most `MAX_SYMLINKS` links are followed, after that (e.g. a link cycle)
+/// `CanonicalizeError::TooManySymlinks` is returned. A missing component is
+/// reported as `CanonicalizeError::Io`.
+pub fn canonicalize_existing(path: &str) -> Result<String, CanonicalizeError> {
+    canonicalize_existing_with_fs(path, &Win32Fs)
+}
+
+/// Like `canonicalize_existing`, reading links through `fs`
+pub fn canonicalize_existing_with_fs<F>(path: &str, fs: &F) -> Result<String, CanonicalizeError>
+where
+    F: FsProvider + ?Sized,
+{
+    // `..` has to survive until the links before it are resolved
+    let physical = CanonicalizeOptions::default().resolve_dot_segments(false);
+    let canon = |path: &str| canonicalize_with(path, &physical);
+    let resolved = resolve_links(canon(path)?, MAX_SYMLINKS, canon, |link: &str| {
+        fs.read_link(link)
+    })?;
+    canonicalize_with(&resolved, &CanonicalizeOptions::default())
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_existing_with_fs() {
+    let fs = crate::MemoryFs::new()
+        .file(r#"C:\real\sub\f.txt"#)
+        .link(r#"C:\x\link"#, r#"C:\real\sub"#)
+        .link(r#"C:\x\rel"#, r#"link\f.txt"#)
+        .link(r#"C:\loop\a"#, r#"C:\loop\b"#)
+        .link(r#"C:\loop\b"#, r#"C:\loop\a"#);
+
+    assert_eq!(
+        canonicalize_existing_with_fs("/c/x/LINK/./f.txt", &fs).unwrap(),
+        r#"C:\real\sub\f.txt"#
+    );
+    assert_eq!(
+        canonicalize_existing_with_fs(r#"C:\x\rel"#, &fs).unwrap(),
+        r#"C:\real\sub\f.txt"#
+    );
+    assert_eq!(
+        canonicalize_existing_with_fs(r#"C:\x\link\.."#, &fs).unwrap(),
+        r#"C:\real"#
+    );
+    match canonicalize_existing_with_fs(r#"C:\x\link\missing.txt"#, &fs) {
+        Err(CanonicalizeError::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::NotFound),
+        other => panic!("expected Io, got {:?}", other),
+    }
+    match canonicalize_existing_with_fs(r#"C:\loop\a"#, &fs) {
+        Err(CanonicalizeError::TooManySymlinks(_)) => {}
+        other => panic!("expected TooManySymlinks, got {:?}", other),
+    }
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_existing_symlink_loop() {
+    let dir = std::env::temp_dir().join("win_canonicalize_loop");
+    std::fs::create_dir_all(&dir).unwrap();
+    let a = dir.join("a");
+    let b = dir.join("b");
+    let _ = std::fs::remove_file(&a);
+    let _ = std::fs::remove_file(&b);
+
+    // creating symlinks needs developer mode or elevation
+    if std::os::windows::fs::symlink_file(&b, &a).is_err() {
+        return;
+    }
+    std::os::windows::fs::symlink_file(&a, &b).unwrap();
+
+    match canonicalize_existing(a.to_str().unwrap()) {
+        Err(CanonicalizeError::TooManySymlinks(_)) => {}
+        other => panic!("expected TooManySymlinks, got {:?}", other),
+    }
+    std::fs::remove_file(&a).unwrap();
+    std::fs::remove_file(&b).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_existing_dot_dot_through_symlink() {
+    let dir = std::env::temp_dir().join("win_canonicalize_dot_dot");
+    let real = dir.join("real");
+    std::fs::create_dir_all(real.join("sub")).unwrap();
+    std::fs::create_dir_all(dir.join("other")).unwrap();
+    let link = dir.join("other").join("link");
+    let _ = std::fs::remove_dir(&link);
+
+    // creating symlinks needs developer mode or elevation
+    if std::os::windows::fs::symlink_dir(real.join("sub"), &link).is_err() {
+        return;
+    }
+    let through = link.join("..");
+    assert_eq!(
+        canonicalize_existing(through.to_str().unwrap()).unwrap(),
+        canonicalize_with(real.to_str().unwrap(), &CanonicalizeOptions::default()).unwrap()
+    );
+    std::fs::remove_dir(&link).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_existing_app_exec_link() {
+    // only exercised where some app has installed an alias
+    let apps = match std::env::var("LOCALAPPDATA") {
+        Ok(local) => std::path::Path::new(&local).join(r#"Microsoft\WindowsApps"#),
+        Err(_) => return,
+    };
+    let alias = std::fs::read_dir(&apps).ok().and_then(|entries| {
+        entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "exe"))
+    });
+    let alias = match alias {
+        Option::Some(alias) => alias.to_str().unwrap().to_string(),
+        Option::None => return,
+    };
+
+    // e.g. C:\Program Files\WindowsApps\PythonSoftwareFoundation.Python.3.9_...\python3.9.exe
+    let resolved = canonicalize_existing(&alias).unwrap();
+    assert_ne!(resolved.to_lowercase(), alias.to_lowercase());
+    assert!(resolved.to_lowercase().ends_with(".exe"));
+}
@@ -0,0 +1,219 @@
+use windows::HRESULT;
+
+use crate::{
+    bindings::Windows::Win32::{
+        Foundation::HANDLE,
+        Storage::FileSystem::{
+            FindClose, FindFileHandle, FindFirstFileW, FindNextFileW, WIN32_FIND_DATAW,
+        },
+    },
+    canonicalize_pattern, canonicalize_with,
+    wide::from_wide_nul,
+    CanonicalizeError, CanonicalizeOptions, FsEntry, FsProvider, Win32Fs,
+};
+
+const ERROR_FILE_NOT_FOUND: u32 = 2;
+const ERROR_PATH_NOT_FOUND: u32 = 3;
+const ERROR_NO_MORE_FILES: u32 = 18;
+
+/// `FindFirstFileW` on a single path, `None` if it doesn't exist
+pub(crate) fn find_file(path: &str) -> Result<Option<FsEntry>, CanonicalizeError> {
+    let mut data = WIN32_FIND_DATAW::default();
+    let handle = unsafe { FindFirstFileW(path, &mut data) };
+    if handle.0 == -1 {
+        let code = HRESULT::from_thread();
+        if code == HRESULT::from_win32(ERROR_FILE_NOT_FOUND)
+            || code == HRESULT::from_win32(ERROR_PATH_NOT_FOUND)
+        {
+            return Ok(None);
+        }
+        return Err(CanonicalizeError::Win32(code));
+    }
+    unsafe { FindClose(handle) };
+
+    Ok(Some(FsEntry {
+        name: from_wide_nul(&data.cFileName)?,
+        short_name: from_wide_nul(&data.cAlternateFileName)?,
+        attributes: data.dwFileAttributes,
+    }))
+}
+
+/// Iterator over the matches of a `FindFirstFileW` pattern, see `glob`
+pub(crate) struct Glob {
+    dir: String,
+    handle: FindFileHandle,
+    /// the entry found but not yet yielded, `None` once exhausted
+    data: Option<WIN32_FIND_DATAW>,
+}
+
+impl Glob {
+    /// the entry after the current one, `None` when there are no more
+    fn advance(&mut self) -> Result<Option<WIN32_FIND_DATAW>, CanonicalizeError> {
+        let mut data = WIN32_FIND_DATAW::default();
+        if unsafe { FindNextFileW(HANDLE(self.handle.0), &mut data) }.as_bool() {
+            return Ok(Some(data));
+        }
+        let code = HRESULT::from_thread();
+        if code == HRESULT::from_win32(ERROR_NO_MORE_FILES) {
+            return Ok(None);
+        }
+        Err(CanonicalizeError::Win32(code))
+    }
+}
+
+impl Iterator for Glob {
+    type Item = Result<String, CanonicalizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let data = self.data.take()?;
+            self.data = match self.advance() {
+                Ok(next) => next,
+                Err(e) => return Some(Err(e)),
+            };
+            let name = match from_wide_nul(&data.cFileName) {
+                Ok(name) => name,
+                Err(e) => return Some(Err(e)),
+            };
+            if name != "." && name != ".." {
+                return Some(Ok(format!(r#"{}\{}"#, self.dir, name)));
+            }
+        }
+    }
+}
+
+impl Drop for Glob {
+    fn drop(&mut self) {
+        if self.handle.0 != -1 {
+            unsafe { FindClose(self.handle) };
+        }
+    }
+}
+
+/// Expands a single level wildcard (`C:/logs/*.txt`) into the canonical paths
+/// of everything it matches.
+///
+/// Only the last component may hold `*` or `?`, there is no recursive `**`.
+/// A pattern matching nothing yields nothing.
+pub fn glob(
+    pattern: &str,
+) -> Result<impl Iterator<Item = Result<String, CanonicalizeError>>, CanonicalizeError> {
+    let pattern = canonicalize_pattern(pattern)?;
+    let dir = match pattern.rfind('\\') {
+        Option::Some(index) => pattern[..index].to_string(),
+        Option::None => ".".to_string(),
+    };
+    find_matches(&pattern, dir)
+}
+
+/// starts a `Glob` over canonical `pattern`, whose matches are joined onto `dir`
+pub(crate) fn find_matches(pattern: &str, dir: String) -> Result<Glob, CanonicalizeError> {
+    let mut data = WIN32_FIND_DATAW::default();
+    let handle = unsafe { FindFirstFileW(pattern, &mut data) };
+    if handle.0 == -1 {
+        let code = HRESULT::from_thread();
+        if code != HRESULT::from_win32(ERROR_FILE_NOT_FOUND) {
+            return Err(CanonicalizeError::Win32(code));
+        }
+        return Ok(Glob {
+            dir,
+            handle,
+            data: None,
+        });
+    }
+    Ok(Glob {
+        dir,
+        handle,
+        data: Some(data),
+    })
+}
+
+#[cfg(windows)]
+#[test]
+fn test_glob() {
+    let temp = std::env::temp_dir().join("win_canonicalize_glob");
+    let _ = std::fs::remove_dir_all(&temp);
+    std::fs::create_dir_all(&temp).unwrap();
+    for name in ["a.txt", "b.txt", "c.log"].iter() {
+        std::fs::write(temp.join(name), b"").unwrap();
+    }
+    let dir = temp.to_str().unwrap().replace('\\', "/");
+
+    let mut found: Vec<String> = glob(&format!("{}/*.txt", dir))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    found.sort();
+    let expected: Vec<String> = ["a.txt", "b.txt"]
+        .iter()
+        .map(|name| format!(r#"{}\{}"#, temp.to_str().unwrap(), name))
+        .collect();
+    assert_eq!(found, expected);
+
+    assert_eq!(glob(&format!("{}/?.log", dir)).unwrap().count(), 1);
+    assert_eq!(glob(&format!("{}/*.md", dir)).unwrap().count(), 0);
+
+    std::fs::remove_dir_all(&temp).unwrap();
+}
+
+/// Lists the immediate children of a directory as canonical paths, joined
+/// onto the directory's own canonical path. `.` and `..` are skipped.
+///
+/// Entries come back in the order Windows lists them, which isn't sorted on
+/// every filesystem.
+pub fn read_dir(path: &str) -> Result<Vec<String>, CanonicalizeError> {
+    read_dir_with_fs(path, &Win32Fs)
+}
+
+/// Like `read_dir`, listing the directory through `fs`
+pub fn read_dir_with_fs<F>(path: &str, fs: &F) -> Result<Vec<String>, CanonicalizeError>
+where
+    F: FsProvider + ?Sized,
+{
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    let dir = path.trim_end_matches('\\');
+    Ok(fs
+        .list_dir(dir)?
+        .into_iter()
+        .map(|name| format!(r#"{}\{}"#, dir, name))
+        .collect())
+}
+
+#[cfg(windows)]
+#[test]
+fn test_read_dir_with_fs() {
+    let fs = crate::MemoryFs::new()
+        .file(r#"C:\logs\b.txt"#)
+        .file(r#"C:\logs\A.txt"#)
+        .dir(r#"C:\logs\old"#);
+    assert_eq!(
+        read_dir_with_fs("/c/logs/old/../", &fs).unwrap(),
+        [r#"C:\logs\A.txt"#, r#"C:\logs\b.txt"#, r#"C:\logs\old"#]
+    );
+    assert!(read_dir_with_fs(r#"C:\logs\old"#, &fs).unwrap().is_empty());
+    assert!(read_dir_with_fs(r#"C:\missing"#, &fs).is_err());
+}
+
+#[cfg(windows)]
+#[test]
+fn test_read_dir() {
+    let temp = std::env::temp_dir().join("win_canonicalize_read_dir");
+    let _ = std::fs::remove_dir_all(&temp);
+    std::fs::create_dir_all(temp.join("sub")).unwrap();
+    for name in ["a.txt", "B.txt"].iter() {
+        std::fs::write(temp.join(name), b"").unwrap();
+    }
+    let dir = canonicalize_with(temp.to_str().unwrap(), &CanonicalizeOptions::default()).unwrap();
+
+    let mut found = read_dir(&format!("{}/sub/../", dir.replace('\\', "/"))).unwrap();
+    found.sort();
+    let expected: Vec<String> = ["B.txt", "a.txt", "sub"]
+        .iter()
+        .map(|name| format!(r#"{}\{}"#, dir, name))
+        .collect();
+    assert_eq!(found, expected);
+    assert!(read_dir(&format!(r#"{}\sub"#, dir)).unwrap().is_empty());
+    assert!(read_dir(&format!(r#"{}\missing"#, dir)).is_err());
+
+    std::fs::remove_dir_all(&temp).unwrap();
+}
@@ -0,0 +1,483 @@
+use std::{borrow::Cow, path::PathBuf, thread, time::Duration};
+
+use windows::HRESULT;
+
+use crate::{
+    bindings::Windows::Win32::Storage::FileSystem::{
+        CreateDirectoryW, DeleteFileW, MOVE_FILE_FLAGS,
+    },
+    canonicalize_with,
+    device::reject_devices,
+    move_file_ex, move_flags,
+    names::root_len,
+    unc_to_extended, CanonicalizeError, CanonicalizeOptions, FsProvider, Win32Fs, MAX_PATH,
+};
+
+const ERROR_SHARING_VIOLATION: u32 = 32;
+const ERROR_LOCK_VIOLATION: u32 = 33;
+const ERROR_ALREADY_EXISTS: u32 = 183;
+
+/// failures which go away on their own, typically antivirus or the
+/// indexer holding a file open for a moment
+fn is_transient(e: &CanonicalizeError) -> bool {
+    match e {
+        CanonicalizeError::Win32(code) => {
+            *code == HRESULT::from_win32(ERROR_SHARING_VIOLATION)
+                || *code == HRESULT::from_win32(ERROR_LOCK_VIOLATION)
+        }
+        _ => false,
+    }
+}
+
+/// runs `op` up to `attempts` times, sleeping `delay` between
+/// transient failures, any other failure is returned immediately
+fn retry_transient<F>(attempts: usize, delay: Duration, mut op: F) -> Result<(), CanonicalizeError>
+where
+    F: FnMut() -> Result<(), CanonicalizeError>,
+{
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Err(ref e) if attempt < attempts && is_transient(e) => {
+                attempt += 1;
+                thread::sleep(delay);
+            }
+            other => return other,
+        }
+    }
+}
+
+#[test]
+fn test_retry_transient() {
+    let sharing = || CanonicalizeError::Win32(HRESULT::from_win32(ERROR_SHARING_VIOLATION));
+    let access_denied = || CanonicalizeError::Win32(HRESULT::from_win32(5));
+
+    // transient failure which then succeeds
+    let mut calls = 0;
+    let result = retry_transient(3, Duration::from_millis(1), || {
+        calls += 1;
+        if calls < 3 {
+            Err(sharing())
+        } else {
+            Ok(())
+        }
+    });
+    assert!(result.is_ok());
+    assert_eq!(calls, 3);
+
+    // out of attempts, last error is returned
+    let mut calls = 0;
+    let result = retry_transient(2, Duration::from_millis(1), || {
+        calls += 1;
+        Err(sharing())
+    });
+    assert!(is_transient(&result.unwrap_err()));
+    assert_eq!(calls, 2);
+
+    // permanent failures are not retried
+    let mut calls = 0;
+    let result = retry_transient(3, Duration::from_millis(1), || {
+        calls += 1;
+        Err(access_denied())
+    });
+    assert!(!is_transient(&result.unwrap_err()));
+    assert_eq!(calls, 1);
+}
+
+/// Moves a file like `move_file`, retrying sharing and lock violations.
+///
+/// Makes at most `attempts` tries, waiting `delay` between each. Permanent
+/// failures (e.g. access denied) are returned without retrying.
+pub fn move_file_retry(
+    src: &str,
+    dst: &str,
+    flags: MOVE_FILE_FLAGS,
+    attempts: usize,
+    delay: Duration,
+) -> Result<(), CanonicalizeError> {
+    retry_transient(attempts, delay, || move_file_ex(src, dst, flags))
+}
+
+/// the directory a canonical path lives in, keeping the trailing `\`
+/// so a drive root stays `C:\` rather than the drive-relative `C:`
+pub(crate) fn parent_dir(path: &str) -> &str {
+    let trimmed = path.trim_end_matches('\\');
+    match trimmed.rfind('\\') {
+        Option::Some(index) => &trimmed[..=index],
+        Option::None => ".",
+    }
+}
+
+#[test]
+fn test_parent_dir() {
+    assert_eq!(parent_dir(r#"C:\Users\Valarauca"#), r#"C:\Users\"#);
+    assert_eq!(parent_dir(r#"C:\Users\Valarauca\"#), r#"C:\Users\"#);
+    assert_eq!(parent_dir(r#"C:\Users"#), r#"C:\"#);
+    assert_eq!(parent_dir(r#"Users"#), ".");
+}
+
+/// Canonicalizes a path which is about to be created, failing early with
+/// `CanonicalizeError::ParentMissing` when its parent directory doesn't exist.
+pub fn canonicalize_for_create(path: &str) -> Result<String, CanonicalizeError> {
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    let parent = parent_dir(&path);
+    match std::fs::metadata(parent) {
+        Ok(meta) if meta.is_dir() => Ok(path),
+        _ => Err(CanonicalizeError::ParentMissing(parent.to_string())),
+    }
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_for_create() {
+    let temp = std::env::temp_dir();
+    let temp = temp.to_str().unwrap();
+
+    let ok = format!(r#"{}\new_file.txt"#, temp);
+    assert_eq!(
+        canonicalize_for_create(&ok).unwrap(),
+        canonicalize_with(&ok, &CanonicalizeOptions::default()).unwrap()
+    );
+
+    let missing = format!(r#"{}\win_canonicalize_missing\new_file.txt"#, temp);
+    match canonicalize_for_create(&missing) {
+        Err(CanonicalizeError::ParentMissing(parent)) => {
+            assert!(parent.ends_with(r#"\win_canonicalize_missing\"#))
+        }
+        other => panic!("expected ParentMissing, got {:?}", other),
+    }
+}
+
+/// every directory from just below the root down to `path` itself, outermost first
+fn dir_ancestors(path: &str) -> Vec<String> {
+    let parts: Vec<&str> = path.trim_end_matches('\\').split('\\').collect();
+    let root = root_len(&parts);
+    (root..parts.len())
+        .map(|end| parts[..=end].join("\\"))
+        .collect()
+}
+
+#[test]
+fn test_dir_ancestors() {
+    assert_eq!(
+        dir_ancestors(r#"C:\a\b\c\"#),
+        [r#"C:\a"#, r#"C:\a\b"#, r#"C:\a\b\c"#]
+    );
+    assert_eq!(
+        dir_ancestors(r#"\\server\share\a\b"#),
+        [r#"\\server\share\a"#, r#"\\server\share\a\b"#]
+    );
+    assert_eq!(dir_ancestors(r#"\\?\C:\a"#), [r#"\\?\C:\a"#]);
+    assert_eq!(dir_ancestors(r#"a\b"#), ["a", r#"a\b"#]);
+    assert!(dir_ancestors(r#"C:\"#).is_empty());
+    assert!(dir_ancestors(r#"\\server\share"#).is_empty());
+}
+
+/// Lists the directories which would have to be created before `path` could
+/// be, shallowest first, ending with its parent.
+///
+/// Once one directory is missing everything below it is too, so the walk
+/// stops checking at the deepest one which exists.
+pub fn missing_ancestors(path: &str) -> Result<Vec<String>, CanonicalizeError> {
+    missing_ancestors_with_fs(path, &Win32Fs)
+}
+
+/// Like `missing_ancestors`, checking for directories through `fs`
+pub fn missing_ancestors_with_fs<F>(path: &str, fs: &F) -> Result<Vec<String>, CanonicalizeError>
+where
+    F: FsProvider + ?Sized,
+{
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    let mut dirs = dir_ancestors(parent_dir(&path));
+    let mut existing = 0;
+    for (index, dir) in dirs.iter().enumerate().rev() {
+        if fs.entry(dir)?.is_some_and(|entry| entry.is_dir()) {
+            existing = index + 1;
+            break;
+        }
+    }
+    Ok(dirs.split_off(existing))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_missing_ancestors_with_fs() {
+    let fs = crate::MemoryFs::new()
+        .dir(r#"C:\build\out"#)
+        .file(r#"C:\build\log"#);
+    assert_eq!(
+        missing_ancestors_with_fs(r#"C:\build\out\a\b\f.o"#, &fs).unwrap(),
+        [r#"C:\build\out\a"#, r#"C:\build\out\a\b"#]
+    );
+    assert!(missing_ancestors_with_fs("/c/build/out/f.o", &fs)
+        .unwrap()
+        .is_empty());
+    // a file where a directory should be doesn't count
+    assert_eq!(
+        missing_ancestors_with_fs(r#"C:\build\log\f"#, &fs).unwrap(),
+        [r#"C:\build\log"#]
+    );
+}
+
+#[cfg(windows)]
+#[test]
+fn test_missing_ancestors() {
+    let base = std::env::temp_dir().join("win_canonicalize_missing_ancestors");
+    let _ = std::fs::remove_dir_all(&base);
+    std::fs::create_dir_all(base.join("a")).unwrap();
+    let base_str =
+        canonicalize_with(base.to_str().unwrap(), &CanonicalizeOptions::default()).unwrap();
+
+    let target = format!(r#"{}\a\b\c\f.txt"#, base_str);
+    assert_eq!(
+        missing_ancestors(&target).unwrap(),
+        [
+            format!(r#"{}\a\b"#, base_str),
+            format!(r#"{}\a\b\c"#, base_str)
+        ]
+    );
+    assert!(missing_ancestors(&format!(r#"{}\a\f.txt"#, base_str))
+        .unwrap()
+        .is_empty());
+
+    std::fs::remove_dir_all(&base).unwrap();
+}
+
+/// Creates a directory and any missing parents, like `mkdir -p`.
+///
+/// Directories which already exist are skipped, the drive or UNC share root is
+/// never created. A file where a directory should be fails with
+/// `ERROR_ALREADY_EXISTS`.
+pub fn create_dir_all(path: &str) -> Result<(), CanonicalizeError> {
+    reject_devices(path)?;
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    reject_devices(&path)?;
+    create_missing_dirs(&path, &mut Vec::new())
+}
+
+/// creates the missing directories of a canonical `path`, pushing each one
+/// this call made onto `created` so the caller can undo them
+fn create_missing_dirs(path: &str, created: &mut Vec<String>) -> Result<(), CanonicalizeError> {
+    for dir in dir_ancestors(path) {
+        if unsafe { CreateDirectoryW(dir.as_str(), std::ptr::null_mut()) }.as_bool() {
+            created.push(dir);
+        } else {
+            let code = HRESULT::from_thread();
+            if code != HRESULT::from_win32(ERROR_ALREADY_EXISTS) {
+                return Err(CanonicalizeError::Win32(code));
+            }
+            // a file of that name exists too, it just isn't a directory
+            match Win32Fs.entry(&dir)? {
+                Option::Some(entry) if entry.is_dir() => {}
+                _ => return Err(CanonicalizeError::Win32(code)),
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+#[test]
+fn test_create_dir_all() {
+    let base = std::env::temp_dir().join("win_canonicalize_mkdir");
+    let _ = std::fs::remove_dir_all(&base);
+    let deep = base.join("a").join("b").join("c");
+    let deep = deep.to_str().unwrap();
+
+    create_dir_all(deep).unwrap();
+    assert!(std::path::Path::new(deep).is_dir());
+
+    // already existing is a no-op
+    create_dir_all(deep).unwrap();
+    create_dir_all(&format!("{}/../b/./c/", deep)).unwrap();
+    assert!(std::path::Path::new(deep).is_dir());
+
+    // a file in the way isn't a directory which already exists
+    let file = format!(r#"{}\file.txt"#, deep);
+    std::fs::write(&file, b"a").unwrap();
+    let exists = HRESULT::from_win32(ERROR_ALREADY_EXISTS);
+    for path in [file.clone(), format!(r#"{}\x"#, file)].iter() {
+        match create_dir_all(path) {
+            Err(CanonicalizeError::Win32(code)) => assert_eq!(code, exists),
+            other => panic!("unexpected {:?} for {}", other, path),
+        }
+    }
+    assert!(std::path::Path::new(&file).is_file());
+
+    std::fs::remove_dir_all(&base).unwrap();
+}
+
+/// Moves a file like `move_file`, first creating the destination's parent
+/// directory and any of its missing ancestors.
+///
+/// When the move fails the directories this call created are removed again,
+/// innermost first, as long as they are still empty.
+pub fn move_file_mkdirs(src: &str, dst: &str, overwrite: bool) -> Result<(), CanonicalizeError> {
+    reject_devices(dst)?;
+    let dst = canonicalize_with(dst, &CanonicalizeOptions::default())?;
+    reject_devices(&dst)?;
+    let mut created = Vec::new();
+    let result = create_missing_dirs(parent_dir(&dst), &mut created)
+        .and_then(|()| move_file_ex(src, &dst, move_flags(overwrite)));
+    if result.is_err() {
+        for dir in created.iter().rev() {
+            // only succeeds on an empty directory
+            if std::fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+    result
+}
+
+#[cfg(windows)]
+#[test]
+fn test_move_file_mkdirs() {
+    let base = std::env::temp_dir().join("win_canonicalize_move_mkdirs");
+    let _ = std::fs::remove_dir_all(&base);
+    std::fs::create_dir_all(&base).unwrap();
+    let src = base.join("src.txt");
+    std::fs::write(&src, b"move me").unwrap();
+
+    let dst = base.join("a").join("b").join("dst.txt");
+    move_file_mkdirs(src.to_str().unwrap(), dst.to_str().unwrap(), false).unwrap();
+    assert_eq!(std::fs::read(&dst).unwrap(), b"move me");
+    assert!(!src.exists());
+
+    // a failed move leaves no new directories behind
+    let missing = base.join("missing.txt");
+    let dst = base.join("c").join("d").join("dst.txt");
+    assert!(move_file_mkdirs(missing.to_str().unwrap(), dst.to_str().unwrap(), false).is_err());
+    assert!(!base.join("c").exists());
+
+    std::fs::remove_dir_all(&base).unwrap();
+}
+
+/// Deletes a file, after canonicalizing its path.
+///
+/// Device paths are refused with `CanonicalizeError::DeviceNotAllowed`,
+/// see `set_allow_devices`.
+pub fn delete_file(path: &str) -> Result<(), CanonicalizeError> {
+    reject_devices(path)?;
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    reject_devices(&path)?;
+    unsafe { DeleteFileW(path.as_str()).ok()? };
+    Ok(())
+}
+
+#[test]
+fn test_delete_file_device() {
+    for path in [
+        r#"\\.\PhysicalDrive0"#,
+        "CON",
+        r#"C:\dir\nul.txt"#,
+        r#"\\?\PhysicalDrive0"#,
+        r#"\\?\GLOBALROOT\Device\HarddiskVolume1\x"#,
+        "CONIN$",
+        r#"C:\dir\CONOUT$"#,
+    ]
+    .iter()
+    {
+        match delete_file(path) {
+            Err(CanonicalizeError::DeviceNotAllowed(p)) => assert_eq!(&p, path),
+            other => panic!("expected DeviceNotAllowed, got {:?}", other),
+        }
+    }
+}
+
+/// `.` and `..` after a device name only go away while canonicalizing
+#[cfg(windows)]
+#[test]
+fn test_device_after_canonicalize() {
+    let device = |result: Result<(), CanonicalizeError>| match result {
+        Err(CanonicalizeError::DeviceNotAllowed(path)) => assert!(path.ends_with("CON")),
+        other => panic!("expected DeviceNotAllowed, got {:?}", other),
+    };
+    let file = std::env::temp_dir().join("win_canonicalize_device_src.txt");
+    std::fs::write(&file, b"a").unwrap();
+    let file = file.to_str().unwrap();
+
+    for path in [r#"C:\dir\CON\."#, r#"C:\dir\CON\x\.."#].iter() {
+        device(delete_file(path));
+        device(create_dir_all(path));
+        device(move_file_mkdirs(file, path, true));
+        device(crate::move_file_ex(file, path, crate::move_flags(true)));
+        let boxed = |e: Box<dyn std::error::Error>| *e.downcast::<CanonicalizeError>().unwrap();
+        device(crate::move_file(file, path, true).map_err(boxed));
+        device(crate::move_path(file, path, true));
+        device(crate::copy_file2(file, path, &crate::CopyOptions::default()).map(|_| ()));
+    }
+    std::fs::remove_file(file).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn test_delete_file() {
+    let path = std::env::temp_dir().join("win_canonicalize_delete.txt");
+    std::fs::write(&path, b"delete me").unwrap();
+    delete_file(path.to_str().unwrap()).unwrap();
+    assert!(!path.exists());
+}
+
+/// `CreateDirectoryW` keeps 12 units back for an 8.3 file name inside, so a
+/// directory needs the prefix sooner than a file
+const MAX_DIR_PATH: usize = MAX_PATH - 12;
+
+/// adds `\\?\` to an absolute canonical path too long for Win32 without it,
+/// relative paths can't take the prefix and are returned untouched
+fn extend_if_long(path: &str) -> Cow<'_, str> {
+    if path.starts_with(r#"\\?\"#) || path.encode_utf16().count() < MAX_DIR_PATH {
+        return Cow::Borrowed(path);
+    }
+    if path.get(1..3) == Some(r#":\"#) {
+        return Cow::Owned(format!(r#"\\?\{}"#, path));
+    }
+    unc_to_extended(path)
+}
+
+#[test]
+fn test_extend_if_long() {
+    let long = "a".repeat(MAX_PATH);
+    let drive = format!(r#"C:\{}\b.txt"#, long);
+    assert_eq!(extend_if_long(&drive), format!(r#"\\?\{}"#, drive));
+    let unc = format!(r#"\\server\share\{}"#, long);
+    assert_eq!(
+        extend_if_long(&unc),
+        format!(r#"\\?\UNC\server\share\{}"#, long)
+    );
+
+    // short, relative, or already extended
+    assert_eq!(extend_if_long(r#"C:\a\b.txt"#), r#"C:\a\b.txt"#);
+    assert_eq!(extend_if_long(r#"a\b.txt"#), r#"a\b.txt"#);
+    let relative = format!(r#"a\{}"#, long);
+    assert_eq!(extend_if_long(&relative), relative);
+    let rooted = format!(r#"\{}"#, long);
+    assert_eq!(extend_if_long(&rooted), rooted);
+    let extended = format!(r#"\\?\C:\{}"#, long);
+    assert_eq!(extend_if_long(&extended), extended);
+}
+
+/// Canonicalizes a path into a `PathBuf` ready for `std::fs`, with a `\\?\`
+/// prefix added when it's too long for Win32 to take as is.
+///
+/// The prefix is only ever added to absolute paths, it isn't valid on
+/// relative ones, which are returned without it whatever their length.
+pub fn for_std_fs(path: &str) -> Result<PathBuf, CanonicalizeError> {
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    Ok(PathBuf::from(extend_if_long(&path).into_owned()))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_for_std_fs() {
+    let long = format!(r#"C:\{}\{}"#, "a".repeat(200), "b".repeat(100));
+    let expected = format!(r#"\\?\{}"#, long);
+    assert_eq!(
+        for_std_fs(&format!(r#"{}\.\c\.."#, long)).unwrap(),
+        PathBuf::from(expected)
+    );
+    assert_eq!(
+        for_std_fs(r#"a\.\b.txt"#).unwrap(),
+        PathBuf::from(r#"a\b.txt"#)
+    );
+}
@@ -0,0 +1,141 @@
+use std::borrow::Cow;
+
+use windows::HRESULT;
+
+use crate::{
+    bindings::Windows::Win32::{
+        Foundation::{CloseHandle, HANDLE, PWSTR},
+        Storage::FileSystem::{
+            CreateFileW, GetFinalPathNameByHandleW, FILE_FLAGS_AND_ATTRIBUTES,
+            FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OPEN_REPARSE_POINT, FILE_NAME_NORMALIZED,
+            FILE_READ_ATTRIBUTES, FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE,
+            OPEN_EXISTING,
+        },
+    },
+    extended_to_unc,
+    wide::from_wide_nul,
+    CanonicalizeError, KIB32,
+};
+
+const ERROR_INSUFFICIENT_BUFFER: u32 = 122;
+
+/// A handle from `CreateFileW`, closed on drop
+pub(crate) struct OwnedHandle(pub(crate) HANDLE);
+
+impl Drop for OwnedHandle {
+    fn drop(&mut self) {
+        unsafe { CloseHandle(self.0) };
+    }
+}
+
+/// Opens an existing file or directory only to query it, sharing everything
+/// so nobody else is locked out while it is open
+fn open_existing(
+    path: &str,
+    flags: FILE_FLAGS_AND_ATTRIBUTES,
+) -> Result<OwnedHandle, CanonicalizeError> {
+    let handle = unsafe {
+        CreateFileW(
+            path,
+            FILE_READ_ATTRIBUTES,
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
+            std::ptr::null_mut(),
+            OPEN_EXISTING,
+            // required to open directories at all
+            FILE_FLAG_BACKUP_SEMANTICS | flags,
+            HANDLE::default(),
+        )
+    };
+    if handle.0 == -1 {
+        return Err(CanonicalizeError::Win32(HRESULT::from_thread()));
+    }
+    Ok(OwnedHandle(handle))
+}
+
+/// opens whatever a path finally points at, following any reparse points
+pub(crate) fn open_for_attributes(path: &str) -> Result<OwnedHandle, CanonicalizeError> {
+    open_existing(path, FILE_FLAGS_AND_ATTRIBUTES::default())
+}
+
+/// opens a reparse point itself, rather than what it points at
+pub(crate) fn open_reparse_point(path: &str) -> Result<OwnedHandle, CanonicalizeError> {
+    open_existing(path, FILE_FLAG_OPEN_REPARSE_POINT)
+}
+
+/// `\\?\C:\x` -> `C:\x` and `\\?\UNC\server\share` -> `\\server\share`,
+/// anything else (e.g. a `\\?\Volume{...}` path) is left alone
+pub(crate) fn strip_verbatim(path: &str) -> Cow<'_, str> {
+    match path.strip_prefix(r#"\\?\"#) {
+        Option::Some(rest) if rest.get(1..2) == Option::Some(":") => Cow::Borrowed(rest),
+        _ => extended_to_unc(path),
+    }
+}
+
+#[test]
+fn test_strip_verbatim() {
+    assert_eq!(strip_verbatim(r#"\\?\C:\Users\a.txt"#), r#"C:\Users\a.txt"#);
+    assert_eq!(
+        strip_verbatim(r#"\\?\UNC\server\share\a.txt"#),
+        r#"\\server\share\a.txt"#
+    );
+    assert_eq!(
+        strip_verbatim(r#"\\?\Volume{0}\a.txt"#),
+        r#"\\?\Volume{0}\a.txt"#
+    );
+    assert_eq!(strip_verbatim(r#"C:\a.txt"#), r#"C:\a.txt"#);
+}
+
+/// `GetFinalPathNameByHandleW` in the `C:\` style, `\\?\` prefix removed
+// only reachable through the windows-only `RawHandle` entry point
+#[cfg_attr(not(windows), allow(dead_code))]
+fn final_path_name(handle: HANDLE) -> Result<String, CanonicalizeError> {
+    let mut v = vec![0u16; KIB32];
+    // FILE_NAME_NORMALIZED with VOLUME_NAME_DOS (0)
+    let len = unsafe {
+        GetFinalPathNameByHandleW(
+            handle,
+            PWSTR(v.as_mut_ptr()),
+            v.len() as u32,
+            FILE_NAME_NORMALIZED,
+        )
+    };
+    if len == 0 {
+        return Err(CanonicalizeError::Win32(HRESULT::from_thread()));
+    }
+    // too small a buffer is reported as the size it needed
+    if len as usize >= v.len() {
+        return Err(CanonicalizeError::Win32(HRESULT::from_win32(
+            ERROR_INSUFFICIENT_BUFFER,
+        )));
+    }
+    let path = from_wide_nul(&v[..len as usize])?;
+    Ok(strip_verbatim(&path).into_owned())
+}
+
+/// The canonical path of an open file, e.g. from `File::as_raw_handle`.
+///
+/// Asks Windows via `GetFinalPathNameByHandleW`, so the casing is what's on
+/// disk and links are already resolved. The `\\?\` prefix Windows adds is
+/// stripped, `\\?\UNC\server\share` comes back as `\\server\share`.
+#[cfg(windows)]
+pub fn canonical_from_handle(
+    handle: std::os::windows::io::RawHandle,
+) -> Result<String, CanonicalizeError> {
+    final_path_name(HANDLE(handle as isize))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonical_from_handle() {
+    use std::os::windows::io::AsRawHandle;
+
+    let path = std::env::temp_dir().join("win_canonicalize_from_handle.txt");
+    let file = std::fs::File::create(&path).unwrap();
+    let canonical = canonical_from_handle(file.as_raw_handle()).unwrap();
+    drop(file);
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(!canonical.starts_with(r#"\\?\"#));
+    assert_eq!(canonical.get(1..3), Some(r#":\"#));
+    assert!(canonical.ends_with(r#"\win_canonicalize_from_handle.txt"#));
+}
@@ -0,0 +1,49 @@
+use crate::{
+    bindings::Windows::Win32::Storage::FileSystem::{
+        FileIdInfo, GetFileInformationByHandleEx, FILE_ID_INFO,
+    },
+    canonicalize_with,
+    handle::open_for_attributes,
+    CanonicalizeError, CanonicalizeOptions,
+};
+
+/// A stable identifier for a file, which survives renames and is shared by
+/// every hard link to it, e.g. for keying a content-addressed cache.
+///
+/// The volume serial number and the 128-bit file id, kept apart since ReFS
+/// uses every bit of the file id. The file must exist.
+pub fn file_id(path: &str) -> Result<(u64, [u8; 16]), CanonicalizeError> {
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    let handle = open_for_attributes(&path)?;
+    let mut info = FILE_ID_INFO::default();
+    unsafe {
+        GetFileInformationByHandleEx(
+            handle.0,
+            FileIdInfo,
+            &mut info as *mut FILE_ID_INFO as *mut std::ffi::c_void,
+            std::mem::size_of::<FILE_ID_INFO>() as u32,
+        )
+        .ok()?
+    };
+    Ok((info.VolumeSerialNumber, info.FileId.Identifier))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_file_id() {
+    let temp = std::env::temp_dir().join("win_canonicalize_file_id");
+    let _ = std::fs::remove_dir_all(&temp);
+    std::fs::create_dir_all(&temp).unwrap();
+    let a = temp.join("a.txt");
+    let link = temp.join("link.txt");
+    let b = temp.join("b.txt");
+    std::fs::write(&a, b"a").unwrap();
+    std::fs::write(&b, b"b").unwrap();
+    std::fs::hard_link(&a, &link).unwrap();
+
+    let id = |p: &std::path::Path| file_id(p.to_str().unwrap()).unwrap();
+    assert_eq!(id(&a), id(&link));
+    assert_ne!(id(&a), id(&b));
+
+    std::fs::remove_dir_all(&temp).unwrap();
+}
@@ -0,0 +1,92 @@
+use crate::{canonicalize_with, CanonicalizeError, CanonicalizeOptions};
+
+/// Lazily canonicalizes every path an iterator yields, see `CanonicalizeIterExt`
+#[derive(Debug)]
+pub struct CanonicalizePaths<I> {
+    paths: I,
+    options: CanonicalizeOptions,
+}
+
+impl<I> Iterator for CanonicalizePaths<I>
+where
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    type Item = Result<String, CanonicalizeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let path = self.paths.next()?;
+        Some(canonicalize_with(path.as_ref(), &self.options))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.paths.size_hint()
+    }
+}
+
+/// Adds `canonicalize_paths` to any iterator of paths, canonicalizing each
+/// one only as it's pulled, nothing is collected up front.
+///
+/// COM is initialized by the first item pulled on a thread, and every item
+/// after that reuses the same thread local scratch buffer, so a long stream
+/// costs no more per path than a single `canonicalize_with`.
+pub trait CanonicalizeIterExt: Iterator + Sized
+where
+    Self::Item: AsRef<str>,
+{
+    /// canonicalizes each path like `canonicalize`
+    fn canonicalize_paths(self) -> CanonicalizePaths<Self> {
+        self.canonicalize_paths_with(CanonicalizeOptions::default())
+    }
+
+    /// canonicalizes each path like `canonicalize_with`
+    fn canonicalize_paths_with(self, options: CanonicalizeOptions) -> CanonicalizePaths<Self> {
+        CanonicalizePaths {
+            paths: self,
+            options,
+        }
+    }
+}
+
+impl<I> CanonicalizeIterExt for I
+where
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+}
+
+#[test]
+fn test_canonicalize_paths_with() {
+    let paths = vec![
+        "/c/Users/./Valarauca".to_string(),
+        "C:\\a\0b".to_string(),
+        r#"relative\.\path"#.to_string(),
+    ];
+    let canonical: Vec<String> = paths
+        .into_iter()
+        .canonicalize_paths_with(CanonicalizeOptions::default().only_cur_dir(true))
+        .filter_map(Result::ok)
+        .collect();
+    assert_eq!(canonical, [r#"C:\Users\Valarauca"#, r#"relative\path"#]);
+
+    // nothing runs until an item is pulled
+    let mut pulled = 0;
+    let mut lazy = ["a", "b"]
+        .iter()
+        .inspect(|_| pulled += 1)
+        .canonicalize_paths_with(CanonicalizeOptions::default().only_cur_dir(true));
+    assert_eq!(lazy.next().unwrap().unwrap(), "a");
+    drop(lazy);
+    assert_eq!(pulled, 1);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_paths() {
+    let canonical: Vec<String> = vec![r#"C:\a\..\b"#, "/c/Users/./Valarauca"]
+        .into_iter()
+        .canonicalize_paths()
+        .filter_map(Result::ok)
+        .collect();
+    assert_eq!(canonical, [r#"C:\b"#, r#"C:\Users\Valarauca"#]);
+}
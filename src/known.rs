@@ -0,0 +1,85 @@
+use windows::Guid;
+
+use crate::{
+    bindings::Windows::Win32::{
+        Foundation::HANDLE,
+        System::Com::CoTaskMemFree,
+        UI::Shell::{
+            FOLDERID_Desktop, FOLDERID_Documents, FOLDERID_Downloads, FOLDERID_LocalAppData,
+            FOLDERID_Profile, FOLDERID_ProgramFiles, FOLDERID_ProgramFilesX86,
+            FOLDERID_RoamingAppData, FOLDERID_System, FOLDERID_Windows, SHGetKnownFolderPath,
+        },
+    },
+    canonicalize_with,
+    dfs::from_pwstr,
+    CanonicalizeError, CanonicalizeOptions,
+};
+
+/// The folders `known_folder` can look up, each a Windows `KNOWNFOLDERID`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KnownFolder {
+    /// `%USERPROFILE%\Desktop`
+    Desktop,
+    /// `%USERPROFILE%\Documents`
+    Documents,
+    /// `%USERPROFILE%\Downloads`
+    Downloads,
+    /// `%APPDATA%`, settings which roam with the user
+    AppDataRoaming,
+    /// `%LOCALAPPDATA%`, settings which stay on this machine
+    LocalAppData,
+    /// `%USERPROFILE%`
+    Profile,
+    /// `Program Files` matching the bitness of this process
+    ProgramFiles,
+    /// `Program Files (x86)`
+    ProgramFilesX86,
+    /// `%windir%`
+    Windows,
+    /// `%windir%\System32`
+    System,
+}
+
+impl KnownFolder {
+    /// the `KNOWNFOLDERID` Windows knows this folder by
+    fn id(self) -> Guid {
+        match self {
+            KnownFolder::Desktop => FOLDERID_Desktop,
+            KnownFolder::Documents => FOLDERID_Documents,
+            KnownFolder::Downloads => FOLDERID_Downloads,
+            KnownFolder::AppDataRoaming => FOLDERID_RoamingAppData,
+            KnownFolder::LocalAppData => FOLDERID_LocalAppData,
+            KnownFolder::Profile => FOLDERID_Profile,
+            KnownFolder::ProgramFiles => FOLDERID_ProgramFiles,
+            KnownFolder::ProgramFilesX86 => FOLDERID_ProgramFilesX86,
+            KnownFolder::Windows => FOLDERID_Windows,
+            KnownFolder::System => FOLDERID_System,
+        }
+    }
+}
+
+/// Canonical path of a known folder, following any redirection the user or
+/// group policy has set up (e.g. Documents moved onto another drive).
+///
+/// Fails with `CanonicalizeError::Win32` when the folder doesn't exist on
+/// this machine, e.g. `ProgramFilesX86` on 32-bit Windows.
+pub fn known_folder(folder: KnownFolder) -> Result<String, CanonicalizeError> {
+    let id = folder.id();
+    let path = unsafe { SHGetKnownFolderPath(&id, 0, HANDLE::default())? };
+    // the buffer is Windows' to allocate, but ours to free
+    let text = unsafe { from_pwstr(path) };
+    unsafe { CoTaskMemFree(path.0 as *mut _) };
+    canonicalize_with(&text?, &CanonicalizeOptions::default())
+}
+
+#[cfg(windows)]
+#[test]
+fn test_known_folder() {
+    for folder in &[KnownFolder::Documents, KnownFolder::ProgramFiles] {
+        let path = known_folder(*folder).unwrap();
+        assert!(std::path::Path::new(&path).is_dir(), "{:?}", path);
+    }
+    assert!(known_folder(KnownFolder::ProgramFiles)
+        .unwrap()
+        .contains("Program Files"));
+}
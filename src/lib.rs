@@ -1,21 +1,172 @@
 use std::{
     borrow::Cow,
-    sync::{Arc, Mutex},
+    cell::{Cell, RefCell},
+    sync::{Arc, Mutex, RwLock},
 };
 
+#[cfg(not(feature = "lite"))]
 #[macro_use]
 extern crate lazy_static;
-use regex::Regex;
+
+#[cfg(not(any(feature = "lite", all(feature = "regex", feature = "lazy_static"))))]
+compile_error!("either keep the default features or enable `lite`");
+
+#[cfg(not(feature = "lite"))]
+mod re;
+#[cfg(not(feature = "lite"))]
+use re as matchers;
+
+#[cfg(any(feature = "lite", test))]
+mod lite;
+#[cfg(feature = "lite")]
+use lite as matchers;
 
 pub mod bindings {
     windows::include_bindings!();
 }
 
+mod options;
+pub use options::{
+    CanonicalizeOptions, Case, MingwRootPolicy, TrailingSlash, UncCase,
+    DEFAULT_MAX_COMPONENT_LEN, DEFAULT_MAX_INPUT_LEN,
+};
+
+mod unc;
+pub use unc::{extended_to_unc, is_wsl_path, unc_parts, unc_to_extended};
+
+mod error;
+pub use error::CanonicalizeError;
+
+mod volume;
+pub use volume::{
+    disk_space, drive_stability, drives_same_volume, resolve_subst, same_volume,
+    to_volume_guid_path, DiskSpace, DriveKind,
+};
+
+mod winpath;
+pub use winpath::{canonicalize_relative_to_file, WinPath, WinPathRef};
+
+mod wide;
+pub use wide::{canonicalize_bytes, canonicalize_u16};
+
+mod stage;
+
+mod report;
+pub use report::{
+    canonicalize_report, classify_change, preview, CanonicalizePreview, Change, ChangeClass,
+    PreviewStage,
+};
+
+mod compare;
+pub use compare::{
+    contract_tilde, equivalent, is_same_path, starts_with_path, strip_prefix_path,
+};
+
+mod find;
+pub use find::{glob, read_dir, read_dir_with_fs};
+
+mod names;
+pub use names::{
+    expand_short_names, expand_short_names_with_fs, short_names_enabled, to_short_path,
+};
+
+mod fs;
+pub use fs::{
+    canonicalize_for_create, create_dir_all, delete_file, for_std_fs, missing_ancestors,
+    missing_ancestors_with_fs, move_file_mkdirs, move_file_retry,
+};
+
+mod url;
+pub use url::from_file_url;
+
+mod existing;
+pub use existing::{canonicalize_existing, canonicalize_existing_with_fs, MAX_SYMLINKS};
+
+mod provider;
+pub use provider::{FsEntry, FsProvider, MemoryFs, Win32Fs};
+
+mod pattern;
+pub use pattern::canonicalize_pattern;
+
+mod exe;
+pub use exe::current_exe_canonical;
+
+mod set;
+pub use set::PathSet;
+
+mod handle;
+#[cfg(windows)]
+pub use handle::canonical_from_handle;
+
+mod reparse;
+
+mod device;
+pub use device::set_allow_devices;
+
+mod id;
+pub use id::file_id;
+
+mod cmd;
+pub use cmd::expand_cmd_macros;
+
+mod env;
+pub use env::{expand_env_vars, expand_env_vars_with, UndefinedVar};
+
+mod pathlist;
+pub use pathlist::{canonicalize_path_list, canonicalize_path_list_with};
+
+mod sandbox;
+pub use sandbox::canonicalize_sandboxed;
+
+mod profile;
+pub use profile::fix_tilde_for_sid;
+
+mod dfs;
+pub use dfs::resolve_dfs;
+
+mod known;
+pub use known::{known_folder, KnownFolder};
+
+mod components;
+pub use components::from_components;
+
+mod stat;
+pub use stat::{canonicalize_and_stat, FileInfo};
+
+mod strict;
+
+mod copy;
+pub use copy::{copy_file2, CancelToken, CopyOptions, CopyProgress, CopyStatus};
+
+mod iter;
+pub use iter::{CanonicalizeIterExt, CanonicalizePaths};
+
 use bindings::Windows::Win32::{
-    Foundation::PWSTR,
-    System::Com::CoInitialize,
-    UI::Shell::PathCchCanonicalizeEx,
-    Storage::FileSystem::{MoveFileExW,MOVE_FILE_FLAGS},
+    Foundation::{PWSTR, RPC_E_CHANGED_MODE},
+    Globalization::{NormalizeString, NormalizationC},
+    System::Com::{
+        CoInitializeEx, CoUninitialize, COINIT_APARTMENTTHREADED, COINIT_MULTITHREADED,
+    },
+    UI::Shell::{PathCchCanonicalizeEx, PATHCCH_MAX_CCH},
+    Storage::FileSystem::{GetFullPathNameW, MoveFileExW,MOVE_FILE_FLAGS},
+};
+
+/// Size, in UTF-16 code units, of every buffer Windows writes a path into.
+///
+/// 32KiB is the most any path can hold with `PATHCCH_ALLOW_LONG_PATHS`,
+/// so one fixed allocation always fits and no API ever needs retrying.
+pub(crate) const KIB32: usize = 32768;
+
+// a larger buffer would let Win32 hand back paths other APIs can't accept
+const _: () = assert!(KIB32 <= PATHCCH_MAX_CCH as usize);
+
+// callers share these across threads, losing `Send + Sync` should fail the build
+fn assert_send_sync<T: Send + Sync>() {}
+const _: fn() = || {
+    assert_send_sync::<WinPath>();
+    assert_send_sync::<PathSet>();
+    assert_send_sync::<CanonicalizeError>();
+    assert_send_sync::<CanonicalizeOptions>();
 };
 
 /*
@@ -23,21 +174,120 @@ use bindings::Windows::Win32::{
  *
  */
 
-lazy_static! {
-    static ref INIT: Arc<Mutex<bool>> = Arc::new(Mutex::new(false));
-    static ref WIN_ESCAPED_CHAR: Regex = Regex::new(r#"\u{005E}(.)"#).unwrap();
-    static ref ROOTED_MING_W64_COMPAT: Regex = Regex::new(r#"^/([a-zA-Z])/(.*)$"#).unwrap();
-    static ref ROOTED_TILDE_COMPAT: Regex = Regex::new(r#"^(~)(.*)$"#).unwrap();
-    static ref NORMALIZE_SLASH: Regex = Regex::new(r#"([\u{005C}\u{002F}]{1,})"#).unwrap();
+static COM_POLICY: Mutex<ComPolicy> = Mutex::new(ComPolicy::Auto);
+
+/// How this crate treats COM, see `set_com_policy`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ComPolicy {
+    /// initialize each calling thread into a single-threaded apartment, before
+    /// its first Win32 call which may need it
+    #[default]
+    Auto,
+    /// like `Auto`, but join each calling thread to the process-wide
+    /// multithreaded apartment instead
+    MultiThreaded,
+    /// never initialize COM, the host has already initialized COM on
+    /// every thread which calls into this crate
+    AssumeInitialized,
+    /// never initialize COM, for hosts where this crate must not touch
+    /// COM at all, even if that means a Win32 call fails
+    Never,
+}
+
+/// Controls whether this crate initializes COM itself.
+///
+/// Set this once at startup, before any other call into this crate. With
+/// `AssumeInitialized` or `Never` it is up to the host to make sure COM is in
+/// whatever state the threads calling in need, nothing here checks that.
+pub fn set_com_policy(policy: ComPolicy) {
+    *COM_POLICY.lock().unwrap_or_else(|e| e.into_inner()) = policy;
 }
 
-fn co_initialize() -> Result<(), Box<dyn std::error::Error>> {
-    let mut flag = INIT.lock()?;
-    if !*flag {
-        unsafe { CoInitialize(std::ptr::null_mut())? };
-        *flag = true;
+/// This thread's COM initialization, balanced with `CoUninitialize` when the
+/// thread exits
+struct ComApartment {
+    /// `co_initialize` has already run to completion on this thread
+    ready: Cell<bool>,
+    /// successful `CoInitializeEx` calls still owed a `CoUninitialize`
+    inits: Cell<u32>,
+}
+
+impl Drop for ComApartment {
+    fn drop(&mut self) {
+        for _ in 0..self.inits.get() {
+            unsafe { CoUninitialize() };
+        }
+    }
+}
+
+thread_local! {
+    static COM_APARTMENT: ComApartment = const {
+        ComApartment {
+            ready: Cell::new(false),
+            inits: Cell::new(0),
+        }
+    };
+}
+
+/// COM is per thread, so every thread calling in is initialized on its own
+fn co_initialize() -> Result<(), CanonicalizeError> {
+    let apartment = match *COM_POLICY.lock().unwrap_or_else(|e| e.into_inner()) {
+        ComPolicy::Auto => COINIT_APARTMENTTHREADED,
+        ComPolicy::MultiThreaded => COINIT_MULTITHREADED,
+        ComPolicy::AssumeInitialized | ComPolicy::Never => return Ok(()),
+    };
+    COM_APARTMENT.with(|com| {
+        if com.ready.get() {
+            return Ok(());
+        }
+        match unsafe { CoInitializeEx(std::ptr::null_mut(), apartment) } {
+            // S_FALSE (already initialized) also needs its own CoUninitialize
+            Ok(()) => com.inits.set(com.inits.get() + 1),
+            // the host put this thread in the other apartment, COM is usable
+            // but there is nothing of ours to undo
+            Err(e) if e.code() == RPC_E_CHANGED_MODE => {}
+            Err(e) => return Err(CanonicalizeError::Com(e.code())),
+        }
+        com.ready.set(true);
+        Ok(())
+    })
+}
+
+/// held by tests which change, or depend on, the process wide `COM_POLICY`
+#[cfg(test)]
+static COM_POLICY_TEST: Mutex<()> = Mutex::new(());
+
+#[test]
+fn test_com_policy_assume_initialized() {
+    let _policy = COM_POLICY_TEST.lock().unwrap_or_else(|e| e.into_inner());
+    set_com_policy(ComPolicy::AssumeInitialized);
+    // `CoInitializeEx` panics off windows, so getting here at all means it was skipped
+    let result = co_initialize();
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    let path = canonicalize_with("/c/a/./b", &options);
+    set_com_policy(ComPolicy::Auto);
+
+    assert!(result.is_ok());
+    assert_eq!(path.unwrap(), r#"C:\a\b"#);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_co_initialize_threads() {
+    let _policy = COM_POLICY_TEST.lock().unwrap_or_else(|e| e.into_inner());
+    let threads: Vec<_> = (0..4)
+        .map(|i| {
+            std::thread::spawn(move || {
+                let path = format!("/c/threads/{}/../x", i);
+                let canonical = canonicalize(&path).unwrap();
+                COM_APARTMENT.with(|com| assert!(com.ready.get()));
+                canonical
+            })
+        })
+        .collect();
+    for thread in threads {
+        assert_eq!(thread.join().unwrap(), r#"C:\threads\x"#);
     }
-    Ok(())
 }
 
 /*
@@ -74,17 +324,14 @@ impl<'a> ToCow<'a> for &'a Cow<'_, str> {
     }
 }
 
-fn win_escape_char<'a,T>(arg: T) -> Result<Cow<'a,str>,Box<dyn std::error::Error>>
+fn win_escape_char<'a,T>(arg: T) -> Result<Cow<'a,str>,CanonicalizeError>
 where
     T: ToCow<'a>,
 {
     let cow = <T as ToCow>::to_cow(arg);
-    if WIN_ESCAPED_CHAR.is_match(cow.as_ref()) {
-        Ok(WIN_ESCAPED_CHAR.replace_all(cow.as_ref(), "$1")
-            .to_string()
-            .to_cow())
-    } else {
-        Ok(cow)
+    match matchers::unescape_caret(cow.as_ref()) {
+        Option::Some(unescaped) => Ok(unescaped.to_cow()),
+        Option::None => Ok(cow),
     }
 }
 
@@ -96,18 +343,46 @@ fn test_win_escape_char() {
     );
 }
 
-fn fix_root<'a, T>(arg: T) -> Result<Cow<'a, str>, Box<dyn std::error::Error>>
+/// Undoes bash style quoting, `\ ` is a literal space and `\\` a single `\`.
+///
+/// Any other `\` is left alone as a separator.
+fn unescape_shell<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
 where
     T: ToCow<'a>,
 {
     let cow = <T as ToCow>::to_cow(arg);
-    match ROOTED_MING_W64_COMPAT.captures(&cow) {
+    match matchers::unescape_backslash(cow.as_ref()) {
+        Option::Some(unescaped) => Ok(unescaped.to_cow()),
         Option::None => Ok(cow),
-        Option::Some(caps) => {
-            let drive_letter = caps.get(1).unwrap().as_str().to_uppercase();
-            let rest = caps.get(2).unwrap().as_str();
-            Ok(Cow::Owned(format!(r#"{}:\{}"#, drive_letter, rest)))
-        }
+    }
+}
+
+#[test]
+fn test_unescape_shell() {
+    assert_eq!(
+        unescape_shell(r#"/c/Program\ Files/App"#).unwrap(),
+        "/c/Program Files/App"
+    );
+    assert_eq!(unescape_shell(r#"a\\b"#).unwrap(), r#"a\b"#);
+    assert_eq!(unescape_shell(r#"a\\\ b"#).unwrap(), r#"a\ b"#);
+    assert_eq!(
+        unescape_shell(r#"C:\Users\Valarauca"#).unwrap(),
+        r#"C:\Users\Valarauca"#
+    );
+}
+
+fn fix_root<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    let cow = <T as ToCow>::to_cow(arg);
+    match matchers::mingw_root(&cow) {
+        Option::None => Ok(cow),
+        Option::Some((drive_letter, rest)) => Ok(Cow::Owned(format!(
+            r#"{}:\{}"#,
+            drive_letter.to_ascii_uppercase(),
+            rest
+        ))),
     }
 }
 
@@ -164,22 +439,118 @@ fn test_fix_root() {
     );
 }
 
-fn fix_tilde<'a, T>(arg: T) -> Result<Cow<'a, str>, Box<dyn std::error::Error>>
+/// `fix_root`, but only when `policy` allows it
+fn apply_mingw_root<'a, T>(
+    arg: T,
+    policy: MingwRootPolicy,
+) -> Result<Cow<'a, str>, CanonicalizeError>
 where
     T: ToCow<'a>,
 {
     let cow = <T as ToCow>::to_cow(arg);
-    if ROOTED_TILDE_COMPAT.is_match(cow.as_ref()) {
-        let home = std::env::var("HOME")?;
-        Ok(ROOTED_TILDE_COMPAT
-            .replace_all(&cow, format!("{}$2", home))
-            .to_string()
-            .to_cow())
+    let rewrite = match policy {
+        MingwRootPolicy::AlwaysDrive => true,
+        MingwRootPolicy::OnlyIfDriveExists => match matchers::mingw_root(&cow) {
+            Option::None => false,
+            Option::Some((drive_letter, _)) => volume::drive_exists(drive_letter),
+        },
+        MingwRootPolicy::Never => false,
+    };
+    if rewrite {
+        fix_root(cow)
     } else {
         Ok(cow)
     }
 }
 
+#[test]
+fn test_canonicalize_with_mingw_root() {
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    assert_eq!(canonicalize_with("/h/foo", &options).unwrap(), r#"H:\foo"#);
+
+    let options = options.mingw_root(MingwRootPolicy::Never);
+    assert_eq!(canonicalize_with("/h/foo", &options).unwrap(), r#"\h\foo"#);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_with_mingw_root_drive_exists() {
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .mingw_root(MingwRootPolicy::OnlyIfDriveExists);
+    let expected = if volume::drive_exists('h') {
+        r#"H:\foo"#
+    } else {
+        r#"\h\foo"#
+    };
+    assert_eq!(canonicalize_with("/h/foo", &options).unwrap(), expected);
+
+    let windows = std::env::var("SystemRoot").unwrap();
+    let drive = windows[..1].to_lowercase();
+    assert_eq!(
+        canonicalize_with(&format!("/{}/foo", drive), &options).unwrap(),
+        format!(r#"{}:\foo"#, drive.to_uppercase())
+    );
+}
+
+/// `~` and `~\rest` -> `HOME`, `~name\rest` -> the `name` directory next to
+/// `HOME`, like bash's `~user`. A `~name` with no such directory is left alone,
+/// `~$doc.docx` and the like are ordinary file names.
+fn fix_tilde<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    let cow = <T as ToCow>::to_cow(arg);
+    let rest = match matchers::rooted_tilde(&cow) {
+        Option::Some(rest) => rest,
+        Option::None => return Ok(cow),
+    };
+    match tilde_home(rest)? {
+        Option::Some((dir, name_len)) => Ok(format!("{}{}", dir, &rest[name_len..]).to_cow()),
+        Option::None => Ok(cow),
+    }
+}
+
+/// the directory a leading `~` or `~name` stands for, given what follows the
+/// `~`, and the length of the name. `None` when it's a file name instead.
+///
+/// `HOME` must be set for a bare `~`, a `~name` without it is a file name.
+fn tilde_home(rest: &str) -> Result<Option<(String, usize)>, CanonicalizeError> {
+    let is_sep = |c: char| c == '\\' || c == '/';
+    let name_len = rest.find(is_sep).unwrap_or(rest.len());
+    if name_len == 0 {
+        let home = std::env::var("HOME").map_err(CanonicalizeError::Home)?;
+        return Ok(Some((home, 0)));
+    }
+    let dir = std::env::var("HOME")
+        .ok()
+        .and_then(|home| user_home(&home, &rest[..name_len]));
+    Ok(dir.map(|dir| (dir, name_len)))
+}
+
+/// `path` starts with a `~` or `~name` which `fix_tilde` would expand
+pub(crate) fn is_home_tilde(path: &str) -> bool {
+    match matchers::rooted_tilde(path) {
+        Option::Some(rest) => !matches!(tilde_home(rest), Ok(None)),
+        Option::None => false,
+    }
+}
+
+/// the existing directory `name` beside `home`, where other users' profiles are
+fn user_home(home: &str, name: &str) -> Option<String> {
+    if name == "." || name == ".." || name.contains(':') {
+        return None;
+    }
+    let is_sep = |c: char| c == '\\' || c == '/';
+    let home = home.trim_end_matches(is_sep);
+    let dir = format!("{}{}", &home[..=home.rfind(is_sep)?], name);
+    if std::path::Path::new(&dir).is_dir() {
+        Some(dir)
+    } else {
+        None
+    }
+}
+
 #[test]
 fn test_fix_tilde() {
     // test cases which should be uneffected
@@ -206,18 +577,174 @@ fn test_fix_tilde() {
     );
 }
 
-fn normalize_slash<'a, T>(arg: T) -> Result<Cow<'a, str>, Box<dyn std::error::Error>>
+#[test]
+fn test_fix_tilde_user() {
+    let home = std::env::var("HOME").unwrap();
+    assert_eq!(fix_tilde("~").unwrap(), home);
+    assert_eq!(fix_tilde("~/x").unwrap(), format!("{}/x", home));
+    assert_eq!(fix_tilde(r#"~\x"#).unwrap(), format!(r#"{}\x"#, home));
+
+    // `~name` is the profile directory beside `HOME`, which includes our own
+    let trimmed = home.trim_end_matches(['\\', '/']);
+    let user = trimmed.rsplit(['\\', '/']).next().unwrap();
+    assert_eq!(
+        fix_tilde(format!(r#"~{}\x"#, user)).unwrap(),
+        format!(r#"{}\x"#, trimmed)
+    );
+
+    // no such user, so it is a file name
+    assert_eq!(
+        fix_tilde("~randomtext-4f1c9a/x").unwrap(),
+        "~randomtext-4f1c9a/x"
+    );
+    assert_eq!(fix_tilde("~$report.docx").unwrap(), "~$report.docx");
+    assert_eq!(fix_tilde("~..").unwrap(), "~..");
+
+    assert!(is_home_tilde("~"));
+    assert!(is_home_tilde(r#"~\x"#));
+    assert!(is_home_tilde(&format!("~{}/x", user)));
+    assert!(!is_home_tilde("~$report.docx"));
+    assert!(!is_home_tilde("a~"));
+}
+
+/// Expands a `~` which is a whole component after a separator (`C:\a\~\b`)
+/// to `HOME`, a `~` anywhere else (`PROGRA~1`, `~$x.docx`) is part of a name
+/// and left alone
+fn fix_tilde_anywhere<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
 where
     T: ToCow<'a>,
 {
     let cow = <T as ToCow>::to_cow(arg);
-    if NORMALIZE_SLASH.is_match(cow.as_ref()) {
-        Ok(NORMALIZE_SLASH
-            .replace_all(&cow, r#"\"#)
-            .to_string()
-            .to_cow())
-    } else {
-        Ok(cow)
+    let tildes = matchers::separated_tildes(&cow);
+    if tildes.is_empty() {
+        return Ok(cow);
+    }
+    let home = std::env::var("HOME").map_err(CanonicalizeError::Home)?;
+    let mut out = String::with_capacity(cow.len() + tildes.len() * home.len());
+    let mut last = 0;
+    for tilde in tildes {
+        out.push_str(&cow[last..tilde]);
+        out.push_str(&home);
+        last = tilde + 1;
+    }
+    out.push_str(&cow[last..]);
+    Ok(out.to_cow())
+}
+
+#[test]
+fn test_fix_tilde_anywhere() {
+    let home = std::env::var("HOME").unwrap();
+    assert_eq!(
+        fix_tilde_anywhere(r#"C:\a\~\b"#).unwrap(),
+        format!(r#"C:\a\{}\b"#, home)
+    );
+    assert_eq!(
+        fix_tilde_anywhere("$ROOT/~").unwrap(),
+        format!("$ROOT/{}", home)
+    );
+    assert_eq!(fix_tilde_anywhere("$ROOT/~cache").unwrap(), "$ROOT/~cache");
+    assert_eq!(
+        fix_tilde_anywhere(r#"C:\docs\~$x.docx"#).unwrap(),
+        r#"C:\docs\~$x.docx"#
+    );
+    assert_eq!(
+        fix_tilde_anywhere(r#"C:\PROGRA~1\a~b"#).unwrap(),
+        r#"C:\PROGRA~1\a~b"#
+    );
+    assert_eq!(fix_tilde_anywhere("~/a").unwrap(), "~/a");
+}
+
+/// `~+` -> current directory, `~-` -> `OLDPWD`, like bash
+fn fix_tilde_dirs<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    fix_tilde_dirs_with(arg, |sign| {
+        if sign == '+' {
+            Ok(std::env::current_dir()?.to_string_lossy().to_string())
+        } else {
+            std::env::var("OLDPWD").map_err(CanonicalizeError::OldPwd)
+        }
+    })
+}
+
+/// `fix_tilde_dirs` with `lookup` handing back the directory for `+` or `-`
+fn fix_tilde_dirs_with<'a, T, F>(arg: T, lookup: F) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+    F: FnOnce(char) -> Result<String, CanonicalizeError>,
+{
+    let cow = <T as ToCow>::to_cow(arg);
+    match matchers::tilde_dir(&cow) {
+        Option::None => Ok(cow),
+        Option::Some((sign, rest)) => Ok(Cow::Owned(format!("{}{}", lookup(sign)?, rest))),
+    }
+}
+
+#[test]
+fn test_fix_tilde_dirs() {
+    let cwd = std::env::current_dir().unwrap();
+    let cwd = cwd.to_string_lossy();
+    assert_eq!(fix_tilde_dirs(r#"~+/sub"#).unwrap(), format!("{}/sub", cwd));
+    assert_eq!(fix_tilde_dirs(r#"~+"#).unwrap(), cwd);
+}
+
+#[test]
+fn test_fix_tilde_dirs_with() {
+    let lookup = |sign: char| match sign {
+        '+' => Ok(r#"C:\Users\valarauca\cur"#.to_string()),
+        _ => Ok(r#"C:\Users\valarauca\old"#.to_string()),
+    };
+
+    // test cases which should be uneffected
+    assert_eq!(fix_tilde_dirs_with(r#"~/sub"#, lookup).unwrap(), r#"~/sub"#);
+    assert_eq!(fix_tilde_dirs_with(r#"~+sub"#, lookup).unwrap(), r#"~+sub"#);
+    assert_eq!(
+        fix_tilde_dirs_with(r#"C:\~+\sub"#, lookup).unwrap(),
+        r#"C:\~+\sub"#
+    );
+
+    assert_eq!(
+        fix_tilde_dirs_with(r#"~+/sub"#, lookup).unwrap(),
+        r#"C:\Users\valarauca\cur/sub"#
+    );
+    assert_eq!(
+        fix_tilde_dirs_with(r#"~+"#, lookup).unwrap(),
+        r#"C:\Users\valarauca\cur"#
+    );
+    assert_eq!(
+        fix_tilde_dirs_with(r#"~-/sub"#, lookup).unwrap(),
+        r#"C:\Users\valarauca\old/sub"#
+    );
+    assert_eq!(
+        fix_tilde_dirs_with(r#"~-\sub"#, lookup).unwrap(),
+        r#"C:\Users\valarauca\old\sub"#
+    );
+
+    let unset = |_| Err(CanonicalizeError::OldPwd(std::env::VarError::NotPresent));
+    match fix_tilde_dirs_with(r#"~-\sub"#, unset) {
+        Err(CanonicalizeError::OldPwd(std::env::VarError::NotPresent)) => {}
+        other => panic!("expected OldPwd, got {:?}", other),
+    }
+    // nothing to expand, nothing looked up
+    assert_eq!(
+        fix_tilde_dirs_with(r#"C:\sub"#, unset).unwrap(),
+        r#"C:\sub"#
+    );
+}
+
+fn normalize_slash<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    let cow = <T as ToCow>::to_cow(arg);
+    match matchers::collapse_separators(&cow) {
+        // a leading `\\` is a UNC or device prefix, not a redundant separator
+        Option::Some(normalized) if matchers::double_separator_prefix(&cow) => {
+            Ok(format!(r#"\{}"#, normalized).to_cow())
+        }
+        Option::Some(normalized) => Ok(normalized.to_cow()),
+        Option::None => Ok(cow),
     }
 }
 
@@ -244,100 +771,1756 @@ fn test_normalize_slash() {
     );
 }
 
-fn path_cch_canonicalize_ex<'a, T>(arg: T) -> Result<Cow<'a, str>, Box<dyn std::error::Error>>
+#[test]
+fn test_normalize_slash_unc() {
+    assert_eq!(
+        normalize_slash(r#"\\server\share"#).unwrap(),
+        r#"\\server\share"#
+    );
+    assert_eq!(
+        normalize_slash(r#"//server//share/"#).unwrap(),
+        r#"\\server\share\"#
+    );
+    assert_eq!(
+        normalize_slash(r#"\\\server\share"#).unwrap(),
+        r#"\\server\share"#
+    );
+    assert_eq!(normalize_slash(r#"\\?\C:\a//b"#).unwrap(), r#"\\?\C:\a\b"#);
+}
+
+/// Converts `/` to `\` and collapses runs of separators, keeping a leading
+/// `\\` UNC or device prefix. No drive, tilde, or Win32 processing is done,
+/// so this is safe on relative fragments.
+pub fn normalize_separators(path: &str) -> Cow<'_, str> {
+    // normalize_slash has no failure case of its own
+    normalize_slash(path).unwrap_or(Cow::Borrowed(path))
+}
+
+#[test]
+fn test_normalize_separators() {
+    assert_eq!(normalize_separators(r#"a//b\\c"#), r#"a\b\c"#);
+    assert_eq!(normalize_separators(r#"a\b"#), r#"a\b"#);
+    assert_eq!(normalize_separators(r#"..//a/./b"#), r#"..\a\.\b"#);
+    assert_eq!(
+        normalize_separators(r#"//server///share/a"#),
+        r#"\\server\share\a"#
+    );
+}
+
+/// anything Windows resolves against a working directory, `a\b`, rooted `\a`
+/// (the current drive), and drive relative `C:a` (C:'s own)
+fn is_relative(path: &str) -> bool {
+    !path.starts_with(r#"\\"#) && path.get(1..3) != Some(r#":\"#)
+}
+
+#[test]
+fn test_is_relative() {
+    assert!(is_relative(r#"a\b"#));
+    assert!(is_relative(r#"\a\b"#));
+    assert!(is_relative(r#"C:foo\bar"#));
+    assert!(is_relative("C:"));
+    assert!(!is_relative(r#"C:\foo\bar"#));
+    assert!(!is_relative(r#"\\server\share"#));
+    assert!(!is_relative(r#"\\?\C:\a"#));
+}
+
+/// makes a relative path absolute with `GetFullPathNameW`, which tracks a
+/// working directory per drive, so `C:a` lands under C:'s and not the process's
+fn resolve_relative<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
 where
     T: ToCow<'a>,
 {
-    co_initialize()?;
-
     let cow = <T as ToCow>::to_cow(arg);
-
-    // 32KiB is totally unreasonable for a path length
-    #[allow(non_snake_case)]
-    let KiB32 = 32768usize;
-    let mut v = Vec::<u16>::with_capacity(KiB32);
-    for _ in 0..KiB32 {
-        v.push(0u16);
+    if cow.is_empty() || !is_relative(&cow) {
+        return Ok(cow);
     }
 
-    unsafe { PathCchCanonicalizeEx(PWSTR(v.as_mut_ptr()), KiB32, cow.as_ref(), 1)? };
-
-    let mut length = 0usize;
-    for index in 0..KiB32 {
-        if v[index] == 0 {
-            break;
-        }
-        length += 1;
+    let mut v = vec![0u16; KIB32];
+    let len = unsafe {
+        GetFullPathNameW(
+            cow.as_ref(),
+            v.len() as u32,
+            PWSTR(v.as_mut_ptr()),
+            std::ptr::null_mut(),
+        )
+    };
+    if len == 0 {
+        return Err(CanonicalizeError::Win32(windows::HRESULT::from_thread()));
+    }
+    if len as usize >= v.len() {
+        return Err(CanonicalizeError::Win32(windows::HRESULT::from_win32(
+            ERROR_INSUFFICIENT_BUFFER,
+        )));
     }
-    Ok(String::from_utf16(&v.as_slice()[0..length])?.to_cow())
+    Ok(String::from_utf16(&v[..len as usize])?.to_cow())
 }
 
 #[test]
-fn test_path_cch_canonicalize_ex() {
+fn test_canonicalize_with_drive_relative() {
+    // resolve off: still drive relative, only the separators change
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
     assert_eq!(
-        path_cch_canonicalize_ex(r#"C:\Users\Valarauca\Documents\"#).unwrap(),
-        r#"C:\Users\Valarauca\Documents\"#
+        canonicalize_with("C:foo/bar", &options).unwrap(),
+        r#"C:foo\bar"#
     );
     assert_eq!(
-        path_cch_canonicalize_ex(r#"C:\Users\Valarauca\Documents\..\..\"#).unwrap(),
-        r#"C:\Users\"#
+        canonicalize_with("C:foo/./bar//", &options).unwrap(),
+        r#"C:foo\bar\"#
     );
 }
 
-/// This canonicalizes a path, if the path in question exists or not
-///
-/// Will handle some -oddities- of cygwin, mingw, and windows shell
-pub fn canonicalize(path: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let a = fix_root(path)?;
-    let b = fix_tilde(a)?;
-    let c = normalize_slash(b)?;
-    let d = path_cch_canonicalize_ex(c)?;
-    Ok(d.to_string())
-}
-
+#[cfg(windows)]
 #[test]
-fn assert_matches() {
+fn test_canonicalize_with_resolve_relative() {
+    let cwd = std::env::current_dir().unwrap();
+    let cwd = cwd.to_str().unwrap().trim_end_matches('\\');
+    let drive = &cwd[..1];
+    let options = CanonicalizeOptions::default().resolve_relative(true);
+
+    // the current drive's working directory is the process's
     assert_eq!(
-        canonicalize("~/Documents/").unwrap(),
-        r#"C:\Users\valarauca\Documents\"#
+        canonicalize_with(&format!("{}:foo/bar", drive), &options).unwrap(),
+        format!(r#"{}\foo\bar"#, cwd)
+    );
+    assert_eq!(
+        canonicalize_with("foo/../bar", &options).unwrap(),
+        format!(r#"{}\bar"#, cwd)
+    );
+    assert_eq!(
+        canonicalize_with(r#"C:\foo/bar"#, &options).unwrap(),
+        r#"C:\foo\bar"#
     );
-    assert_eq!(canonicalize("/f/Downloads/").unwrap(), r#"F:\Downloads\"#);
-    assert_eq!(canonicalize("/f/Downloads/../").unwrap(), r#"F:\"#);
 }
 
-/// moves file
-fn priv_move_file<'a,A,B>(
-    src: A,
-    dst: B,
-    overwrite_okay: bool) -> Result<(),Box<dyn std::error::Error>>
+/// Composes the path text into Unicode NFC, the form Windows itself stores
+/// names in. Purely textual, nothing is looked up on disk.
+fn normalize_nfc<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
 where
-    A: ToCow<'a>,
-    B: ToCow<'a>,
+    T: ToCow<'a>,
 {
-    co_initialize()?;
-
-    let src_value = <A as ToCow>::to_cow(src);
-    let dst_value = <B as ToCow>::to_cow(dst);
+    let cow = <T as ToCow>::to_cow(arg);
+    // ascii is already in every normal form
+    if cow.is_ascii() {
+        return Ok(cow);
+    }
 
-    // see: https://docs.microsoft.com/en-us/windows/win32/api/winbase/nf-winbase-movefileexa
-    let mut flags = 0u32;
-    if overwrite_okay {
-        flags += 1u32;
+    let mut input: Vec<u16> = cow.encode_utf16().collect();
+    let src = PWSTR(input.as_mut_ptr());
+    let src_len = input.len() as i32;
+    let estimate =
+        unsafe { NormalizeString(NormalizationC, src, src_len, PWSTR(std::ptr::null_mut()), 0) };
+    if estimate <= 0 {
+        return Err(CanonicalizeError::Win32(windows::HRESULT::from_thread()));
     }
-    // ensure copy occurs before flushing
-    flags += 0u32;
-    // allow for copy + delete when needed
-    flags += 2u32;
-    unsafe {
-        MoveFileExW(
-            src_value.as_ref(),
-            dst_value.as_ref(),
-            MOVE_FILE_FLAGS(flags)).ok()?;
+    let mut v = vec![0u16; estimate as usize];
+    let len = unsafe {
+        NormalizeString(
+            NormalizationC,
+            src,
+            src_len,
+            PWSTR(v.as_mut_ptr()),
+            estimate,
+        )
+    };
+    if len <= 0 {
+        return Err(CanonicalizeError::Win32(windows::HRESULT::from_thread()));
     }
-    Ok(())
+    Ok(String::from_utf16(&v[..len as usize])?.to_cow())
+}
+
+#[test]
+fn test_normalize_nfc_ascii() {
+    // never reaches Win32
+    assert_eq!(normalize_nfc(r#"C:\a\b"#).unwrap(), r#"C:\a\b"#);
+}
+
+/// drops a leading byte order mark, left behind by reading a path out of a
+/// file saved as UTF-8 with BOM
+fn strip_bom<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    let cow = <T as ToCow>::to_cow(arg);
+    Ok(match cow {
+        Cow::Borrowed(s) => Cow::Borrowed(s.strip_prefix('\u{FEFF}').unwrap_or(s)),
+        Cow::Owned(s) => match s.strip_prefix('\u{FEFF}') {
+            Option::Some(rest) => Cow::Owned(rest.to_string()),
+            Option::None => Cow::Owned(s),
+        },
+    })
+}
+
+#[test]
+fn test_strip_bom() {
+    assert_eq!(strip_bom("\u{feff}C:\\a").unwrap(), r#"C:\a"#);
+    assert_eq!(strip_bom(r#"C:\a"#).unwrap(), r#"C:\a"#);
+    // only one, and only at the start
+    assert_eq!(strip_bom("\u{feff}\u{feff}a").unwrap(), "\u{feff}a");
+    assert_eq!(strip_bom("a\u{feff}").unwrap(), "a\u{feff}");
+}
+
+/// trims whitespace from both ends
+fn trim_input<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    let cow = <T as ToCow>::to_cow(arg);
+    Ok(match cow {
+        Cow::Borrowed(s) => Cow::Borrowed(s.trim()),
+        Cow::Owned(s) if s.trim().len() == s.len() => Cow::Owned(s),
+        Cow::Owned(s) => Cow::Owned(s.trim().to_string()),
+    })
+}
+
+#[test]
+fn test_trim_input() {
+    assert_eq!(trim_input("  C:\\a b \t\n").unwrap(), "C:\\a b");
+    assert_eq!(trim_input(r#"C:\a"#).unwrap(), r#"C:\a"#);
+}
+
+#[test]
+fn test_canonicalize_with_bom_and_padding() {
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    assert_eq!(
+        canonicalize_with("\u{feff}/c/Users/./Valarauca", &options).unwrap(),
+        r#"C:\Users\Valarauca"#
+    );
+    // padding is kept unless asked for, a leading space is a valid name
+    assert_eq!(
+        canonicalize_with("  a\\.\\b", &options).unwrap(),
+        r#"  a\b"#
+    );
+
+    let options = options.trim_input(true);
+    assert_eq!(
+        canonicalize_with("  /c/Users/./Valarauca \t", &options).unwrap(),
+        r#"C:\Users\Valarauca"#
+    );
+    assert_eq!(
+        canonicalize_with("\u{feff} /c/Users ", &options).unwrap(),
+        r#"C:\Users"#
+    );
+}
+
+/// maps the full-width forms of ASCII (`U+FF01`-`U+FF5E`) down to ASCII,
+/// the ASCII-only slice of what NFKC would do
+fn narrow_fullwidth<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    let cow = <T as ToCow>::to_cow(arg);
+    let is_fullwidth = |c: char| ('\u{FF01}'..='\u{FF5E}').contains(&c);
+    if !cow.chars().any(is_fullwidth) {
+        return Ok(cow);
+    }
+    Ok(Cow::Owned(
+        cow.chars()
+            .map(|c| {
+                if is_fullwidth(c) {
+                    std::char::from_u32(c as u32 - 0xFF01 + 0x21).unwrap()
+                } else {
+                    c
+                }
+            })
+            .collect(),
+    ))
+}
+
+#[test]
+fn test_narrow_fullwidth() {
+    assert_eq!(
+        narrow_fullwidth("\u{ff23}\u{ff1a}\u{ff3c}Users").unwrap(),
+        r#"C:\Users"#
+    );
+    assert_eq!(
+        narrow_fullwidth("\u{ff0f}\u{ff43}\u{ff0f}a").unwrap(),
+        "/c/a"
+    );
+    // only the ASCII range, other full-width forms and CJK text are untouched
+    assert_eq!(
+        narrow_fullwidth("C:\\\u{65e5}\u{672c}\u{ffe5}").unwrap(),
+        "C:\\\u{65e5}\u{672c}\u{ffe5}"
+    );
+}
+
+#[cfg(windows)]
+#[test]
+fn test_normalize_nfc() {
+    let composed = "C:\\caf\u{e9}";
+    let decomposed = "C:\\cafe\u{301}";
+    assert_eq!(normalize_nfc(composed).unwrap(), composed);
+    assert_eq!(normalize_nfc(decomposed).unwrap(), composed);
+}
+
+/// Drops `.` segments from a `\` separated path, leaving `..` alone. The
+/// root is never touched, the `.` of `\\.\` is part of the device prefix.
+fn strip_cur_dir<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    let cow = <T as ToCow>::to_cow(arg);
+    if !cow.split('\\').any(|seg| seg == ".") {
+        return Ok(cow);
+    }
+
+    let segments: Vec<&str> = cow.split('\\').collect();
+    let root = names::root_len(&segments);
+    let last = segments.len() - 1;
+    let mut kept = Vec::with_capacity(segments.len());
+    for (index, seg) in segments.into_iter().enumerate() {
+        if index < root || seg != "." {
+            kept.push(seg);
+        } else if index == last {
+            // `C:\a\.` still names the directory `C:\a\`
+            kept.push("");
+        }
+    }
+    if kept.iter().all(|seg| seg.is_empty()) {
+        return Ok(".".to_cow());
+    }
+    // `.\c:notes` is a file, `c:notes` would be relative to the drive
+    let mut first = kept[0].chars();
+    if cow.starts_with(".\\")
+        && first.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && first.next() == Some(':')
+    {
+        kept.insert(0, ".");
+    }
+    Ok(kept.join("\\").to_cow())
+}
+
+#[test]
+fn test_strip_cur_dir() {
+    // test cases which should be uneffected
+    assert_eq!(
+        strip_cur_dir(r#"C:\Users\Valarauca\..\Documents\"#).unwrap(),
+        r#"C:\Users\Valarauca\..\Documents\"#
+    );
+
+    assert_eq!(strip_cur_dir(r#".\a\.\b"#).unwrap(), r#"a\b"#);
+    assert_eq!(strip_cur_dir(r#"C:\a\.\b\..\c"#).unwrap(), r#"C:\a\b\..\c"#);
+    assert_eq!(strip_cur_dir(r#"C:\a\."#).unwrap(), r#"C:\a\"#);
+    assert_eq!(strip_cur_dir(r#".\"#).unwrap(), r#"."#);
+    assert_eq!(strip_cur_dir(r#".\.\c:notes"#).unwrap(), r#".\c:notes"#);
+
+    // the device prefix stays a device prefix, not a UNC path
+    assert_eq!(
+        strip_cur_dir(r#"\\.\PhysicalDrive0"#).unwrap(),
+        r#"\\.\PhysicalDrive0"#
+    );
+    assert_eq!(strip_cur_dir(r#"\\.\pipe\.\x"#).unwrap(), r#"\\.\pipe\x"#);
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    assert_eq!(
+        canonicalize_with(r#"\\.\PhysicalDrive0"#, &options).unwrap(),
+        r#"\\.\PhysicalDrive0"#
+    );
+}
+
+thread_local! {
+    /// reused by `path_cch_canonicalize_in_place`, holds the terminated input
+    /// followed by room for the output
+    static SCRATCH: RefCell<Vec<u16>> = const { RefCell::new(Vec::new()) };
+}
+
+/// the scratch buffer is shrunk back to this many code units after a long path,
+/// rather than holding on to `KIB32` for the life of the thread
+const SCRATCH_KEEP: usize = 2048;
+
+/// room `PathCchCanonicalizeEx` may need beyond the input, e.g. for `\\?\UNC\`
+const OUTPUT_SLACK: usize = 16;
+
+const ERROR_INSUFFICIENT_BUFFER: u32 = 122;
+
+/// `PathCchCanonicalizeEx` over a raw wide string (no terminator), `path` is
+/// replaced with exactly the code units Windows wrote back.
+///
+/// Works in a thread local scratch buffer, so once that and `path` have grown
+/// to fit, repeated calls don't allocate.
+fn path_cch_canonicalize_in_place(path: &mut Vec<u16>) -> Result<(), CanonicalizeError> {
+    // Windows would stop reading at the first NUL and silently drop the rest
+    if path.contains(&0u16) {
+        return Err(CanonicalizeError::InteriorNul);
+    }
+    co_initialize()?;
+
+    SCRATCH.with(|scratch| {
+        let mut scratch = scratch.borrow_mut();
+        let cch = (path.len() + OUTPUT_SLACK).min(KIB32);
+        let result = match path_cch_scratch(&mut scratch, path, cch) {
+            Err(e) if e.code() == windows::HRESULT::from_win32(ERROR_INSUFFICIENT_BUFFER) => {
+                path_cch_scratch(&mut scratch, path, KIB32)
+            }
+            result => result,
+        };
+        if scratch.capacity() > SCRATCH_KEEP {
+            scratch.clear();
+            scratch.shrink_to(SCRATCH_KEEP);
+        }
+        result?;
+        strip_leading_cur_dir(path);
+        Ok(())
+    })
+}
+
+/// drops any leading `.\` a relative path still has after
+/// `PathCchCanonicalizeEx`, `.\src\main.rs` -> `src\main.rs`, without
+/// allocating. A path which was nothing but `.\` is left as `.`, and one `.\`
+/// stays in front of a `c:notes` file, which would otherwise name a drive
+fn strip_leading_cur_dir(path: &mut Vec<u16>) {
+    const CUR_DIR: [u16; 2] = ['.' as u16, '\\' as u16];
+    let mut skip = 0;
+    while path[skip..].starts_with(&CUR_DIR) {
+        skip += CUR_DIR.len();
+    }
+    let is_letter = |c: u16| c < 0x80 && (c as u8).is_ascii_alphabetic();
+    if let [letter, colon, ..] = path[skip..] {
+        if skip > 0 && is_letter(letter) && colon == ':' as u16 {
+            skip -= CUR_DIR.len();
+        }
+    }
+    if skip > 0 && skip == path.len() {
+        path.truncate(1);
+    } else {
+        path.drain(..skip);
+    }
+}
+
+#[test]
+fn test_strip_leading_cur_dir() {
+    let strip = |path: &str| {
+        let mut wide: Vec<u16> = path.encode_utf16().collect();
+        strip_leading_cur_dir(&mut wide);
+        String::from_utf16(&wide).unwrap()
+    };
+    assert_eq!(strip(r#".\src\main.rs"#), r#"src\main.rs"#);
+    assert_eq!(strip(r#".\.\a\b"#), r#"a\b"#);
+    assert_eq!(strip(r#".\"#), ".");
+    assert_eq!(strip("."), ".");
+    assert_eq!(strip(""), "");
+    // only an exact `.` segment
+    assert_eq!(strip(r#"..\a"#), r#"..\a"#);
+    assert_eq!(strip(r#".git\config"#), r#".git\config"#);
+    assert_eq!(strip(r#"C:\a\.\b"#), r#"C:\a\.\b"#);
+    // without the `.\` these would be drive relative
+    assert_eq!(strip(r#".\c:notes"#), r#".\c:notes"#);
+    assert_eq!(strip(r#".\.\c:notes\a"#), r#".\c:notes\a"#);
+    assert_eq!(strip(r#".\c:"#), r#".\c:"#);
+    assert_eq!(strip(r#".\cd:x"#), r#"cd:x"#);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_leading_cur_dir() {
+    let options = CanonicalizeOptions::default();
+    assert_eq!(
+        canonicalize_with(r#".\src\main.rs"#, &options).unwrap(),
+        r#"src\main.rs"#
+    );
+    assert_eq!(canonicalize_with("./a/b", &options).unwrap(), r#"a\b"#);
+}
+
+#[test]
+fn test_canonicalize_with_leading_cur_dir() {
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    assert_eq!(
+        canonicalize_with(r#".\src\main.rs"#, &options).unwrap(),
+        r#"src\main.rs"#
+    );
+    assert_eq!(canonicalize_with("./a/b", &options).unwrap(), r#"a\b"#);
+}
+
+/// one `PathCchCanonicalizeEx` call with `cch` units of output space in `scratch`
+fn path_cch_scratch(
+    scratch: &mut Vec<u16>,
+    path: &mut Vec<u16>,
+    cch: usize,
+) -> windows::Result<()> {
+    scratch.clear();
+    scratch.extend_from_slice(path);
+    scratch.push(0u16);
+    let split = scratch.len();
+    scratch.resize(split + cch, 0u16);
+    let (input, output) = scratch.split_at_mut(split);
+    unsafe {
+        PathCchCanonicalizeEx(
+            PWSTR(output.as_mut_ptr()),
+            cch,
+            PWSTR(input.as_mut_ptr()),
+            1,
+        )?
+    };
+
+    let length = output.iter().position(|&c| c == 0).unwrap_or(cch);
+    path.clear();
+    path.extend_from_slice(&output[..length]);
+    Ok(())
+}
+
+/// `path_cch_canonicalize_in_place` into a new buffer
+fn path_cch_canonicalize_wide(input: &[u16]) -> Result<Vec<u16>, CanonicalizeError> {
+    let mut v = input.to_vec();
+    path_cch_canonicalize_in_place(&mut v)?;
+    Ok(v)
+}
+
+#[cfg(windows)]
+#[test]
+fn test_path_cch_canonicalize_long() {
+    // longer than `SCRATCH_KEEP`, which shouldn't stay allocated afterwards
+    let long = format!(r#"C:\{}\..\a"#, "b".repeat(SCRATCH_KEEP * 2));
+    let wide: Vec<u16> = long.encode_utf16().collect();
+    let canonical = path_cch_canonicalize_wide(&wide).unwrap();
+    assert_eq!(String::from_utf16(&canonical).unwrap(), r#"C:\a"#);
+    SCRATCH.with(|scratch| assert!(scratch.borrow().capacity() <= SCRATCH_KEEP));
+}
+
+fn path_cch_canonicalize_ex<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    let cow = <T as ToCow>::to_cow(arg);
+    let wide: Vec<u16> = cow.encode_utf16().collect();
+    let v = path_cch_canonicalize_wide(&wide)?;
+    Ok(String::from_utf16(&v)?.to_cow())
+}
+
+#[test]
+fn test_path_cch_canonicalize_ex() {
+    assert_eq!(
+        path_cch_canonicalize_ex(r#"C:\Users\Valarauca\Documents\"#).unwrap(),
+        r#"C:\Users\Valarauca\Documents\"#
+    );
+    assert_eq!(
+        path_cch_canonicalize_ex(r#"C:\Users\Valarauca\Documents\..\..\"#).unwrap(),
+        r#"C:\Users\"#
+    );
+}
+
+/// the longest path, counting its NUL, Win32 takes without a `\\?\` prefix
+pub(crate) const MAX_PATH: usize = 260;
+
+/// drops a `\\?\C:\` or `\\?\UNC\` prefix, but only when what's left still
+/// fits in `MAX_PATH`
+fn strip_extended_when_short<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    let cow = <T as ToCow>::to_cow(arg);
+    let stripped = handle::strip_verbatim(&cow);
+    if stripped.len() == cow.len() || stripped.encode_utf16().count() >= MAX_PATH {
+        return Ok(cow);
+    }
+    Ok(Cow::Owned(stripped.into_owned()))
+}
+
+#[test]
+fn test_strip_extended_when_short() {
+    assert_eq!(
+        strip_extended_when_short(r#"\\?\C:\Users\a.txt"#).unwrap(),
+        r#"C:\Users\a.txt"#
+    );
+    assert_eq!(
+        strip_extended_when_short(r#"\\?\UNC\server\share\a.txt"#).unwrap(),
+        r#"\\server\share\a.txt"#
+    );
+    assert_eq!(
+        strip_extended_when_short(r#"C:\Users\a.txt"#).unwrap(),
+        r#"C:\Users\a.txt"#
+    );
+
+    // 259 units is the most which fits, with room for the NUL
+    let fits = format!(r#"C:\{}"#, "a".repeat(MAX_PATH - 4));
+    assert_eq!(
+        strip_extended_when_short(format!(r#"\\?\{}"#, fits)).unwrap(),
+        fits
+    );
+    let long = format!(r#"\\?\C:\{}"#, "a".repeat(MAX_PATH - 3));
+    assert_eq!(strip_extended_when_short(long.as_str()).unwrap(), long);
+    let long = format!(r#"\\?\UNC\server\share\{}"#, "a".repeat(300));
+    assert_eq!(strip_extended_when_short(long.as_str()).unwrap(), long);
+
+    // a volume GUID path has no short form to fall back to
+    assert_eq!(
+        strip_extended_when_short(r#"\\?\Volume{0}\a.txt"#).unwrap(),
+        r#"\\?\Volume{0}\a.txt"#
+    );
+}
+
+/// drops a trailing `::$DATA` (any case), which names the default stream
+fn strip_default_stream<'a, T>(arg: T) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    const DEFAULT_STREAM: &str = "::$DATA";
+    let cow = <T as ToCow>::to_cow(arg);
+    let at = match cow.len().checked_sub(DEFAULT_STREAM.len()) {
+        Option::Some(at) if cow.is_char_boundary(at) => at,
+        _ => return Ok(cow),
+    };
+    if !cow[at..].eq_ignore_ascii_case(DEFAULT_STREAM) {
+        return Ok(cow);
+    }
+    Ok(match cow {
+        Cow::Borrowed(s) => Cow::Borrowed(&s[..at]),
+        Cow::Owned(mut s) => {
+            s.truncate(at);
+            Cow::Owned(s)
+        }
+    })
+}
+
+#[test]
+fn test_strip_default_stream() {
+    assert_eq!(
+        strip_default_stream(r#"C:\a\x.txt::$DATA"#).unwrap(),
+        r#"C:\a\x.txt"#
+    );
+    assert_eq!(
+        strip_default_stream(r#"C:\a\x.txt::$data"#).unwrap(),
+        r#"C:\a\x.txt"#
+    );
+    // named streams are kept
+    assert_eq!(
+        strip_default_stream(r#"C:\a\x.txt:notes:$DATA"#).unwrap(),
+        r#"C:\a\x.txt:notes:$DATA"#
+    );
+    assert_eq!(
+        strip_default_stream(r#"C:\a\x.txt:notes"#).unwrap(),
+        r#"C:\a\x.txt:notes"#
+    );
+    assert_eq!(
+        strip_default_stream(r#"C:\a\x.txt"#).unwrap(),
+        r#"C:\a\x.txt"#
+    );
+    assert_eq!(
+        strip_default_stream("\u{e9}::$DAT").unwrap(),
+        "\u{e9}::$DAT"
+    );
+}
+
+/// adds or strips the trailing separator per `TrailingSlash`
+fn apply_trailing_slash<'a, T>(
+    arg: T,
+    trailing_slash: TrailingSlash,
+) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    let cow = <T as ToCow>::to_cow(arg);
+    match trailing_slash {
+        TrailingSlash::Preserve => Ok(cow),
+        TrailingSlash::Always if cow.is_empty() || cow.ends_with('\\') => Ok(cow),
+        TrailingSlash::Always => Ok(format!(r#"{}\"#, cow).to_cow()),
+        TrailingSlash::Never => {
+            let trimmed = cow.trim_end_matches('\\');
+            // `C:\` and `\` are roots, without the separator they mean something else
+            if trimmed.len() == cow.len() || trimmed.is_empty() || trimmed.ends_with(':') {
+                Ok(cow)
+            } else {
+                Ok(trimmed.to_string().to_cow())
+            }
+        }
+    }
+}
+
+#[test]
+fn test_apply_trailing_slash() {
+    let always = |s| apply_trailing_slash(s, TrailingSlash::Always).unwrap();
+    let never = |s| apply_trailing_slash(s, TrailingSlash::Never).unwrap();
+
+    assert_eq!(
+        apply_trailing_slash(r#"C:\a"#, TrailingSlash::Preserve).unwrap(),
+        r#"C:\a"#
+    );
+    assert_eq!(
+        apply_trailing_slash(r#"C:\a\"#, TrailingSlash::Preserve).unwrap(),
+        r#"C:\a\"#
+    );
+
+    assert_eq!(always(r#"C:\a"#), r#"C:\a\"#);
+    assert_eq!(always(r#"C:\a\"#), r#"C:\a\"#);
+    assert_eq!(always(r#"\\server\share"#), r#"\\server\share\"#);
+
+    assert_eq!(never(r#"C:\a\"#), r#"C:\a"#);
+    assert_eq!(never(r#"C:\a"#), r#"C:\a"#);
+    assert_eq!(never(r#"C:\"#), r#"C:\"#);
+    assert_eq!(never(r#"\"#), r#"\"#);
+    assert_eq!(never(r#"\\?\C:\"#), r#"\\?\C:\"#);
+    assert_eq!(never(r#"\\server\share\"#), r#"\\server\share"#);
+}
+
+/// rewrites the casing of the whole path per `Case`
+fn apply_case<'a, T>(arg: T, case: Case) -> Result<Cow<'a, str>, CanonicalizeError>
+where
+    T: ToCow<'a>,
+{
+    let cow = <T as ToCow>::to_cow(arg);
+    match case {
+        Case::AsIs => Ok(cow),
+        Case::LowerAll => Ok(cow.to_lowercase().to_cow()),
+        Case::UpperAll => Ok(cow.to_uppercase().to_cow()),
+        Case::UpperDrive => {
+            // the drive may sit behind a `\\?\` or `\\.\` prefix
+            let at = if cow.starts_with(r#"\\?\"#) || cow.starts_with(r#"\\.\"#) {
+                4
+            } else {
+                0
+            };
+            match cow.as_bytes().get(at..at + 2) {
+                Option::Some([letter, b':']) if letter.is_ascii_lowercase() => {
+                    let mut upper = cow.into_owned();
+                    upper[at..at + 1].make_ascii_uppercase();
+                    Ok(upper.to_cow())
+                }
+                _ => Ok(cow),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_apply_case() {
+    let path = r#"c:\Über\straße\ǅ"#;
+    assert_eq!(apply_case(path, Case::AsIs).unwrap(), path);
+    assert_eq!(
+        apply_case(path, Case::UpperDrive).unwrap(),
+        r#"C:\Über\straße\ǅ"#
+    );
+    assert_eq!(
+        apply_case(path, Case::LowerAll).unwrap(),
+        r#"c:\über\straße\ǆ"#
+    );
+    assert_eq!(
+        apply_case(path, Case::UpperAll).unwrap(),
+        r#"C:\ÜBER\STRASSE\Ǆ"#
+    );
+    assert_eq!(
+        apply_case(r#"\\?\d:\Ä"#, Case::UpperDrive).unwrap(),
+        r#"\\?\D:\Ä"#
+    );
+    assert_eq!(
+        apply_case(r#"\\server\Ä"#, Case::UpperDrive).unwrap(),
+        r#"\\server\Ä"#
+    );
+}
+
+/// wide string APIs treat a NUL as the end of the path, so anything after
+/// it would be silently ignored
+fn reject_interior_nul(path: &str) -> Result<(), CanonicalizeError> {
+    if path.contains('\0') {
+        return Err(CanonicalizeError::InteriorNul);
+    }
+    Ok(())
+}
+
+/// control characters (`0x01`-`0x1F`) have no business in a path, and
+/// can be used to forge log lines or confuse tools further downstream
+fn reject_control_chars(path: &str) -> Result<(), CanonicalizeError> {
+    match path.chars().find(|c| ('\u{1}'..='\u{1F}').contains(c)) {
+        Option::Some(c) => Err(CanonicalizeError::InvalidChar(c)),
+        Option::None => Ok(()),
+    }
+}
+
+/// the limit is in UTF-16 code units, so a character outside the BMP counts twice
+fn reject_long_components(path: &str, max: usize) -> Result<(), CanonicalizeError> {
+    match path
+        .split('\\')
+        .find(|component| component.encode_utf16().count() > max)
+    {
+        Option::Some(component) => Err(CanonicalizeError::ComponentTooLong(component.to_string())),
+        Option::None => Ok(()),
+    }
+}
+
+#[test]
+fn test_reject_long_components() {
+    let long = "a".repeat(256);
+    match reject_long_components(&format!(r#"C:\x\{}\y"#, long), 255) {
+        Err(CanonicalizeError::ComponentTooLong(component)) => assert_eq!(component, long),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert!(reject_long_components(&format!(r#"C:\{}"#, "a".repeat(255)), 255).is_ok());
+
+    // a long path made of short segments is fine
+    let deep = vec!["segment"; 200].join("\\");
+    assert!(reject_long_components(&format!(r#"C:\{}"#, deep), 255).is_ok());
+
+    // U+1F600 is a surrogate pair, 128 of them are 256 code units but only 128 chars
+    let emoji = "\u{1F600}".repeat(128);
+    assert!(reject_long_components(&format!(r#"C:\{}"#, emoji), 255).is_err());
+    assert!(reject_long_components(&format!(r#"C:\{}"#, "\u{e9}".repeat(255)), 255).is_ok());
+}
+
+/// any `..` component, including `.. ` which Win32 trims to `..`
+fn has_dot_dot(path: &str) -> bool {
+    path.split(&['\\', '/'][..])
+        .any(|component| component.trim_end_matches(' ') == "..")
+}
+
+#[test]
+fn test_has_dot_dot() {
+    assert!(has_dot_dot(r#"C:\a\..\b"#));
+    assert!(has_dot_dot(r#"..\a"#));
+    assert!(has_dot_dot("C:/a/.. /b"));
+    assert!(has_dot_dot(r#"\\server\share\.."#));
+    assert!(!has_dot_dot(r#"C:\a\..b\c..\...\.\d"#));
+}
+
+/// the options `canonicalize` uses, `None` until `set_default_options`
+type DefaultOptions = RwLock<Option<Arc<CanonicalizeOptions>>>;
+
+static DEFAULT_OPTIONS: DefaultOptions = RwLock::new(None);
+
+/// Replaces the options `canonicalize` uses, which are otherwise
+/// `CanonicalizeOptions::default()`.
+///
+/// Safe to call from any thread, but meant to be called once at startup.
+/// A `canonicalize` already running on another thread finishes with the
+/// options it started with, so changing this mid-flight gives mixed results.
+/// `canonicalize_with` and every function taking options never look at this.
+pub fn set_default_options(options: CanonicalizeOptions) {
+    *DEFAULT_OPTIONS.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(options));
+}
+
+/// runs `f` with whatever `defaults` holds, the lock isn't held while `f`
+/// runs so a `post_process` may itself call `set_default_options`
+fn with_defaults<R, F>(defaults: &DefaultOptions, f: F) -> R
+where
+    F: FnOnce(&CanonicalizeOptions) -> R,
+{
+    let options = defaults.read().unwrap_or_else(|e| e.into_inner()).clone();
+    match options {
+        Option::Some(options) => f(&options),
+        Option::None => f(&CanonicalizeOptions::default()),
+    }
+}
+
+/// canonicalizes with whatever `defaults` holds
+fn canonicalize_defaults(
+    path: &str,
+    defaults: &DefaultOptions,
+) -> Result<String, CanonicalizeError> {
+    with_defaults(defaults, |options| canonicalize_with(path, options))
+}
+
+#[test]
+fn test_canonicalize_defaults() {
+    // a private lock, the global one would leak into tests running alongside
+    let defaults = DefaultOptions::default();
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    *defaults.write().unwrap() = Some(Arc::new(options));
+    assert_eq!(
+        canonicalize_defaults("/c/Users/./Valarauca/", &defaults).unwrap(),
+        r#"C:\Users\Valarauca\"#
+    );
+
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .post_process(|path| Cow::Owned(path.replace('\\', "/")));
+    *defaults.write().unwrap() = Some(Arc::new(options));
+    assert_eq!(
+        canonicalize_defaults("/c/Users/./Valarauca/", &defaults).unwrap(),
+        "C:/Users/Valarauca/"
+    );
+}
+
+#[test]
+fn test_canonicalize_with_wsl() {
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .unc_case(UncCase::Upper);
+    assert_eq!(
+        canonicalize_with("//wsl$/Ubuntu/./home//me", &options).unwrap(),
+        r#"\\wsl$\Ubuntu\home\me"#
+    );
+    assert_eq!(
+        canonicalize_with(r#"\\wsl.localhost\Ubuntu\.\home"#, &options).unwrap(),
+        r#"\\wsl.localhost\Ubuntu\home"#
+    );
+}
+
+/// fails with `CanonicalizeError::InputTooLong` past `max` UTF-16 code units,
+/// only ever counting up to `max + 1` of them
+fn reject_long_input(path: &str, max: usize) -> Result<(), CanonicalizeError> {
+    // a UTF-16 code unit takes at least one byte
+    if path.len() > max && path.encode_utf16().nth(max).is_some() {
+        return Err(CanonicalizeError::InputTooLong(max));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_canonicalize_with_max_input_len() {
+    // rejected before any later stage sees it
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .post_process(|_| panic!("post_process ran on a rejected input"));
+    let huge = "/".repeat(8 * 1024 * 1024);
+    match canonicalize_with(&huge, &options) {
+        Err(CanonicalizeError::InputTooLong(DEFAULT_MAX_INPUT_LEN)) => {}
+        other => panic!("expected InputTooLong, got {:?}", other),
+    }
+
+    // counted in UTF-16 code units, not bytes
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .max_input_len(4);
+    assert_eq!(
+        canonicalize_with("\u{e9}\u{e9}\u{e9}\u{e9}", &options).unwrap(),
+        "\u{e9}\u{e9}\u{e9}\u{e9}"
+    );
+    match canonicalize_with(r#"a\b\c"#, &options) {
+        Err(CanonicalizeError::InputTooLong(4)) => {}
+        other => panic!("expected InputTooLong, got {:?}", other),
+    }
+}
+
+/// fails with `CanonicalizeError::UncNotAllowed` if `path` is, or is on a
+/// drive letter mapped onto, a network share.
+///
+/// Any `\\.\` or `\\?\` path which isn't on a drive counts too, those can
+/// reach a share without looking like UNC (`\\.\UNC\`, `\\?\GLOBALROOT\Device\Mup\`).
+fn reject_unc(path: &str) -> Result<(), CanonicalizeError> {
+    let device = [r#"\\.\"#, r#"\\?\"#].iter().any(|prefix| {
+        path.strip_prefix(prefix)
+            .is_some_and(|rest| !device::is_drive_root(rest))
+    });
+    if device || unc_parts(path).is_some() || volume::is_network_drive(path) {
+        return Err(CanonicalizeError::UncNotAllowed(path.to_string()));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_canonicalize_with_deny_unc() {
+    // UNC is spotted from the text, Windows is never asked
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .deny_unc(true);
+    for path in &[
+        r#"\\server\share\a.txt"#,
+        "//server/share/./a.txt",
+        r#"\\?\UNC\server\share\a.txt"#,
+        r#"\\.\UNC\attacker\share\x"#,
+        r#"\\?\GLOBALROOT\Device\Mup\attacker\share\x"#,
+    ] {
+        match canonicalize_with(path, &options) {
+            Err(CanonicalizeError::UncNotAllowed(_)) => {}
+            other => panic!("expected UncNotAllowed for {:?}, got {:?}", path, other),
+        }
+    }
+    // a relative path has no drive to look up either
+    assert_eq!(canonicalize_with(r#"a\.\b"#, &options).unwrap(), r#"a\b"#);
+    assert!(reject_unc(r#"\\?\C:\a.txt"#).is_ok());
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_with_deny_unc_drive() {
+    let options = CanonicalizeOptions::default().deny_unc(true);
+    assert_eq!(
+        canonicalize_with("/c/Windows/./System32", &options).unwrap(),
+        r#"C:\Windows\System32"#
+    );
+}
+
+/// This canonicalizes a path, if the path in question exists or not
+///
+/// Will handle some -oddities- of cygwin, mingw, and windows shell.
+/// A leading `~name` is the one place the disk is consulted, it only expands
+/// when a `name` directory sits next to `HOME`.
+/// Uses the options given to `set_default_options`, if it was called.
+pub fn canonicalize(path: &str) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(canonicalize_defaults(path, &DEFAULT_OPTIONS)?)
+}
+
+/// Canonicalizes a path like `canonicalize`, but never fails, any error
+/// (`HOME` unset, COM refusing to start, undecodable output) just gives back
+/// `path` as it was.
+///
+/// For logging and display only, the result may not be canonical at all and
+/// mustn't be used to open, compare, or check access to anything.
+pub fn canonicalize_lossy(path: &str) -> String {
+    canonicalize_defaults(path, &DEFAULT_OPTIONS).unwrap_or_else(|_| path.to_string())
+}
+
+#[test]
+fn test_canonicalize_lossy() {
+    // the NUL fails before any stage runs, so nothing is half rewritten
+    assert_eq!(canonicalize_lossy("C:\\a\0b"), "C:\\a\0b");
+    assert_eq!(
+        canonicalize_lossy("~/Documents/./a\0.txt"),
+        "~/Documents/./a\0.txt"
+    );
+}
+
+#[test]
+fn assert_matches() {
+    assert_eq!(
+        canonicalize("~/Documents/").unwrap(),
+        r#"C:\Users\valarauca\Documents\"#
+    );
+    assert_eq!(canonicalize("/f/Downloads/").unwrap(), r#"F:\Downloads\"#);
+    assert_eq!(canonicalize("/f/Downloads/../").unwrap(), r#"F:\"#);
+}
+
+/// Canonicalizes a path like `canonicalize`, tuned by `options`
+pub fn canonicalize_with(path: &str, options: &CanonicalizeOptions) -> Result<String, CanonicalizeError> {
+    let mut path = Cow::Borrowed(path);
+    for stage in stage::enabled_stages(options) {
+        path = (stage.run)(path, options)?;
+    }
+    Ok(path.into_owned())
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_with_post_process() {
+    let options = CanonicalizeOptions::default()
+        .post_process(|path| Cow::Owned(path.to_uppercase()));
+    assert_eq!(
+        canonicalize_with("/f/Downloads/../Music/", &options).unwrap(),
+        r#"F:\MUSIC\"#
+    );
+}
+
+#[test]
+fn test_canonicalize_with_adversarial() {
+    // regressions kept from fuzz/fuzz_targets/canonicalize.rs, only the
+    // textual stages run so any answer but a panic is fine
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .shell_escapes(true)
+        .shell_tilde_dirs(true)
+        .expand_tilde_anywhere(true)
+        .narrow_fullwidth(true);
+    // `~-` sees whatever OLDPWD is, set or not
+    for path in [
+        "",
+        "^^^^^^^^",
+        "~C:",
+        r#"~C:\x"#,
+        "~-~+",
+        r#"\\\\"#,
+        "/c",
+        "/\u{ff43}/",
+        "\u{1F600}\u{ff1a}-c%c\u{1F600}^",
+        r#"\\?\UNC\"#,
+    ]
+    .iter()
+    {
+        let _ = canonicalize_with(path, &options);
+        let _ = preview(path, &options);
+    }
+}
+
+#[test]
+fn test_canonicalize_with_assert_no_dot_dot() {
+    // only_cur_dir leaves `..` alone, standing in for anything else which might
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .assert_no_dot_dot(true);
+    match canonicalize_with(r#"C:\a\.\..\b"#, &options) {
+        Err(CanonicalizeError::UnresolvedDotDot(path)) => assert_eq!(path, r#"C:\a\..\b"#),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(
+        canonicalize_with(r#"C:\a\.\b"#, &options).unwrap(),
+        r#"C:\a\b"#
+    );
+
+    // a post processor can't sneak one back in either
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .assert_no_dot_dot(true)
+        .post_process(|path| Cow::Owned(format!(r#"{}\.."#, path)));
+    assert!(canonicalize_with(r#"C:\a"#, &options).is_err());
+}
+
+#[test]
+fn test_canonicalize_with_default_stream() {
+    // only_cur_dir keeps this textual
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .normalize_default_stream(true);
+    assert_eq!(
+        canonicalize_with(r#"C:\a\.\x.txt::$DATA"#, &options).unwrap(),
+        r#"C:\a\x.txt"#
+    );
+    assert_eq!(
+        canonicalize_with(r#"C:\a\x.txt:notes:$DATA"#, &options).unwrap(),
+        r#"C:\a\x.txt:notes:$DATA"#
+    );
+}
+
+#[test]
+fn test_canonicalize_with_narrow_fullwidth() {
+    // only_cur_dir keeps this textual
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .narrow_fullwidth(true);
+    assert_eq!(
+        canonicalize_with("\u{ff23}\u{ff1a}\u{ff3c}a\u{ff3c}.\u{ff3c}b", &options).unwrap(),
+        r#"C:\a\b"#
+    );
+    assert_eq!(
+        canonicalize_with("\u{ff0f}\u{ff43}\u{ff0f}a", &options).unwrap(),
+        r#"C:\a"#
+    );
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_with_max_component_len() {
+    let long = format!(r#"C:\x\{}"#, "a".repeat(256));
+    match canonicalize_with(&long, &CanonicalizeOptions::default()) {
+        Err(CanonicalizeError::ComponentTooLong(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    let options = CanonicalizeOptions::default().max_component_len(usize::MAX);
+    assert_eq!(canonicalize_with(&long, &options).unwrap(), long);
+    let options = CanonicalizeOptions::default().max_component_len(3);
+    assert!(canonicalize_with(r#"C:\abcd"#, &options).is_err());
+    assert!(canonicalize_with(r#"C:\abc\def"#, &options).is_ok());
+}
+
+/// Canonicalizes an `OsStr` without a lossy round trip through `String`.
+///
+/// Valid unicode runs through the same stages as `canonicalize_with`. Anything
+/// else (NTFS happily stores filenames with unpaired surrogates) goes through
+/// them too, each unpaired surrogate held by a stand in character, so every
+/// code unit survives and every option still applies.
+#[cfg(windows)]
+pub fn canonicalize_os(
+    path: &std::ffi::OsStr,
+    options: &CanonicalizeOptions,
+) -> Result<std::ffi::OsString, CanonicalizeError> {
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+    if let Option::Some(path) = path.to_str() {
+        return Ok(canonicalize_with(path, options)?.into());
+    }
+    let wide: Vec<u16> = path.encode_wide().collect();
+    Ok(std::ffi::OsString::from_wide(
+        &wide::canonicalize_wide_with(&wide, options)?,
+    ))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_os_lone_surrogate() {
+    use std::os::windows::ffi::{OsStrExt, OsStringExt};
+
+    let mut input: Vec<u16> = r#"C:/Users/Valarauca/../"#.encode_utf16().collect();
+    input.push(0xD800);
+    input.extend(".txt".encode_utf16());
+    let mut expected: Vec<u16> = r#"C:\Users\"#.encode_utf16().collect();
+    expected.push(0xD800);
+    expected.extend(".txt".encode_utf16());
+
+    let output = canonicalize_os(
+        &std::ffi::OsString::from_wide(&input),
+        &CanonicalizeOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(output.encode_wide().collect::<Vec<u16>>(), expected);
+
+    // the mingw root and doubled separators are fixed as for valid unicode
+    let mut input: Vec<u16> = "/c/Users//Valarauca/./".encode_utf16().collect();
+    input.push(0xD800);
+    let mut expected: Vec<u16> = r#"C:\Users\Valarauca\"#.encode_utf16().collect();
+    expected.push(0xD800);
+    let output = canonicalize_os(
+        &std::ffi::OsString::from_wide(&input),
+        &CanonicalizeOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(output.encode_wide().collect::<Vec<u16>>(), expected);
+
+    // restrictive options reject it like they would valid unicode
+    let mut input: Vec<u16> = r#"\\server\share\"#.encode_utf16().collect();
+    input.push(0xD800);
+    let options = CanonicalizeOptions::default().deny_unc(true);
+    match canonicalize_os(&std::ffi::OsString::from_wide(&input), &options) {
+        Err(CanonicalizeError::UncNotAllowed(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    let mut input: Vec<u16> = r#"C:\nul.txt\"#.encode_utf16().collect();
+    input.push(0xD800);
+    let options = CanonicalizeOptions::default().strict(true);
+    match canonicalize_os(&std::ffi::OsString::from_wide(&input), &options) {
+        Err(CanonicalizeError::ReservedName(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_with_unc() {
+    let default = CanonicalizeOptions::default();
+    assert_eq!(
+        canonicalize_with(r#"\\server\share"#, &default).unwrap(),
+        r#"\\server\share"#
+    );
+    assert_eq!(
+        canonicalize_with(r#"//server/share/a//b"#, &default).unwrap(),
+        r#"\\server\share\a\b"#
+    );
+    assert_eq!(
+        canonicalize_with(r#"\\server\share\a\..\.."#, &default).unwrap(),
+        r#"\\server\share"#
+    );
+
+    let options = CanonicalizeOptions::default()
+        .unc_case(UncCase::Upper)
+        .trailing_slash(TrailingSlash::Always);
+    assert_eq!(
+        canonicalize_with(r#"\\server\Share"#, &options).unwrap(),
+        r#"\\SERVER\SHARE\"#
+    );
+    assert_eq!(
+        canonicalize_with(r#"\\SERVER\share\"#, &options).unwrap(),
+        r#"\\SERVER\SHARE\"#
+    );
+
+    let options = CanonicalizeOptions::default().trailing_slash(TrailingSlash::Never);
+    assert_eq!(
+        canonicalize_with(r#"\\server\share\"#, &options).unwrap(),
+        r#"\\server\share"#
+    );
+}
+
+#[test]
+fn test_canonicalize_with_only_cur_dir() {
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    assert_eq!(
+        canonicalize_with(r#"C:\a\.\b\..\c"#, &options).unwrap(),
+        r#"C:\a\b\..\c"#
+    );
+    assert_eq!(
+        canonicalize_with("/c/a/./b/../c", &options).unwrap(),
+        r#"C:\a\b\..\c"#
+    );
+    assert_eq!(canonicalize_with("./a/./b", &options).unwrap(), r#"a\b"#);
+}
+
+#[test]
+fn test_canonicalize_with_tilde_anywhere() {
+    let home = std::env::var("HOME").unwrap();
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    assert_eq!(
+        canonicalize_with(r#"C:\a\~\b"#, &options).unwrap(),
+        r#"C:\a\~\b"#
+    );
+    let options = options.expand_tilde_anywhere(true);
+    assert_eq!(
+        canonicalize_with(r#"C:\a\~\b"#, &options).unwrap(),
+        normalize_separators(&format!(r#"C:\a\{}\b"#, home))
+    );
+}
+
+#[test]
+fn test_canonicalize_with_reject_control_chars() {
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .reject_control_chars(true);
+    for (path, bad) in [("C:\\a\tb", '\t'), ("C:\\a\u{7}\\b", '\u{7}')].iter() {
+        match canonicalize_with(path, &options) {
+            Err(CanonicalizeError::InvalidChar(c)) => assert_eq!(c, *bad),
+            other => panic!("expected InvalidChar, got {:?}", other),
+        }
+    }
+    assert_eq!(
+        canonicalize_with(r#"C:\a\.\b"#, &options).unwrap(),
+        r#"C:\a\b"#
+    );
+
+    // off by default
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    assert_eq!(canonicalize_with("C:\\a\tb", &options).unwrap(), "C:\\a\tb");
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_with_unicode_normalize() {
+    let options = CanonicalizeOptions::default().unicode_normalize(true);
+    assert_eq!(
+        canonicalize_with("/c/Users/caf\u{e9}/./x", &options).unwrap(),
+        canonicalize_with("/c/Users/cafe\u{301}/./x", &options).unwrap()
+    );
+    assert_eq!(
+        canonicalize_with("/c/Users/cafe\u{301}", &options).unwrap(),
+        "C:\\Users\\caf\u{e9}"
+    );
+}
+
+#[test]
+fn test_canonicalize_with_keep_dot_segments() {
+    let options = CanonicalizeOptions::default().resolve_dot_segments(false);
+    assert_eq!(
+        canonicalize_with("/c/a/../b", &options).unwrap(),
+        r#"C:\a\..\b"#
+    );
+    assert_eq!(
+        canonicalize_with("..//build/./x", &options).unwrap(),
+        r#"..\build\.\x"#
+    );
+}
+
+#[test]
+fn test_canonicalize_with_shell_escapes() {
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .shell_escapes(true);
+    assert_eq!(
+        canonicalize_with(r#"/c/Program\ Files"#, &options).unwrap(),
+        r#"C:\Program Files"#
+    );
+    assert_eq!(
+        canonicalize_with(r#"/c/Program\ Files/./App\ Data"#, &options).unwrap(),
+        r#"C:\Program Files\App Data"#
+    );
+}
+
+#[test]
+fn test_canonicalize_interior_nul() {
+    match canonicalize("C:\\a\0b") {
+        Err(e) => match e.downcast_ref::<CanonicalizeError>() {
+            Option::Some(CanonicalizeError::InteriorNul) => {}
+            other => panic!("expected InteriorNul, got {:?}", other),
+        },
+        Ok(path) => panic!("expected InteriorNul, got {:?}", path),
+    }
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    match canonicalize_with("C:\\a\0b", &options) {
+        Err(CanonicalizeError::InteriorNul) => {}
+        other => panic!("expected InteriorNul, got {:?}", other),
+    }
+}
+
+/// Canonicalizes command line arguments, e.g. `std::env::args().skip(1)`, the
+/// same way `canonicalize` would.
+///
+/// Arguments `is_flag` accepts are returned unchanged, everything else is
+/// treated as a path. With `|arg| arg.starts_with('-')` a file named
+/// `-foo.txt` is taken for a flag, and so is `--out=C:/x`, whose path is left
+/// as written.
+pub fn canonicalize_args<I, F>(args: I, is_flag: F) -> Vec<Result<String, CanonicalizeError>>
+where
+    I: Iterator<Item = String>,
+    F: Fn(&str) -> bool,
+{
+    args.map(|arg| {
+        if is_flag(&arg) {
+            Ok(arg)
+        } else {
+            canonicalize_defaults(&arg, &DEFAULT_OPTIONS)
+        }
+    })
+    .collect()
+}
+
+#[test]
+fn test_canonicalize_args_flags() {
+    // flags never reach Win32
+    let args = vec!["--verbose".to_string(), "-o".to_string()];
+    let out: Vec<String> = canonicalize_args(args.into_iter(), |arg| arg.starts_with('-'))
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(out, ["--verbose", "-o"]);
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_args() {
+    let args = vec![
+        "--verbose".to_string(),
+        "/c/Users/./Valarauca".to_string(),
+        "-o".to_string(),
+        r#"C:\out\..\build\"#.to_string(),
+        "-foo.txt".to_string(),
+    ];
+    let is_flag = |arg: &str| arg.starts_with('-') && arg != "-foo.txt";
+    let out: Vec<String> = canonicalize_args(args.into_iter(), is_flag)
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(
+        out,
+        [
+            "--verbose",
+            r#"C:\Users\Valarauca"#,
+            "-o",
+            r#"C:\build\"#,
+            canonicalize("-foo.txt").unwrap().as_str(),
+        ]
+    );
+}
+
+/// Canonicalizes both sides of a two path operation the same way `canonicalize`
+/// would, so `/c/...` and friends work for every operation taking a pair.
+pub(crate) fn canonicalize_pair(a: &str, b: &str) -> Result<(String, String), CanonicalizeError> {
+    // neither side should reach Win32 if the other is going to be rejected
+    reject_interior_nul(a)?;
+    reject_interior_nul(b)?;
+
+    let options = CanonicalizeOptions::default();
+    Ok((
+        canonicalize_with(a, &options)?,
+        canonicalize_with(b, &options)?,
+    ))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_pair() {
+    assert_eq!(
+        canonicalize_pair(
+            "/c/Users//Valarauca/./src",
+            r#"C:\Users\Valarauca\dst\..\dst"#
+        )
+        .unwrap(),
+        (
+            r#"C:\Users\Valarauca\src"#.to_string(),
+            r#"C:\Users\Valarauca\dst"#.to_string()
+        )
+    );
+}
+
+/// Canonicalizes `path`, returning the canonical form for IO along with a
+/// display form for showing to a user, without canonicalizing twice.
+///
+/// The display form is the canonical path with a `\\?\` prefix dropped
+/// (`\\?\UNC\server` going back to `//server`) and every `\` turned into
+/// `/`. Casing is left as `canonicalize` produced it.
+pub fn canonicalize_pair_display(path: &str) -> Result<(String, String), CanonicalizeError> {
+    canonicalize_pair_display_with(path, &CanonicalizeOptions::default())
+}
+
+/// Like `canonicalize_pair_display`, tuned by `options`.
+///
+/// `display_separator` picks what replaces `\` in the display form, the
+/// canonical form is exactly what `canonicalize_with` returns.
+pub fn canonicalize_pair_display_with(
+    path: &str,
+    options: &CanonicalizeOptions,
+) -> Result<(String, String), CanonicalizeError> {
+    let canonical = canonicalize_with(path, options)?;
+    let display = display_form(&canonical, options.display_separator.unwrap_or('/'));
+    Ok((canonical, display))
+}
+
+/// the user facing form of an already canonical path
+fn display_form(canonical: &str, separator: char) -> String {
+    let stripped = handle::strip_verbatim(canonical);
+    if separator == '\\' {
+        return stripped.into_owned();
+    }
+    stripped.replace('\\', separator.encode_utf8(&mut [0; 4]))
+}
+
+#[test]
+fn test_display_form() {
+    let long = "a".repeat(300);
+    assert_eq!(
+        display_form(&format!(r#"\\?\UNC\server\share\{}\b.txt"#, long), '/'),
+        format!("//server/share/{}/b.txt", long)
+    );
+    assert_eq!(display_form(r#"\\?\C:\Users\a.txt"#, '/'), "C:/Users/a.txt");
+    assert_eq!(
+        display_form(r#"C:\Users\Valarauca\"#, '/'),
+        "C:/Users/Valarauca/"
+    );
+    assert_eq!(
+        display_form(r#"\\?\UNC\server\share\a.txt"#, '\\'),
+        r#"\\server\share\a.txt"#
+    );
+}
+
+#[test]
+fn test_canonicalize_pair_display_with() {
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    assert_eq!(
+        canonicalize_pair_display_with("/c/Users/./Valarauca/a.txt", &options).unwrap(),
+        (
+            r#"C:\Users\Valarauca\a.txt"#.to_string(),
+            "C:/Users/Valarauca/a.txt".to_string()
+        )
+    );
+
+    let options = options.display_separator('\\');
+    assert_eq!(
+        canonicalize_pair_display_with("/c/Users/./Valarauca/a.txt", &options).unwrap(),
+        (
+            r#"C:\Users\Valarauca\a.txt"#.to_string(),
+            r#"C:\Users\Valarauca\a.txt"#.to_string()
+        )
+    );
+}
+
+/// Canonicalizes `path` once, returning it with `\` separators for Windows
+/// APIs and with `/` separators for scripts, as `(back, forward)`.
+///
+/// Unlike `canonicalize_pair_display` nothing but the separators differ, a
+/// `\\?\` or `\\server` prefix is kept in both as `//?/` or `//server`.
+pub fn canonicalize_both(path: &str) -> Result<(String, String), CanonicalizeError> {
+    canonicalize_both_with(path, &CanonicalizeOptions::default())
+}
+
+/// Like `canonicalize_both`, tuned by `options`.
+pub fn canonicalize_both_with(
+    path: &str,
+    options: &CanonicalizeOptions,
+) -> Result<(String, String), CanonicalizeError> {
+    let back = canonicalize_with(path, options)?;
+    let forward = back.replace('\\', "/");
+    Ok((back, forward))
+}
+
+#[test]
+fn test_canonicalize_both_with() {
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    for (input, back) in &[
+        ("/c/Users/./Valarauca/a.txt", r#"C:\Users\Valarauca\a.txt"#),
+        (r#"\\server\share\.\a.txt"#, r#"\\server\share\a.txt"#),
+        (r#"\\?\C:\Users\.\a.txt"#, r#"\\?\C:\Users\a.txt"#),
+    ] {
+        let (b, f) = canonicalize_both_with(input, &options).unwrap();
+        assert_eq!(b, *back);
+        assert_eq!(b.len(), f.len());
+        for (b, f) in b.chars().zip(f.chars()) {
+            assert!(b == f || (b == '\\' && f == '/'), "{:?} {:?}", b, f);
+        }
+        assert!(!f.contains('\\'));
+    }
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_both() {
+    let (back, forward) = canonicalize_both(r#"\\server\share\x\..\b.txt"#).unwrap();
+    assert!(back.starts_with(r#"\\"#) && back.ends_with(r#"server\share\b.txt"#));
+    assert_eq!(forward, back.replace('\\', "/"));
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_pair_display() {
+    let long = "a".repeat(300);
+    assert_eq!(
+        canonicalize_pair_display(&format!(r#"\\server\share\x\..\{}\b.txt"#, long)).unwrap(),
+        (
+            format!(r#"\\?\UNC\server\share\{}\b.txt"#, long),
+            format!("//server/share/{}/b.txt", long)
+        )
+    );
+}
+
+/// canonicalizes both paths, then hands them to `MoveFileExW`
+fn move_file_ex(src: &str, dst: &str, flags: MOVE_FILE_FLAGS) -> Result<(), CanonicalizeError> {
+    device::reject_devices(src)?;
+    device::reject_devices(dst)?;
+    let (src_value, dst_value) = canonicalize_pair(src, dst)?;
+    device::reject_devices(&src_value)?;
+    device::reject_devices(&dst_value)?;
+    if compare::is_noop_move(&src_value, &dst_value) {
+        return Ok(());
+    }
+    co_initialize()?;
+
+    unsafe {
+        MoveFileExW(
+            src_value.as_str(),
+            dst_value.as_str(),
+            flags).ok()?;
+    }
+    Ok(())
+}
+
+/// the `MoveFileExW` flags shared by `move_file` and `move_path`
+pub(crate) fn move_flags(overwrite_okay: bool) -> MOVE_FILE_FLAGS {
+    // see: https://docs.microsoft.com/en-us/windows/win32/api/winbase/nf-winbase-movefileexa
+    let mut flags = 0u32;
+    if overwrite_okay {
+        flags += 1u32;
+    }
+    // ensure copy occurs before flushing
+    flags += 0u32;
+    // allow for copy + delete when needed
+    flags += 2u32;
+    MOVE_FILE_FLAGS(flags)
 }
 
 
 pub fn move_file(src: &str, dst: &str, overwrite: bool) -> Result<(),Box<dyn std::error::Error>> {
-    priv_move_file(src, dst, overwrite)
+    Ok(move_path(src, dst, overwrite)?)
+}
+
+#[test]
+fn test_move_file_interior_nul() {
+    for (src, dst) in [("C:\\a\0b", "C:\\c"), ("C:\\a", "C:\\c\0d")].iter() {
+        let e = move_file(src, dst, false).unwrap_err();
+        match e.downcast_ref::<CanonicalizeError>() {
+            Option::Some(CanonicalizeError::InteriorNul) => {}
+            other => panic!("expected InteriorNul, got {:?}", other),
+        }
+    }
+}
+
+#[cfg(windows)]
+#[test]
+fn test_move_file_onto_itself() {
+    let dir = std::env::temp_dir();
+    let file = dir.join("win_canonicalize_move_self.txt");
+    std::fs::write(&file, b"a").unwrap();
+    let path = file.to_str().unwrap();
+    let same = format!(
+        r#"{}\x\..\win_canonicalize_move_self.txt"#,
+        dir.to_str().unwrap()
+    );
+
+    move_file(path, path, false).unwrap();
+    move_file(path, &same, true).unwrap();
+    assert_eq!(std::fs::read(&file).unwrap(), b"a");
+    std::fs::remove_file(&file).unwrap();
+}
+
+#[test]
+fn test_move_file_device() {
+    for (src, dst) in [
+        ("CON", r#"C:\a.txt"#),
+        (r#"C:\a.txt"#, r#"\\.\PhysicalDrive0"#),
+    ]
+    .iter()
+    {
+        let e = move_file(src, dst, true).unwrap_err();
+        match e.downcast_ref::<CanonicalizeError>() {
+            Option::Some(CanonicalizeError::DeviceNotAllowed(_)) => {}
+            other => panic!("expected DeviceNotAllowed, got {:?}", other),
+        }
+        match move_path(src, dst, true) {
+            Err(CanonicalizeError::DeviceNotAllowed(_)) => {}
+            other => panic!("expected DeviceNotAllowed, got {:?}", other),
+        }
+    }
+}
+
+/// Moves a file like `move_file`, but takes `Path`s and never converts them to
+/// `String`, so names which aren't valid unicode survive the trip.
+pub fn move_path<A, B>(src: A, dst: B, overwrite: bool) -> Result<(), CanonicalizeError>
+where
+    A: AsRef<std::path::Path>,
+    B: AsRef<std::path::Path>,
+{
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    #[cfg(windows)]
+    {
+        if src.to_str().is_none() || dst.to_str().is_none() {
+            return move_wide(src.as_os_str(), dst.as_os_str(), move_flags(overwrite));
+        }
+    }
+    // on windows both are valid unicode by now, so nothing is lost
+    move_file_ex(
+        &src.to_string_lossy(),
+        &dst.to_string_lossy(),
+        move_flags(overwrite),
+    )
+}
+
+/// `move_file_ex` for paths which aren't valid unicode, canonicalized by
+/// `canonicalize_os` and handed to `MoveFileExW` as they are
+#[cfg(windows)]
+fn move_wide(
+    src: &std::ffi::OsStr,
+    dst: &std::ffi::OsStr,
+    flags: MOVE_FILE_FLAGS,
+) -> Result<(), CanonicalizeError> {
+    use std::os::windows::ffi::OsStrExt;
+
+    // device names are ASCII, a replaced surrogate can't hide or forge one
+    device::reject_devices(&src.to_string_lossy())?;
+    device::reject_devices(&dst.to_string_lossy())?;
+
+    let options = CanonicalizeOptions::default();
+    let src_value = canonicalize_os(src, &options)?;
+    let dst_value = canonicalize_os(dst, &options)?;
+    device::reject_devices(&src_value.to_string_lossy())?;
+    device::reject_devices(&dst_value.to_string_lossy())?;
+    // `is_noop_move` needs a `String`, so only the exact same name is caught
+    if src_value == dst_value {
+        return Ok(());
+    }
+    let wide = |path: std::ffi::OsString| -> Vec<u16> {
+        path.encode_wide().chain(std::iter::once(0u16)).collect()
+    };
+    let mut src_value = wide(src_value);
+    let mut dst_value = wide(dst_value);
+    co_initialize()?;
+
+    unsafe {
+        MoveFileExW(
+            PWSTR(src_value.as_mut_ptr()),
+            PWSTR(dst_value.as_mut_ptr()),
+            flags,
+        )
+        .ok()?;
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+#[test]
+fn test_move_path_lone_surrogate() {
+    use std::os::windows::ffi::OsStringExt;
+
+    let temp = std::env::temp_dir().join("win_canonicalize_move_path");
+    std::fs::create_dir_all(&temp).unwrap();
+    // an unpaired surrogate can't be held by a `String`
+    let name = |prefix: &str| {
+        let mut name: Vec<u16> = prefix.encode_utf16().collect();
+        name.push(0xD800);
+        name.extend(".txt".encode_utf16());
+        std::ffi::OsString::from_wide(&name)
+    };
+    let src = temp.join(name("src"));
+    let dst = temp.join(name("dst"));
+    assert!(src.to_str().is_none());
+    std::fs::write(&src, b"move me").unwrap();
+
+    move_path(&src, &dst, true).unwrap();
+    assert!(!src.exists());
+    assert_eq!(std::fs::read(&dst).unwrap(), b"move me");
+
+    std::fs::remove_dir_all(&temp).unwrap();
+}
+
+#[cfg(windows)]
+#[test]
+fn test_move_file_canonicalizes() {
+    // C:\Users\...\Temp -> /c/Users/.../Temp
+    fn mingw(path: &str) -> String {
+        format!("/{}/{}", path[0..1].to_lowercase(), path[3..].replace('\\', "/"))
+    }
+
+    let temp = std::env::temp_dir().join("win_canonicalize_move_file");
+    std::fs::create_dir_all(&temp).unwrap();
+    let temp = temp.to_str().unwrap().to_string();
+    let src = format!(r#"{}\src.txt"#, temp);
+    let mid = format!(r#"{}\mid.txt"#, temp);
+    let dst = format!(r#"{}\dst.txt"#, temp);
+    std::fs::write(&src, b"move me").unwrap();
+
+    // mingw style source
+    move_file(&mingw(&src), &mid, true).unwrap();
+    assert!(std::path::Path::new(&mid).exists());
+
+    // mixed slashes with redundant segments
+    let mixed = format!(r#"{}/./\\dst.txt"#, temp.replace('\\', "/"));
+    move_file(&mid, &mixed, true).unwrap();
+    assert_eq!(std::fs::read(&dst).unwrap(), b"move me");
+    assert!(!std::path::Path::new(&mid).exists());
+
+    std::fs::remove_dir_all(&temp).unwrap();
 }
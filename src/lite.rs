@@ -0,0 +1,294 @@
+//! Hand written versions of the matchers in `re.rs`, used in place of them by
+//! the `lite` feature so `regex` can be left out of the build.
+//!
+//! Each returns exactly what its `regex` twin does, `test_matches_regex`
+//! checks the two against each other. That includes the regex quirk of `.`
+//! not matching `\n`, so a trailing `(.*)$` fails on a newline.
+
+use std::ops::Range;
+
+fn is_sep(c: char) -> bool {
+    c == '\\' || c == '/'
+}
+
+/// `^x` -> `x`, cmd's escape, `None` when there is nothing to unescape
+// only `win_escape_char` uses this, and nothing calls that yet
+#[allow(dead_code)]
+pub(crate) fn unescape_caret(s: &str) -> Option<String> {
+    unescape(s, '^', |c| c != '\n')
+}
+
+/// `\ ` -> ` ` and `\\` -> `\`, bash's escapes, `None` when there are none
+pub(crate) fn unescape_backslash(s: &str) -> Option<String> {
+    unescape(s, '\\', |c| c == ' ' || c == '\\')
+}
+
+/// drops every `escape` which is followed by a char `escaped` accepts,
+/// scanning left to right so an escaped `escape` is never reused
+fn unescape<F>(s: &str, escape: char, escaped: F) -> Option<String>
+where
+    F: Fn(char) -> bool,
+{
+    let mut out = String::with_capacity(s.len());
+    let mut changed = false;
+    let mut chars = s.chars().peekable();
+    while let Option::Some(c) = chars.next() {
+        match chars.peek() {
+            Option::Some(&next) if c == escape && escaped(next) => {
+                out.push(next);
+                chars.next();
+                changed = true;
+            }
+            _ => out.push(c),
+        }
+    }
+    if changed {
+        Some(out)
+    } else {
+        None
+    }
+}
+
+/// `/c/rest` -> (`c`, `rest`)
+pub(crate) fn mingw_root(s: &str) -> Option<(char, &str)> {
+    let mut chars = s.chars();
+    let (slash, letter, slash2) = (chars.next()?, chars.next()?, chars.next()?);
+    let rest = chars.as_str();
+    if slash == '/' && letter.is_ascii_alphabetic() && slash2 == '/' && !rest.contains('\n') {
+        Some((letter, rest))
+    } else {
+        None
+    }
+}
+
+/// `~rest` -> `rest`
+pub(crate) fn rooted_tilde(s: &str) -> Option<&str> {
+    s.strip_prefix('~').filter(|rest| !rest.contains('\n'))
+}
+
+/// byte offset of every `~` which is a whole component, directly after a
+/// separator and followed by another or the end
+pub(crate) fn separated_tildes(s: &str) -> Vec<usize> {
+    let mut found = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    let mut prev = None;
+    while let Option::Some((index, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, next)| next);
+        if c == '~' && prev.is_some_and(is_sep) && next.is_none_or(is_sep) {
+            found.push(index);
+        }
+        prev = Some(c);
+    }
+    found
+}
+
+/// `~+rest` or `~-rest` -> (`+` or `-`, `rest`), `rest` is empty or starts
+/// with a separator
+pub(crate) fn tilde_dir(s: &str) -> Option<(char, &str)> {
+    let mut chars = s.strip_prefix('~')?.chars();
+    let sign = chars.next().filter(|&c| c == '+' || c == '-')?;
+    let rest = chars.as_str();
+    if (rest.is_empty() || rest.starts_with(is_sep)) && !rest.contains('\n') {
+        Some((sign, rest))
+    } else {
+        None
+    }
+}
+
+/// every run of separators replaced with one `\`, `None` when there are none
+pub(crate) fn collapse_separators(s: &str) -> Option<String> {
+    if !s.contains(is_sep) {
+        return None;
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut in_run = false;
+    for c in s.chars() {
+        if !is_sep(c) {
+            out.push(c);
+        } else if !in_run {
+            out.push('\\');
+        }
+        in_run = is_sep(c);
+    }
+    Some(out)
+}
+
+/// starts with two separators, a UNC or device prefix
+pub(crate) fn double_separator_prefix(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(is_sep) && chars.next().is_some_and(is_sep)
+}
+
+/// byte ranges of the server, share and remainder of `\\server\share\rest`
+/// or `\\?\UNC\server\share\rest`. The server may come back as `?` or `.`
+pub(crate) fn unc(s: &str) -> Option<[Range<usize>; 3]> {
+    let b = s.as_bytes();
+    let sep = |i: usize| b.get(i).is_some_and(|&c| c == b'\\' || c == b'/');
+    if !(sep(0) && sep(1)) {
+        return None;
+    }
+    // `\\?\UNC\` is tried first, a regex alternation falls back to plain `\\`
+    // when the rest doesn't match after it
+    let extended = b.get(2) == Some(&b'?') && sep(3) && b.get(4..7) == Some(b"UNC") && sep(7);
+    let after_extended = if extended { unc_after(s, 8) } else { None };
+    after_extended.or_else(|| unc_after(s, 2))
+}
+
+/// the server, share and remainder of `s`, whose UNC prefix ends at `start`
+fn unc_after(s: &str, start: usize) -> Option<[Range<usize>; 3]> {
+    let b = s.as_bytes();
+    // separators are ascii, so every run boundary is also a char boundary
+    let run = |from: usize, of_sep: bool| {
+        (from..b.len())
+            .find(|&i| (b[i] == b'\\' || b[i] == b'/') != of_sep)
+            .unwrap_or(b.len())
+    };
+    let server = start..run(start, false);
+    let share_start = run(server.end, true);
+    let share = share_start..run(share_start, false);
+    let rest = run(share.end, true)..b.len();
+    if server.is_empty() || share_start == server.end || share.is_empty() {
+        return None;
+    }
+    if s[rest.clone()].contains('\n') {
+        return None;
+    }
+    Some([server, share, rest])
+}
+
+/// `C:rest` -> (`C:`, `rest`)
+pub(crate) fn drive_prefix(s: &str) -> Option<(&str, &str)> {
+    let b = s.as_bytes();
+    if b.len() >= 2 && b[0].is_ascii_alphabetic() && b[1] == b':' && !s[2..].contains('\n') {
+        Some(s.split_at(2))
+    } else {
+        None
+    }
+}
+
+/// byte ranges of every `%NAME%`, and of the `NAME` inside it
+pub(crate) fn env_vars(s: &str) -> Vec<(Range<usize>, Range<usize>)> {
+    delimited(
+        s,
+        "%",
+        |c| c.is_ascii_alphanumeric() || c == b'_' || c == b'(' || c == b')',
+        |name| !name[0].is_ascii_digit() && name[0] != b'(' && name[0] != b')',
+        b'%',
+    )
+}
+
+/// byte ranges of every `%~dp0`, and of the modifiers (`dp`) inside it
+pub(crate) fn arg0_modifiers(s: &str) -> Vec<(Range<usize>, Range<usize>)> {
+    delimited(
+        s,
+        "%~",
+        |c| matches!(c.to_ascii_lowercase(), b'd' | b'p' | b'n' | b'x'),
+        |_| true,
+        b'0',
+    )
+}
+
+/// every non-overlapping `open`, run of at least one byte `body` accepts, then
+/// `close`, as (whole, body) byte ranges. `first` vets the run as a whole.
+///
+/// `body` never accepts `close`, so the longest run is the only candidate.
+fn delimited<B, F>(
+    s: &str,
+    open: &str,
+    body: B,
+    first: F,
+    close: u8,
+) -> Vec<(Range<usize>, Range<usize>)>
+where
+    B: Fn(u8) -> bool,
+    F: Fn(&[u8]) -> bool,
+{
+    let b = s.as_bytes();
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Option::Some(offset) = s[from..].find(open) {
+        let start = from + offset;
+        let body_start = start + open.len();
+        let body_end = (body_start..b.len())
+            .find(|&i| !body(b[i]))
+            .unwrap_or(b.len());
+        let name = &b[body_start..body_end];
+        if !name.is_empty() && first(name) && b.get(body_end) == Some(&close) {
+            found.push((start..body_end + 1, body_start..body_end));
+            from = body_end + 1;
+        } else {
+            from = start + 1;
+        }
+    }
+    found
+}
+
+#[cfg(not(feature = "lite"))]
+#[test]
+fn test_matches_regex() {
+    use crate::re;
+
+    // the characters the patterns care about, plus a newline and non-ascii
+    const ALPHABET: &[char] = &[
+        '\\', '/', '?', 'U', 'N', 'C', '~', '+', '-', '%', '^', ':', ' ', '0', '1', 'a', 'Z', 'd',
+        'P', 'x', '_', '(', ')', '.', '\n', '\u{e9}', '\u{212a}',
+    ];
+    // random strings rarely spell these out on their own
+    const PREFIXES: &[&str] = &[
+        "",
+        "",
+        "",
+        r#"\\?\UNC\"#,
+        "//?/UNC/",
+        r#"\\"#,
+        "/c/",
+        "~+",
+        "~-",
+        "%~",
+        "%",
+        "C:",
+    ];
+
+    // xorshift, deterministic so a failure reproduces
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state as usize
+    };
+    for _ in 0..20_000 {
+        let mut s = PREFIXES[next() % PREFIXES.len()].to_string();
+        for _ in 0..next() % 12 {
+            s.push(ALPHABET[next() % ALPHABET.len()]);
+        }
+
+        assert_eq!(unescape_caret(&s), re::unescape_caret(&s), "{:?}", s);
+        assert_eq!(
+            unescape_backslash(&s),
+            re::unescape_backslash(&s),
+            "{:?}",
+            s
+        );
+        assert_eq!(mingw_root(&s), re::mingw_root(&s), "{:?}", s);
+        assert_eq!(rooted_tilde(&s), re::rooted_tilde(&s), "{:?}", s);
+        assert_eq!(separated_tildes(&s), re::separated_tildes(&s), "{:?}", s);
+        assert_eq!(tilde_dir(&s), re::tilde_dir(&s), "{:?}", s);
+        assert_eq!(
+            collapse_separators(&s),
+            re::collapse_separators(&s),
+            "{:?}",
+            s
+        );
+        assert_eq!(
+            double_separator_prefix(&s),
+            re::double_separator_prefix(&s),
+            "{:?}",
+            s
+        );
+        assert_eq!(unc(&s), re::unc(&s), "{:?}", s);
+        assert_eq!(drive_prefix(&s), re::drive_prefix(&s), "{:?}", s);
+        assert_eq!(env_vars(&s), re::env_vars(&s), "{:?}", s);
+        assert_eq!(arg0_modifiers(&s), re::arg0_modifiers(&s), "{:?}", s);
+    }
+}
@@ -0,0 +1,315 @@
+use windows::HRESULT;
+
+use crate::{
+    bindings::Windows::Win32::{
+        Foundation::PWSTR,
+        Storage::FileSystem::GetShortPathNameW,
+        System::{
+            Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_DWORD},
+            SystemServices::{
+                DeviceIoControl, FILE_FS_PERSISTENT_VOLUME_INFORMATION,
+                FSCTL_QUERY_PERSISTENT_VOLUME_STATE,
+                PERSISTENT_VOLUME_STATE_SHORT_NAME_CREATION_DISABLED,
+            },
+        },
+    },
+    canonicalize_with,
+    handle::open_for_attributes,
+    wide::from_wide_nul,
+    CanonicalizeError, CanonicalizeOptions, FsProvider, Win32Fs, KIB32,
+};
+
+#[cfg(all(windows, test))]
+use crate::find::find_file;
+
+const ERROR_FILE_NOT_FOUND: u32 = 2;
+
+/// how many leading `\` separated components of a canonical path form its root,
+/// which can never be expanded or looked up on their own
+pub(crate) fn root_len(components: &[&str]) -> usize {
+    match components {
+        ["", "", "?", "UNC", _, _, ..] => 6,
+        ["", "", "?", _, ..] | ["", "", ".", _, ..] => 4,
+        ["", "", _, _, ..] => 4,
+        [drive, ..] if drive.ends_with(':') => 1,
+        ["", ..] => 1,
+        _ => 0,
+    }
+}
+
+#[test]
+fn test_root_len() {
+    let root = |path: &str| {
+        let parts: Vec<&str> = path.split('\\').collect();
+        parts[..root_len(&parts)].join("\\")
+    };
+    assert_eq!(root(r#"C:\PROGRA~1\x"#), "C:");
+    assert_eq!(root(r#"\\server\share\PROGRA~1"#), r#"\\server\share"#);
+    assert_eq!(root(r#"\\?\C:\PROGRA~1"#), r#"\\?\C:"#);
+    assert_eq!(
+        root(r#"\\?\UNC\server\share\PROGRA~1"#),
+        r#"\\?\UNC\server\share"#
+    );
+    assert_eq!(root(r#"\PROGRA~1"#), "");
+    assert_eq!(root(r#"PROGRA~1\x"#), "");
+}
+
+/// Expands 8.3 short name components (`PROGRA~1`) to their real long names.
+///
+/// Each component containing a `~` is looked up with `FindFirstFileW`. Once a
+/// component doesn't exist, it and everything after it are left as-is.
+pub fn expand_short_names(path: &str) -> Result<String, CanonicalizeError> {
+    expand_short_names_with_fs(path, &Win32Fs)
+}
+
+/// Like `expand_short_names`, looking names up through `fs`
+pub fn expand_short_names_with_fs<F>(path: &str, fs: &F) -> Result<String, CanonicalizeError>
+where
+    F: FsProvider + ?Sized,
+{
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    let parts: Vec<&str> = path.split('\\').collect();
+    let root = root_len(&parts);
+
+    let mut expanded: Vec<String> = parts[..root].iter().map(|s| s.to_string()).collect();
+    let mut exists = true;
+    for part in &parts[root..] {
+        if exists && part.contains('~') {
+            // a relative path has no root to hang the first component off
+            let candidate = if expanded.is_empty() {
+                part.to_string()
+            } else {
+                format!("{}\\{}", expanded.join("\\"), part)
+            };
+            match fs.entry(&candidate)? {
+                // a long name which happens to contain `~` is left as typed
+                Option::Some(found) if found.short_name.eq_ignore_ascii_case(part) => {
+                    expanded.push(found.name);
+                    continue;
+                }
+                Option::Some(_) => {}
+                Option::None => exists = false,
+            }
+        }
+        expanded.push(part.to_string());
+    }
+    Ok(expanded.join("\\"))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_expand_short_names_with_fs() {
+    let fs = crate::MemoryFs::new()
+        .file(r#"C:\Program Files\Common Files\x.txt"#)
+        .short_name(r#"C:\Program Files"#, "PROGRA~1")
+        .short_name(r#"C:\Program Files\Common Files"#, "COMMON~1")
+        .dir(r#"C:\a~b"#);
+
+    assert_eq!(
+        expand_short_names_with_fs(r#"c:\progra~1\COMMON~1\x.txt"#, &fs).unwrap(),
+        r#"c:\Program Files\Common Files\x.txt"#
+    );
+    // a long name with a `~`, and a missing tail, stay as typed
+    assert_eq!(
+        expand_short_names_with_fs(r#"C:\A~B\NOSUCH~1\FILE~1.TXT"#, &fs).unwrap(),
+        r#"C:\A~B\NOSUCH~1\FILE~1.TXT"#
+    );
+
+    // relative to the working directory, not rooted on its drive
+    let fs = crate::MemoryFs::new()
+        .dir(r#"Program Files\Local"#)
+        .short_name("Program Files", "PROGRA~1")
+        .dir(r#"\Program Files\Rooted"#)
+        .short_name(r#"\Program Files"#, "PROGRA~2");
+    assert_eq!(
+        expand_short_names_with_fs(r#"PROGRA~1\Local"#, &fs).unwrap(),
+        r#"Program Files\Local"#
+    );
+    assert_eq!(
+        expand_short_names_with_fs(r#"PROGRA~2\Local"#, &fs).unwrap(),
+        r#"PROGRA~2\Local"#
+    );
+}
+
+#[cfg(windows)]
+#[test]
+fn test_expand_short_names() {
+    let long = std::env::temp_dir().join("win_canonicalize long directory name");
+    std::fs::create_dir_all(&long).unwrap();
+    let long = long.to_str().unwrap().to_string();
+
+    // only exercised where the volume generates 8.3 names
+    let found = find_file(&long).unwrap().unwrap();
+    if !found.short_name.is_empty() {
+        let short = format!(
+            r#"{}\{}\missing\file.txt"#,
+            &long[..long.rfind('\\').unwrap()],
+            found.short_name
+        );
+        let expanded = expand_short_names(&short).unwrap();
+        assert!(expanded.ends_with(r#"\win_canonicalize long directory name\missing\file.txt"#));
+    }
+
+    // nonexistent tails are left alone
+    assert_eq!(
+        expand_short_names(r#"C:\NOSUCH~1\FILE~1.TXT"#).unwrap(),
+        r#"C:\NOSUCH~1\FILE~1.TXT"#
+    );
+    std::fs::remove_dir(&long).unwrap();
+}
+
+/// Converts an existing path to its 8.3 short form with `GetShortPathNameW`.
+///
+/// Components without a short name are kept long, so on a volume with 8.3
+/// generation disabled the (canonical) long path comes back unchanged rather
+/// than as an error. `short_names_enabled` tells the two cases apart.
+pub fn to_short_path(path: &str) -> Result<String, CanonicalizeError> {
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    let mut v = vec![0u16; KIB32];
+    let len = unsafe { GetShortPathNameW(path.as_str(), PWSTR(v.as_mut_ptr()), v.len() as u32) };
+    if len == 0 {
+        return Err(CanonicalizeError::Win32(HRESULT::from_thread()));
+    }
+    from_wide_nul(&v[..len as usize])
+}
+
+#[cfg(windows)]
+#[test]
+fn test_to_short_path() {
+    let long = std::env::temp_dir().join("win_canonicalize long short path");
+    std::fs::create_dir_all(&long).unwrap();
+    let long = canonicalize_with(long.to_str().unwrap(), &CanonicalizeOptions::default()).unwrap();
+
+    let short = to_short_path(&long).unwrap();
+    let drive = long.chars().next().unwrap();
+    if short_names_enabled(drive).unwrap()
+        && !find_file(&long).unwrap().unwrap().short_name.is_empty()
+    {
+        assert!(short.contains('~'));
+    } else {
+        // 8.3 disabled: the long path survives untouched
+        assert_eq!(short, long);
+    }
+    std::fs::remove_dir(&long).unwrap();
+}
+
+/// Applies the `NtfsDisable8dot3NameCreation` policy, `volume_disabled` is
+/// only asked when the policy defers to the per-volume setting
+fn short_names_allowed<F>(
+    policy: u32,
+    system_volume: bool,
+    volume_disabled: F,
+) -> Result<bool, CanonicalizeError>
+where
+    F: FnOnce() -> Result<bool, CanonicalizeError>,
+{
+    match policy {
+        0 => Ok(true),
+        1 => Ok(false),
+        3 => Ok(system_volume),
+        // 2, and anything newer, defers to the volume
+        _ => Ok(!volume_disabled()?),
+    }
+}
+
+#[test]
+fn test_short_names_allowed() {
+    let never = || -> Result<bool, CanonicalizeError> { panic!("volume state not needed") };
+    assert!(short_names_allowed(0, false, never).unwrap());
+    assert!(!short_names_allowed(1, true, never).unwrap());
+    assert!(short_names_allowed(3, true, never).unwrap());
+    assert!(!short_names_allowed(3, false, never).unwrap());
+    assert!(short_names_allowed(2, false, || Ok(false)).unwrap());
+    assert!(!short_names_allowed(2, false, || Ok(true)).unwrap());
+}
+
+/// the system wide `NtfsDisable8dot3NameCreation` setting, 2 (per volume)
+/// when it was never set
+fn disable_8dot3_policy() -> Result<u32, CanonicalizeError> {
+    let mut value = 0u32;
+    let mut size = std::mem::size_of::<u32>() as u32;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            r#"SYSTEM\CurrentControlSet\Control\FileSystem"#,
+            "NtfsDisable8dot3NameCreation",
+            RRF_RT_REG_DWORD,
+            std::ptr::null_mut(),
+            &mut value as *mut u32 as *mut std::ffi::c_void,
+            &mut size,
+        )
+    };
+    match status.0 as u32 {
+        0 => Ok(value),
+        ERROR_FILE_NOT_FOUND => Ok(2),
+        code => Err(CanonicalizeError::Win32(HRESULT::from_win32(code))),
+    }
+}
+
+/// the per-volume "short name creation disabled" flag of a drive root
+fn volume_short_names_disabled(root: &str) -> Result<bool, CanonicalizeError> {
+    let handle = open_for_attributes(root)?;
+    let mut input = FILE_FS_PERSISTENT_VOLUME_INFORMATION {
+        Version: 1,
+        FlagMask: PERSISTENT_VOLUME_STATE_SHORT_NAME_CREATION_DISABLED,
+        ..Default::default()
+    };
+    let mut output = FILE_FS_PERSISTENT_VOLUME_INFORMATION::default();
+    let size = std::mem::size_of::<FILE_FS_PERSISTENT_VOLUME_INFORMATION>() as u32;
+    let mut returned = 0u32;
+    unsafe {
+        DeviceIoControl(
+            handle.0,
+            FSCTL_QUERY_PERSISTENT_VOLUME_STATE,
+            &mut input as *mut _ as *mut std::ffi::c_void,
+            size,
+            &mut output as *mut _ as *mut std::ffi::c_void,
+            size,
+            &mut returned,
+            std::ptr::null_mut(),
+        )
+        .ok()?
+    };
+    Ok(output.VolumeFlags & PERSISTENT_VOLUME_STATE_SHORT_NAME_CREATION_DISABLED != 0)
+}
+
+/// Reports if NTFS creates 8.3 short names for new files on a drive, so
+/// callers can tell whether a `~` free `to_short_path` result is expected.
+///
+/// Combines the system wide policy with the drive's own setting. Files created
+/// while generation was enabled keep their short names either way. Fails with
+/// `CanonicalizeError::InvalidDrive` if `drive` isn't an ASCII letter.
+pub fn short_names_enabled(drive: char) -> Result<bool, CanonicalizeError> {
+    if !drive.is_ascii_alphabetic() {
+        return Err(CanonicalizeError::InvalidDrive(drive));
+    }
+    let system_volume = std::env::var("SystemDrive")
+        .map(|system| {
+            system
+                .chars()
+                .next()
+                .is_some_and(|c| c.eq_ignore_ascii_case(&drive))
+        })
+        .unwrap_or(false);
+    let root = format!(r#"{}:\"#, drive.to_ascii_uppercase());
+    short_names_allowed(disable_8dot3_policy()?, system_volume, || {
+        volume_short_names_disabled(&root)
+    })
+}
+
+#[cfg(windows)]
+#[test]
+fn test_short_names_enabled() {
+    let drive = std::env::temp_dir()
+        .to_str()
+        .unwrap()
+        .chars()
+        .next()
+        .unwrap();
+    // either answer is fine, it must just be an answer
+    let _: bool = short_names_enabled(drive).unwrap();
+    match short_names_enabled('1') {
+        Err(CanonicalizeError::InvalidDrive('1')) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
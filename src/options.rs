@@ -0,0 +1,340 @@
+use std::{borrow::Cow, fmt};
+
+/// user supplied transform run after the standard stages
+pub(crate) type PostProcess = Box<dyn Fn(Cow<str>) -> Cow<str> + Send + Sync>;
+
+/// What to do with a trailing separator on the canonical path
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// keep whatever the input had
+    #[default]
+    Preserve,
+    /// always end with `\`
+    Always,
+    /// never end with `\`, except for roots like `C:\` where it changes the meaning
+    Never,
+}
+
+/// Casing applied to the server and share of a UNC path.
+///
+/// The casing the share was registered with isn't looked up, server and share
+/// names are case-insensitive, so a fixed casing is enough to compare them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UncCase {
+    /// keep the casing as given
+    #[default]
+    Preserve,
+    /// uppercase the server and share, `\\server\share` -> `\\SERVER\SHARE`
+    Upper,
+    /// lowercase the server and share, `\\SERVER\Share` -> `\\server\share`
+    Lower,
+}
+
+/// When `fix_root` rewrites a mingw/cygwin `/c/...` root to `C:\...`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MingwRootPolicy {
+    /// any single letter is taken as a drive, `/h/foo` -> `H:\foo`
+    #[default]
+    AlwaysDrive,
+    /// only when that drive is mapped, otherwise `/h/foo` stays a rooted path.
+    /// Asks Windows, even when the rest of the pipeline is purely textual
+    OnlyIfDriveExists,
+    /// never, for input which really is a POSIX path
+    Never,
+}
+
+/// Final casing applied to the whole canonical path
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Case {
+    /// keep the casing as given
+    #[default]
+    AsIs,
+    /// uppercase only the drive letter, `c:\Users` -> `C:\Users`
+    UpperDrive,
+    /// lowercase everything (Unicode aware), the real on-disk casing is lost
+    LowerAll,
+    /// uppercase everything (Unicode aware), the real on-disk casing is lost
+    UpperAll,
+}
+
+/// NTFS refuses any single name longer than this many UTF-16 code units
+pub const DEFAULT_MAX_COMPONENT_LEN: usize = 255;
+
+/// Longest input `canonicalize_with` accepts, in UTF-16 code units, by
+/// default. No path Win32 can open is any longer.
+pub const DEFAULT_MAX_INPUT_LEN: usize = 32768;
+
+/// Tunes how `canonicalize_with` processes a path.
+///
+/// `CanonicalizeOptions::default()` behaves like `canonicalize` before any
+/// `set_default_options` call.
+#[derive(Default)]
+pub struct CanonicalizeOptions {
+    pub(crate) only_cur_dir: bool,
+    pub(crate) trim_input: bool,
+    pub(crate) keep_dot_segments: bool,
+    pub(crate) unicode_normalize: bool,
+    pub(crate) narrow_fullwidth: bool,
+    pub(crate) shell_escapes: bool,
+    pub(crate) reject_control_chars: bool,
+    pub(crate) shell_tilde_dirs: bool,
+    pub(crate) expand_tilde_anywhere: bool,
+    pub(crate) normalize_default_stream: bool,
+    pub(crate) auto_strip_extended_when_short: bool,
+    pub(crate) mingw_root: MingwRootPolicy,
+    pub(crate) trailing_slash: TrailingSlash,
+    pub(crate) unc_case: UncCase,
+    pub(crate) case: Case,
+    /// `None` is `DEFAULT_MAX_COMPONENT_LEN`
+    pub(crate) max_component_len: Option<usize>,
+    /// `None` is `DEFAULT_MAX_INPUT_LEN`
+    pub(crate) max_input_len: Option<usize>,
+    pub(crate) assert_no_dot_dot: bool,
+    pub(crate) deny_unc: bool,
+    pub(crate) resolve_relative: bool,
+    pub(crate) strict: bool,
+    /// `None` is `/`
+    pub(crate) display_separator: Option<char>,
+    pub(crate) post_process: Option<PostProcess>,
+}
+
+impl CanonicalizeOptions {
+    /// Only drop `.` segments, leaving `..` in place for later logical processing.
+    ///
+    /// This is purely textual, `PathCchCanonicalizeEx` is never called.
+    pub fn only_cur_dir(mut self, only_cur_dir: bool) -> Self {
+        self.only_cur_dir = only_cur_dir;
+        self
+    }
+
+    /// Trim whitespace from both ends of the input, e.g. padding around a path
+    /// read from a CSV.
+    ///
+    /// Leading spaces are valid in Windows names, so this is off by default. A
+    /// leading byte order mark is always stripped, it never belongs in a path.
+    pub fn trim_input(mut self, trim_input: bool) -> Self {
+        self.trim_input = trim_input;
+        self
+    }
+
+    /// With `false`, `.` and `..` are left exactly as written while roots,
+    /// tildes and separators are still normalized. Defaults to `true`.
+    ///
+    /// This is purely textual, `PathCchCanonicalizeEx` is never called.
+    pub fn resolve_dot_segments(mut self, resolve_dot_segments: bool) -> Self {
+        self.keep_dot_segments = !resolve_dot_segments;
+        self
+    }
+
+    /// Compose the text into Unicode NFC before anything else, so a decomposed
+    /// `e\u{301}` (common from macOS) matches the `\u{e9}` Windows stores.
+    ///
+    /// This only rewrites the text, it doesn't consult the filesystem.
+    pub fn unicode_normalize(mut self, unicode_normalize: bool) -> Self {
+        self.unicode_normalize = unicode_normalize;
+        self
+    }
+
+    /// Map full-width ASCII (`U+FF01`-`U+FF5E`, as typed by East-Asian IMEs)
+    /// to plain ASCII, so `\u{ff23}\u{ff1a}\u{ff3c}` is recognized as `C:\`
+    pub fn narrow_fullwidth(mut self, narrow_fullwidth: bool) -> Self {
+        self.narrow_fullwidth = narrow_fullwidth;
+        self
+    }
+
+    /// Treat `\ ` as an escaped space and `\\` as an escaped `\`, as bash
+    /// prints them, instead of as separators
+    pub fn shell_escapes(mut self, shell_escapes: bool) -> Self {
+        self.shell_escapes = shell_escapes;
+        self
+    }
+
+    /// Fail with `CanonicalizeError::InvalidChar` on any control character
+    /// (`0x01`-`0x1F`), e.g. a tab or bell smuggled into a request path
+    pub fn reject_control_chars(mut self, reject_control_chars: bool) -> Self {
+        self.reject_control_chars = reject_control_chars;
+        self
+    }
+
+    /// Expand bash's `~+` to the current directory and `~-` to `OLDPWD`
+    pub fn shell_tilde_dirs(mut self, shell_tilde_dirs: bool) -> Self {
+        self.shell_tilde_dirs = shell_tilde_dirs;
+        self
+    }
+
+    /// Also expand a `~` which is a whole component after a separator
+    /// (`C:\a\~\b`), not just at the start.
+    ///
+    /// A `~` inside a name, like `PROGRA~1` or `~$x.docx`, is still left alone.
+    pub fn expand_tilde_anywhere(mut self, expand_tilde_anywhere: bool) -> Self {
+        self.expand_tilde_anywhere = expand_tilde_anywhere;
+        self
+    }
+
+    /// Strip a trailing `::$DATA`, the file's default stream, so `x.txt::$DATA`
+    /// (which opens exactly the same data) can't slip past a filter on `x.txt`.
+    ///
+    /// Named streams like `x.txt:notes:$DATA` are left intact.
+    pub fn normalize_default_stream(mut self, normalize_default_stream: bool) -> Self {
+        self.normalize_default_stream = normalize_default_stream;
+        self
+    }
+
+    /// Drop a `\\?\` (or `\\?\UNC\`) prefix when the path without it fits in
+    /// `MAX_PATH`, for legacy consumers which can't take one.
+    ///
+    /// A path too long for `MAX_PATH` keeps its prefix, without it Win32
+    /// couldn't open it at all.
+    pub fn auto_strip_extended_when_short(mut self, auto_strip_extended_when_short: bool) -> Self {
+        self.auto_strip_extended_when_short = auto_strip_extended_when_short;
+        self
+    }
+
+    /// Whether a leading `/x/` is read as drive `X:`
+    pub fn mingw_root(mut self, mingw_root: MingwRootPolicy) -> Self {
+        self.mingw_root = mingw_root;
+        self
+    }
+
+    /// Add, strip, or preserve a trailing separator
+    pub fn trailing_slash(mut self, trailing_slash: TrailingSlash) -> Self {
+        self.trailing_slash = trailing_slash;
+        self
+    }
+
+    /// Casing for the server and share of UNC paths
+    pub fn unc_case(mut self, unc_case: UncCase) -> Self {
+        self.unc_case = unc_case;
+        self
+    }
+
+    /// Casing applied to the whole path, after `unc_case`
+    pub fn case(mut self, case: Case) -> Self {
+        self.case = case;
+        self
+    }
+
+    /// Fail with `CanonicalizeError::ComponentTooLong` when a component of the
+    /// canonical path is longer than `max_component_len` UTF-16 code units.
+    /// Defaults to `DEFAULT_MAX_COMPONENT_LEN`, `usize::MAX` turns the check off.
+    pub fn max_component_len(mut self, max_component_len: usize) -> Self {
+        self.max_component_len = Some(max_component_len);
+        self
+    }
+
+    /// Fail with `CanonicalizeError::InputTooLong` when the input is longer
+    /// than `max_input_len` UTF-16 code units, checked before any other stage
+    /// so a hostile multi-megabyte path costs next to nothing.
+    /// Defaults to `DEFAULT_MAX_INPUT_LEN`.
+    pub fn max_input_len(mut self, max_input_len: usize) -> Self {
+        self.max_input_len = Some(max_input_len);
+        self
+    }
+
+    /// Fail with `CanonicalizeError::UnresolvedDotDot` if the final result,
+    /// after any `post_process`, still has a `..` component, for callers
+    /// passing it on to an API which must never see one
+    pub fn assert_no_dot_dot(mut self, assert_no_dot_dot: bool) -> Self {
+        self.assert_no_dot_dot = assert_no_dot_dot;
+        self
+    }
+
+    /// Fail with `CanonicalizeError::UncNotAllowed` on a network path, for
+    /// services which must never be pointed at a share an attacker controls.
+    ///
+    /// Covers `\\server\share` and `\\?\UNC\` input, and drive letters
+    /// mapped (or `subst`ed) onto a share. That last check asks Windows about
+    /// the drive, even when the rest of the pipeline is purely textual.
+    pub fn deny_unc(mut self, deny_unc: bool) -> Self {
+        self.deny_unc = deny_unc;
+        self
+    }
+
+    /// Make relative input absolute with `GetFullPathNameW`, against the
+    /// working directory of the drive it names.
+    ///
+    /// `a\b` uses the process's working directory and `\a` its drive, a drive
+    /// relative `C:a\b` uses C:'s own, which may differ. Off, all three stay
+    /// relative.
+    /// Windows also drops trailing dots and spaces from each name on the way.
+    pub fn resolve_relative(mut self, resolve_relative: bool) -> Self {
+        self.resolve_relative = resolve_relative;
+        self
+    }
+
+    /// Fail on anything `CreateFileW` would refuse or quietly reinterpret,
+    /// before it gets that far: reserved device names (`CON`, `nul.txt`) as any
+    /// component, a component ending in `.` or a space, `<>"|?*` and control
+    /// characters. Interior NULs and overlong components are always rejected.
+    ///
+    /// Each failure is its own `CanonicalizeError` variant. `:` is allowed,
+    /// `x.txt:stream` names an alternate data stream.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Separator used in the display form from `canonicalize_pair_display_with`,
+    /// `/` by default. Only the display form changes, the canonical form used
+    /// for IO always separates with `\`.
+    pub fn display_separator(mut self, display_separator: char) -> Self {
+        self.display_separator = Some(display_separator);
+        self
+    }
+
+    pub(crate) fn component_limit(&self) -> usize {
+        self.max_component_len.unwrap_or(DEFAULT_MAX_COMPONENT_LEN)
+    }
+
+    pub(crate) fn input_limit(&self) -> usize {
+        self.max_input_len.unwrap_or(DEFAULT_MAX_INPUT_LEN)
+    }
+
+    /// Runs `f` over the result once all the standard stages have finished.
+    ///
+    /// Intended for bespoke conventions this crate doesn't know about. `f` must
+    /// be `Send + Sync` so the options can be shared between threads.
+    ///
+    /// `f` runs after `PathCchCanonicalizeEx` and works on a `str`, so what it
+    /// returns is never handed back to Win32 and can't hold a lone surrogate.
+    pub fn post_process<F>(mut self, f: F) -> Self
+    where
+        F: Fn(Cow<str>) -> Cow<str> + Send + Sync + 'static,
+    {
+        self.post_process = Some(Box::new(f));
+        self
+    }
+}
+
+impl fmt::Debug for CanonicalizeOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CanonicalizeOptions")
+            .field("only_cur_dir", &self.only_cur_dir)
+            .field("trim_input", &self.trim_input)
+            .field("resolve_dot_segments", &!self.keep_dot_segments)
+            .field("unicode_normalize", &self.unicode_normalize)
+            .field("narrow_fullwidth", &self.narrow_fullwidth)
+            .field("shell_escapes", &self.shell_escapes)
+            .field("reject_control_chars", &self.reject_control_chars)
+            .field("shell_tilde_dirs", &self.shell_tilde_dirs)
+            .field("expand_tilde_anywhere", &self.expand_tilde_anywhere)
+            .field("normalize_default_stream", &self.normalize_default_stream)
+            .field(
+                "auto_strip_extended_when_short",
+                &self.auto_strip_extended_when_short,
+            )
+            .field("mingw_root", &self.mingw_root)
+            .field("trailing_slash", &self.trailing_slash)
+            .field("unc_case", &self.unc_case)
+            .field("case", &self.case)
+            .field("max_component_len", &self.component_limit())
+            .field("max_input_len", &self.input_limit())
+            .field("assert_no_dot_dot", &self.assert_no_dot_dot)
+            .field("deny_unc", &self.deny_unc)
+            .field("resolve_relative", &self.resolve_relative)
+            .field("strict", &self.strict)
+            .field("display_separator", &self.display_separator.unwrap_or('/'))
+            .field("post_process", &self.post_process.is_some())
+            .finish()
+    }
+}
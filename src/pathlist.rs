@@ -0,0 +1,103 @@
+use crate::{canonicalize_with, CanonicalizeError, CanonicalizeOptions, PathSet};
+
+/// Splits a `;` delimited list, a `"` quoted run may contain `;`.
+/// Quotes are removed and empty entries dropped.
+fn split_path_list(list: &str) -> Vec<String> {
+    let mut entries = Vec::new();
+    let mut entry = String::new();
+    let mut quoted = false;
+    for c in list.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => entries.push(std::mem::take(&mut entry)),
+            c => entry.push(c),
+        }
+    }
+    entries.push(entry);
+    entries.retain(|entry| !entry.trim().is_empty());
+    entries
+}
+
+#[test]
+fn test_split_path_list() {
+    assert_eq!(
+        split_path_list(r#"C:\a;;C:\b;"#),
+        vec![r#"C:\a"#, r#"C:\b"#]
+    );
+    assert_eq!(
+        split_path_list(r#"C:\a;"C:\semi;colon";C:\b"#),
+        vec![r#"C:\a"#, r#"C:\semi;colon"#, r#"C:\b"#]
+    );
+    assert_eq!(
+        split_path_list(r#""C:\Program Files\x""#),
+        vec![r#"C:\Program Files\x"#]
+    );
+    assert!(split_path_list(" ; ").is_empty());
+}
+
+/// joins entries with `;`, quoting any which contain one
+fn join_path_list(entries: &[String]) -> String {
+    entries
+        .iter()
+        .map(|entry| {
+            if entry.contains(';') {
+                format!("\"{}\"", entry)
+            } else {
+                entry.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+#[test]
+fn test_join_path_list() {
+    let entries = vec![r#"C:\a"#.to_string(), r#"C:\semi;colon"#.to_string()];
+    assert_eq!(join_path_list(&entries), r#"C:\a;"C:\semi;colon""#);
+    assert_eq!(split_path_list(&join_path_list(&entries)), entries);
+}
+
+/// Canonicalizes every entry of a `;` delimited list like `PATH`, dropping
+/// empty entries. Entries may be `"` quoted to contain a `;`.
+pub fn canonicalize_path_list(list: &str) -> Result<String, CanonicalizeError> {
+    canonicalize_path_list_with(list, false)
+}
+
+/// Like `canonicalize_path_list`, with `dedup` only the first of several
+/// entries naming the same directory (compared as `PathSet` does) is kept
+pub fn canonicalize_path_list_with(list: &str, dedup: bool) -> Result<String, CanonicalizeError> {
+    let options = CanonicalizeOptions::default();
+    let mut seen = PathSet::new();
+    let mut entries = Vec::new();
+    for entry in split_path_list(list) {
+        let canonical = canonicalize_with(&entry, &options)?;
+        if dedup && !seen.insert(&canonical)? {
+            continue;
+        }
+        entries.push(canonical);
+    }
+    Ok(join_path_list(&entries))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_path_list() {
+    let path = concat!(
+        r#"C:\Windows\system32;C:\Windows;;/c/msys64/usr/bin;"#,
+        r#""C:\Program Files\semi;colon\bin";c:\windows\System32\;C:\Tools\.\bin"#,
+    );
+    assert_eq!(
+        canonicalize_path_list(path).unwrap(),
+        concat!(
+            r#"C:\Windows\system32;C:\Windows;C:\msys64\usr\bin;"#,
+            r#""C:\Program Files\semi;colon\bin";c:\windows\System32\;C:\Tools\bin"#,
+        )
+    );
+    assert_eq!(
+        canonicalize_path_list_with(path, true).unwrap(),
+        concat!(
+            r#"C:\Windows\system32;C:\Windows;C:\msys64\usr\bin;"#,
+            r#""C:\Program Files\semi;colon\bin";C:\Tools\bin"#,
+        )
+    );
+}
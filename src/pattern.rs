@@ -0,0 +1,81 @@
+use crate::{canonicalize_with, normalize_separators, CanonicalizeError, CanonicalizeOptions};
+
+/// Splits a pattern just before its first component holding a wildcard.
+///
+/// The literal prefix keeps its trailing separator, `None` when there is no wildcard.
+fn split_pattern(pattern: &str) -> (&str, Option<&str>) {
+    // the `?` of a `\\?\` prefix is not a wildcard
+    let skip = match pattern.get(..4) {
+        Option::Some(prefix) if normalize_separators(prefix) == r#"\\?\"# => 4,
+        _ => 0,
+    };
+    let mut start = skip;
+    for (index, c) in pattern.char_indices().skip(skip) {
+        match c {
+            '\\' | '/' => start = index + 1,
+            '*' | '?' | '[' => return (&pattern[..start], Some(&pattern[start..])),
+            _ => {}
+        }
+    }
+    (pattern, None)
+}
+
+#[test]
+fn test_split_pattern() {
+    assert_eq!(split_pattern("C:/logs/*.txt"), ("C:/logs/", Some("*.txt")));
+    assert_eq!(
+        split_pattern(r#"C:\logs\2021-*\app?.log"#),
+        (r#"C:\logs\"#, Some(r#"2021-*\app?.log"#))
+    );
+    assert_eq!(
+        split_pattern("/c/logs/[ab].txt"),
+        ("/c/logs/", Some("[ab].txt"))
+    );
+    assert_eq!(split_pattern("*.txt"), ("", Some("*.txt")));
+    assert_eq!(
+        split_pattern(r#"\\?\C:\logs\*.txt"#),
+        (r#"\\?\C:\logs\"#, Some("*.txt"))
+    );
+    assert_eq!(split_pattern(r#"\\?\C:\a"#), (r#"\\?\C:\a"#, None));
+    assert_eq!(split_pattern("C:/logs/a.txt"), ("C:/logs/a.txt", None));
+}
+
+/// Canonicalizes the literal directory part of a glob style pattern, leaving
+/// the wildcard part as written apart from its separators.
+///
+/// The pattern is split at the first component containing `*`, `?` or `[`.
+/// Without any wildcard this is the same as `canonicalize`.
+pub fn canonicalize_pattern(pattern: &str) -> Result<String, CanonicalizeError> {
+    let options = CanonicalizeOptions::default();
+    let (prefix, tail) = match split_pattern(pattern) {
+        (prefix, Option::None) => return canonicalize_with(prefix, &options),
+        (prefix, Option::Some(tail)) => (prefix, normalize_separators(tail)),
+    };
+    if prefix.is_empty() {
+        return Ok(tail.into_owned());
+    }
+    let mut out = canonicalize_with(prefix, &options)?;
+    if !out.ends_with('\\') {
+        out.push('\\');
+    }
+    out.push_str(&tail);
+    Ok(out)
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_pattern() {
+    assert_eq!(
+        canonicalize_pattern("C:/logs/*.txt").unwrap(),
+        r#"C:\logs\*.txt"#
+    );
+    assert_eq!(
+        canonicalize_pattern("/c/logs/./old/..//2021-*/app?.log").unwrap(),
+        r#"C:\logs\2021-*\app?.log"#
+    );
+    assert_eq!(canonicalize_pattern("/c/*").unwrap(), r#"C:\*"#);
+    assert_eq!(
+        canonicalize_pattern("/c/logs/./a.txt").unwrap(),
+        crate::canonicalize("/c/logs/./a.txt").unwrap()
+    );
+}
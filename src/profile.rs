@@ -0,0 +1,145 @@
+use std::borrow::Cow;
+
+use windows::HRESULT;
+
+use crate::{
+    bindings::Windows::Win32::System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ},
+    canonicalize_with, user_home,
+    wide::from_wide_nul,
+    CanonicalizeError, CanonicalizeOptions, KIB32,
+};
+
+/// `S-1-5-21-...`, anything else could name some other key under `ProfileList`
+fn is_sid(sid: &str) -> bool {
+    sid.strip_prefix("S-1-").is_some_and(|rest| {
+        !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit() || c == '-')
+    })
+}
+
+#[test]
+fn test_is_sid() {
+    assert!(is_sid("S-1-5-18"));
+    assert!(is_sid("S-1-5-21-3623811015-3361044348-30300820-1013"));
+    assert!(!is_sid(r#"S-1-5-18\..\x"#));
+    assert!(!is_sid("Administrator"));
+    assert!(!is_sid("S-1-"));
+    assert!(!is_sid(""));
+}
+
+/// replaces a leading `~` component with the profile directory `profile`
+/// reads, which is only consulted when there is a `~`. Follows `fix_tilde`'s
+/// rules otherwise, `~name` is the directory `name` beside the profile if there
+/// is one, and left alone (as is `~file.txt`) if not.
+fn fix_tilde_with<F>(path: &str, profile: F) -> Result<Cow<'_, str>, CanonicalizeError>
+where
+    F: FnOnce() -> Result<String, CanonicalizeError>,
+{
+    let rest = match path.strip_prefix('~') {
+        Option::None => return Ok(Cow::Borrowed(path)),
+        Option::Some(rest) => rest,
+    };
+    let name_len = rest.find(['\\', '/']).unwrap_or(rest.len());
+    if name_len == 0 {
+        return Ok(Cow::Owned(format!("{}{}", profile()?, rest)));
+    }
+    let dir = profile()
+        .ok()
+        .and_then(|profile| user_home(&profile, &rest[..name_len]));
+    match dir {
+        Option::Some(dir) => Ok(Cow::Owned(format!("{}{}", dir, &rest[name_len..]))),
+        Option::None => Ok(Cow::Borrowed(path)),
+    }
+}
+
+#[test]
+fn test_fix_tilde_with() {
+    let profile = || Ok(r#"D:\Profiles\svc-build"#.to_string());
+    assert_eq!(
+        fix_tilde_with(r#"~\Documents"#, profile).unwrap(),
+        r#"D:\Profiles\svc-build\Documents"#
+    );
+    assert_eq!(
+        fix_tilde_with("~", profile).unwrap(),
+        r#"D:\Profiles\svc-build"#
+    );
+
+    // `~name` is only a profile when that directory exists
+    assert_eq!(
+        fix_tilde_with("~backup.txt", profile).unwrap(),
+        "~backup.txt"
+    );
+    assert_eq!(
+        fix_tilde_with(r#"~other\x"#, profile).unwrap(),
+        r#"~other\x"#
+    );
+    let profiles = std::env::temp_dir().join("win_canonicalize_profiles");
+    std::fs::create_dir_all(profiles.join("other")).unwrap();
+    let svc = profiles.join("svc").to_str().unwrap().to_string();
+    let other = profiles.join("other").to_str().unwrap().to_string();
+    assert_eq!(
+        fix_tilde_with(r#"~other\x"#, || Ok(svc.clone())).unwrap(),
+        format!(r#"{}\x"#, other)
+    );
+    std::fs::remove_dir_all(&profiles).unwrap();
+
+    let never = || -> Result<String, CanonicalizeError> { panic!("profile not needed") };
+    assert_eq!(fix_tilde_with(r#"C:\x\~y"#, never).unwrap(), r#"C:\x\~y"#);
+
+    let missing = || Err(CanonicalizeError::Win32(HRESULT::from_win32(2)));
+    assert!(fix_tilde_with(r#"~\x"#, missing).is_err());
+}
+
+/// `ProfileList\<sid>\ProfileImagePath`, with its `%SystemDrive%` style
+/// variables already expanded by `RegGetValueW`
+fn profile_image_path(sid: &str) -> Result<String, CanonicalizeError> {
+    if !is_sid(sid) {
+        return Err(CanonicalizeError::InvalidSid(sid.to_string()));
+    }
+    let key = format!(
+        r#"SOFTWARE\Microsoft\Windows NT\CurrentVersion\ProfileList\{}"#,
+        sid
+    );
+    let mut v = vec![0u16; KIB32];
+    let mut size = (v.len() * std::mem::size_of::<u16>()) as u32;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            key.as_str(),
+            "ProfileImagePath",
+            RRF_RT_REG_SZ,
+            std::ptr::null_mut(),
+            v.as_mut_ptr() as *mut std::ffi::c_void,
+            &mut size,
+        )
+    };
+    match status.0 as u32 {
+        0 => from_wide_nul(&v),
+        code => Err(CanonicalizeError::Win32(HRESULT::from_win32(code))),
+    }
+}
+
+/// Canonicalizes `path` with a leading `~` meaning the profile directory of
+/// the user `sid` (e.g. `S-1-5-21-...`), not the current `HOME`. A `~name`
+/// is the directory `name` beside that profile, as with `canonicalize`.
+///
+/// The profile is read from the registry's `ProfileList`, which stays right
+/// for services impersonating a user, or when `USERPROFILE` is redirected.
+pub fn fix_tilde_for_sid(path: &str, sid: &str) -> Result<String, CanonicalizeError> {
+    let path = fix_tilde_with(path, || profile_image_path(sid))?;
+    canonicalize_with(&path, &CanonicalizeOptions::default())
+}
+
+#[cfg(windows)]
+#[test]
+fn test_fix_tilde_for_sid() {
+    // LocalSystem's profile lives under the Windows directory
+    let path = fix_tilde_for_sid(r#"~\AppData"#, "S-1-5-18").unwrap();
+    assert!(path
+        .to_lowercase()
+        .ends_with(r#"\system32\config\systemprofile\appdata"#));
+
+    match fix_tilde_for_sid(r#"~\x"#, r#"S-1-5-18\.."#) {
+        Err(CanonicalizeError::InvalidSid(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+}
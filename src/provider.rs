@@ -0,0 +1,272 @@
+use std::{collections::BTreeMap, io};
+
+use crate::{
+    compare::fold_case,
+    existing::read_link,
+    find::{find_file, find_matches},
+    names::root_len,
+    CanonicalizeError,
+};
+
+/// What an `FsProvider` knows about a single path
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FsEntry {
+    /// the final component as stored, in its on disk casing
+    pub name: String,
+    /// the 8.3 name of the final component, empty if it has none
+    pub short_name: String,
+    /// the raw `FILE_ATTRIBUTE_*` flags
+    pub attributes: u32,
+}
+
+const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+
+impl FsEntry {
+    /// `true` for a directory, including a directory symbolic link
+    pub fn is_dir(&self) -> bool {
+        self.attributes & FILE_ATTRIBUTE_DIRECTORY != 0
+    }
+}
+
+/// The filesystem queries `canonicalize_existing_with_fs` and the other
+/// `_with_fs` functions make, so tests can answer them without a disk.
+///
+/// Every path handed in is already canonical. `Win32Fs` is the real thing,
+/// `MemoryFs` a stand-in.
+pub trait FsProvider {
+    /// the entry at `path`, which may name components by their 8.3 names,
+    /// `None` if it doesn't exist. A final link is not followed.
+    fn entry(&self, path: &str) -> Result<Option<FsEntry>, CanonicalizeError>;
+
+    /// `Some(target)` if `path` is a symbolic link or an app execution alias,
+    /// fails if `path` doesn't exist
+    fn read_link(&self, path: &str) -> Result<Option<String>, CanonicalizeError>;
+
+    /// the names of everything directly inside the directory `path`, without
+    /// `.` and `..`
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, CanonicalizeError>;
+}
+
+/// The real filesystem, through `FindFirstFileW` and friends
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Win32Fs;
+
+impl FsProvider for Win32Fs {
+    fn entry(&self, path: &str) -> Result<Option<FsEntry>, CanonicalizeError> {
+        find_file(path)
+    }
+
+    fn read_link(&self, path: &str) -> Result<Option<String>, CanonicalizeError> {
+        read_link(path)
+    }
+
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, CanonicalizeError> {
+        let dir = path.trim_end_matches('\\').to_string();
+        let skip = dir.len() + 1;
+        find_matches(&format!(r#"{}\*"#, dir), dir)?
+            .map(|found| found.map(|path| path[skip..].to_string()))
+            .collect()
+    }
+}
+
+/// case folds a path into the key `MemoryFs` stores it under
+fn fold_key(path: &str) -> String {
+    fold_case(path.trim_end_matches('\\'))
+}
+
+#[derive(Clone, Debug)]
+struct MemoryEntry {
+    entry: FsEntry,
+    /// the folded key of the directory holding it
+    parent: String,
+    link: Option<String>,
+}
+
+/// An `FsProvider` held in memory, for simulating links, missing files and
+/// on disk casing in tests.
+///
+/// Paths are canonical, `\` separated and matched case insensitively, each
+/// entry keeps the casing it was added with. Adding a path adds any missing
+/// parent directories, the root (`C:`, `\\server\share`) always exists.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryFs {
+    entries: BTreeMap<String, MemoryEntry>,
+}
+
+impl MemoryFs {
+    pub fn new() -> MemoryFs {
+        MemoryFs::default()
+    }
+
+    /// Adds a directory
+    pub fn dir(self, path: &str) -> Self {
+        self.add(path, FILE_ATTRIBUTE_DIRECTORY, None)
+    }
+
+    /// Adds an empty file
+    pub fn file(self, path: &str) -> Self {
+        self.add(path, 0, None)
+    }
+
+    /// Adds a symbolic link to `target`, which is taken as written
+    pub fn link(self, path: &str, target: &str) -> Self {
+        self.add(path, FILE_ATTRIBUTE_REPARSE_POINT, Some(target.to_string()))
+    }
+
+    /// Gives the already added `path` an 8.3 name
+    pub fn short_name(mut self, path: &str, short_name: &str) -> Self {
+        if let Option::Some(found) = self.entries.get_mut(&fold_key(path)) {
+            found.entry.short_name = short_name.to_string();
+        }
+        self
+    }
+
+    fn add(mut self, path: &str, attributes: u32, link: Option<String>) -> Self {
+        let parts: Vec<&str> = path.trim_end_matches('\\').split('\\').collect();
+        let root = root_len(&parts);
+        for end in root..parts.len() {
+            let key = fold_key(&parts[..=end].join("\\"));
+            let last = end + 1 == parts.len();
+            if !last && self.entries.contains_key(&key) {
+                continue;
+            }
+            let entry = FsEntry {
+                name: parts[end].to_string(),
+                short_name: String::new(),
+                attributes: if last {
+                    attributes
+                } else {
+                    FILE_ATTRIBUTE_DIRECTORY
+                },
+            };
+            let found = MemoryEntry {
+                entry,
+                parent: fold_key(&parts[..end].join("\\")),
+                link: if last { link.clone() } else { None },
+            };
+            self.entries.insert(key, found);
+        }
+        self
+    }
+
+    /// the key and entry at `path`, each component matched by its name or 8.3 name
+    fn find(&self, path: &str) -> Option<(&String, &MemoryEntry)> {
+        let parts: Vec<&str> = path.trim_end_matches('\\').split('\\').collect();
+        let root = root_len(&parts);
+        // a relative path's first component has no root, `\a` and `a` differ
+        let mut dir = if root == 0 {
+            None
+        } else {
+            Some(fold_key(&parts[..root].join("\\")))
+        };
+        let child = |dir: &Option<String>, name: &str| match dir {
+            Option::Some(dir) => fold_key(&format!(r#"{}\{}"#, dir, name)),
+            Option::None => fold_key(name),
+        };
+        let mut found = None;
+        for part in &parts[root..] {
+            let (key, entry) = match self.entries.get_key_value(&child(&dir, part)) {
+                Option::Some(hit) => hit,
+                Option::None => self.entries.iter().find(|(key, entry)| {
+                    **key == child(&dir, &entry.entry.name)
+                        && entry.entry.short_name.eq_ignore_ascii_case(part)
+                })?,
+            };
+            dir = Some(key.clone());
+            found = Some((key, entry));
+        }
+        found
+    }
+
+    fn not_found(path: &str) -> CanonicalizeError {
+        CanonicalizeError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} does not exist", path),
+        ))
+    }
+}
+
+impl FsProvider for MemoryFs {
+    fn entry(&self, path: &str) -> Result<Option<FsEntry>, CanonicalizeError> {
+        Ok(self.find(path).map(|(_, found)| found.entry.clone()))
+    }
+
+    fn read_link(&self, path: &str) -> Result<Option<String>, CanonicalizeError> {
+        match self.find(path) {
+            Option::Some((_, found)) => Ok(found.link.clone()),
+            Option::None => Err(MemoryFs::not_found(path)),
+        }
+    }
+
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, CanonicalizeError> {
+        let parts: Vec<&str> = path.trim_end_matches('\\').split('\\').collect();
+        let dir = if root_len(&parts) == parts.len() {
+            fold_key(path)
+        } else {
+            match self.find(path) {
+                Option::Some((key, found)) if found.entry.is_dir() => key.clone(),
+                _ => return Err(MemoryFs::not_found(path)),
+            }
+        };
+        Ok(self
+            .entries
+            .values()
+            .filter(|entry| entry.parent == dir)
+            .map(|entry| entry.entry.name.clone())
+            .collect())
+    }
+}
+
+#[test]
+fn test_memory_fs() {
+    let fs = MemoryFs::new()
+        .file(r#"C:\Program Files\App\app.exe"#)
+        .short_name(r#"C:\Program Files"#, "PROGRA~1")
+        .link(r#"C:\latest"#, r#"C:\Program Files\App"#)
+        .dir(r#"\\server\share\Docs"#);
+
+    // casing as added, whatever the lookup used
+    let exe = fs
+        .entry(r#"c:\PROGRAM FILES\app\APP.EXE"#)
+        .unwrap()
+        .unwrap();
+    assert_eq!(exe.name, "app.exe");
+    assert!(!exe.is_dir());
+    let short = fs.entry(r#"C:\progra~1\App"#).unwrap().unwrap();
+    assert_eq!(short.name, "App");
+    assert!(short.is_dir());
+    assert_eq!(
+        fs.entry(r#"C:\Program Files"#).unwrap().unwrap().short_name,
+        "PROGRA~1"
+    );
+    assert!(fs.entry(r#"C:\missing"#).unwrap().is_none());
+    assert!(fs.entry(r#"C:\app.exe"#).unwrap().is_none());
+
+    assert_eq!(
+        fs.read_link(r#"C:\LATEST"#).unwrap(),
+        Some(r#"C:\Program Files\App"#.to_string())
+    );
+    assert_eq!(fs.read_link(r#"C:\Program Files"#).unwrap(), None);
+    assert!(fs.read_link(r#"C:\missing"#).is_err());
+
+    assert_eq!(fs.list_dir("C:").unwrap(), ["latest", "Program Files"]);
+    assert_eq!(fs.list_dir(r#"C:\PROGRA~1\"#).unwrap(), ["App"]);
+    assert_eq!(fs.list_dir(r#"\\server\share"#).unwrap(), ["Docs"]);
+    assert!(fs.list_dir(r#"\\server\share\Docs"#).unwrap().is_empty());
+    assert!(fs.list_dir(r#"C:\missing"#).is_err());
+    assert!(fs.list_dir(r#"C:\Program Files\App\app.exe"#).is_err());
+
+    // relative and rooted paths are different entries
+    let fs = MemoryFs::new()
+        .dir(r#"Program Files\Local"#)
+        .short_name("Program Files", "PROGRA~1")
+        .dir(r#"\Program Files\Rooted"#);
+    assert_eq!(
+        fs.entry(r#"progra~1\Local"#).unwrap().unwrap().name,
+        "Local"
+    );
+    assert!(fs.entry(r#"\PROGRA~1"#).unwrap().is_none());
+    assert!(fs.entry(r#"Program Files\Rooted"#).unwrap().is_none());
+    assert!(fs.entry(r#"\Program Files\Rooted"#).unwrap().is_some());
+}
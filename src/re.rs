@@ -0,0 +1,126 @@
+//! The textual matchers the pipeline is built on, backed by `regex`.
+//!
+//! `lite.rs` has the same functions written by hand, for builds with the
+//! `lite` feature which leave `regex` out.
+
+use std::ops::Range;
+
+use regex::Regex;
+
+lazy_static! {
+    static ref WIN_ESCAPED_CHAR: Regex = Regex::new(r#"\u{005E}(.)"#).unwrap();
+    static ref SHELL_ESCAPED_CHAR: Regex = Regex::new(r#"\u{005C}([\u{0020}\u{005C}])"#).unwrap();
+    static ref ROOTED_MING_W64_COMPAT: Regex = Regex::new(r#"^/([a-zA-Z])/(.*)$"#).unwrap();
+    static ref ROOTED_TILDE_COMPAT: Regex = Regex::new(r#"^(~)(.*)$"#).unwrap();
+    static ref SEPARATED_TILDE: Regex = Regex::new(r#"([\u{005C}\u{002F}])~"#).unwrap();
+    static ref ROOTED_TILDE_DIR_COMPAT: Regex =
+        Regex::new(r#"^~([+-])([\u{005C}\u{002F}].*)?$"#).unwrap();
+    static ref NORMALIZE_SLASH: Regex = Regex::new(r#"([\u{005C}\u{002F}]{1,})"#).unwrap();
+    static ref DOUBLE_SLASH_PREFIX: Regex = Regex::new(r#"^[\u{005C}\u{002F}]{2}"#).unwrap();
+    static ref UNC_PATH: Regex =
+        Regex::new(r#"^(?:[\\/]{2}\?[\\/]UNC[\\/]|[\\/]{2})([^\\/]+)[\\/]+([^\\/]+)[\\/]*(.*)$"#)
+            .unwrap();
+    static ref DRIVE_PREFIX: Regex = Regex::new(r#"^([a-zA-Z]:)(.*)$"#).unwrap();
+    static ref ENV_VAR: Regex = Regex::new(r#"%([A-Za-z_][A-Za-z0-9_()]*)%"#).unwrap();
+    static ref CMD_ARG0_MODIFIER: Regex = Regex::new(r#"(?i)%~([dpnx]+)0"#).unwrap();
+}
+
+/// `^x` -> `x`, cmd's escape, `None` when there is nothing to unescape
+// only `win_escape_char` uses this, and nothing calls that yet
+#[allow(dead_code)]
+pub(crate) fn unescape_caret(s: &str) -> Option<String> {
+    replace_group(&WIN_ESCAPED_CHAR, s)
+}
+
+/// `\ ` -> ` ` and `\\` -> `\`, bash's escapes, `None` when there are none
+pub(crate) fn unescape_backslash(s: &str) -> Option<String> {
+    replace_group(&SHELL_ESCAPED_CHAR, s)
+}
+
+/// every match of `re` replaced by its first group
+fn replace_group(re: &Regex, s: &str) -> Option<String> {
+    if re.is_match(s) {
+        Some(re.replace_all(s, "$1").into_owned())
+    } else {
+        None
+    }
+}
+
+/// `/c/rest` -> (`c`, `rest`)
+pub(crate) fn mingw_root(s: &str) -> Option<(char, &str)> {
+    let caps = ROOTED_MING_W64_COMPAT.captures(s)?;
+    let letter = caps.get(1).unwrap().as_str().chars().next().unwrap();
+    Some((letter, caps.get(2).unwrap().as_str()))
+}
+
+/// `~rest` -> `rest`
+pub(crate) fn rooted_tilde(s: &str) -> Option<&str> {
+    let caps = ROOTED_TILDE_COMPAT.captures(s)?;
+    Some(caps.get(2).unwrap().as_str())
+}
+
+/// byte offset of every `~` which is a whole component, directly after a
+/// separator and followed by another or the end
+pub(crate) fn separated_tildes(s: &str) -> Vec<usize> {
+    SEPARATED_TILDE
+        .find_iter(s)
+        .map(|m| m.end() - 1)
+        .filter(|&tilde| matches!(s.as_bytes().get(tilde + 1), None | Some(b'\\') | Some(b'/')))
+        .collect()
+}
+
+/// `~+rest` or `~-rest` -> (`+` or `-`, `rest`), `rest` is empty or starts
+/// with a separator
+pub(crate) fn tilde_dir(s: &str) -> Option<(char, &str)> {
+    let caps = ROOTED_TILDE_DIR_COMPAT.captures(s)?;
+    let sign = caps.get(1).unwrap().as_str().chars().next().unwrap();
+    Some((sign, caps.get(2).map(|m| m.as_str()).unwrap_or("")))
+}
+
+/// every run of separators replaced with one `\`, `None` when there are none
+pub(crate) fn collapse_separators(s: &str) -> Option<String> {
+    if NORMALIZE_SLASH.is_match(s) {
+        Some(NORMALIZE_SLASH.replace_all(s, r#"\"#).into_owned())
+    } else {
+        None
+    }
+}
+
+/// starts with two separators, a UNC or device prefix
+pub(crate) fn double_separator_prefix(s: &str) -> bool {
+    DOUBLE_SLASH_PREFIX.is_match(s)
+}
+
+/// byte ranges of the server, share and remainder of `\\server\share\rest`
+/// or `\\?\UNC\server\share\rest`. The server may come back as `?` or `.`
+pub(crate) fn unc(s: &str) -> Option<[Range<usize>; 3]> {
+    let caps = UNC_PATH.captures(s)?;
+    Some([
+        caps.get(1).unwrap().range(),
+        caps.get(2).unwrap().range(),
+        caps.get(3).unwrap().range(),
+    ])
+}
+
+/// `C:rest` -> (`C:`, `rest`)
+pub(crate) fn drive_prefix(s: &str) -> Option<(&str, &str)> {
+    let caps = DRIVE_PREFIX.captures(s)?;
+    Some((caps.get(1).unwrap().as_str(), caps.get(2).unwrap().as_str()))
+}
+
+/// byte ranges of every `%NAME%`, and of the `NAME` inside it
+pub(crate) fn env_vars(s: &str) -> Vec<(Range<usize>, Range<usize>)> {
+    group_ranges(&ENV_VAR, s)
+}
+
+/// byte ranges of every `%~dp0`, and of the modifiers (`dp`) inside it
+pub(crate) fn arg0_modifiers(s: &str) -> Vec<(Range<usize>, Range<usize>)> {
+    group_ranges(&CMD_ARG0_MODIFIER, s)
+}
+
+/// the whole match and first group of every match of `re`
+fn group_ranges(re: &Regex, s: &str) -> Vec<(Range<usize>, Range<usize>)> {
+    re.captures_iter(s)
+        .map(|caps| (caps.get(0).unwrap().range(), caps.get(1).unwrap().range()))
+        .collect()
+}
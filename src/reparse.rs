@@ -0,0 +1,105 @@
+use windows::HRESULT;
+
+use crate::{
+    bindings::Windows::Win32::System::SystemServices::{
+        DeviceIoControl, FSCTL_GET_REPARSE_POINT, IO_REPARSE_TAG_APPEXECLINK,
+    },
+    handle::open_reparse_point,
+    CanonicalizeError,
+};
+
+const ERROR_NOT_A_REPARSE_POINT: u32 = 4390;
+/// `MAXIMUM_REPARSE_DATA_BUFFER_SIZE`, no reparse buffer is ever larger
+const MAX_REPARSE_BUFFER: usize = 16 * 1024;
+
+/// Pulls the target executable out of an `IO_REPARSE_TAG_APPEXECLINK`
+/// reparse buffer, `None` for any other tag or a malformed buffer.
+///
+/// After the usual tag, length and reserved header the data is a version
+/// (`3`) and a list of NUL terminated UTF-16 strings: the package id, the
+/// app user model id, then the target path.
+fn parse_app_exec_link(buffer: &[u8]) -> Option<String> {
+    let u32_at = |at: usize| -> Option<u32> {
+        let bytes = buffer.get(at..at + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    if u32_at(0)? != IO_REPARSE_TAG_APPEXECLINK as u32 || u32_at(8)? != 3 {
+        return None;
+    }
+    let wide: Vec<u16> = buffer[12..]
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    let target = wide.split(|&c| c == 0).nth(2)?;
+    if target.is_empty() {
+        return None;
+    }
+    String::from_utf16(target).ok()
+}
+
+#[test]
+fn test_parse_app_exec_link() {
+    fn buffer(tag: u32, version: u32, strings: &[&str]) -> Vec<u8> {
+        let mut data: Vec<u8> = version.to_le_bytes().to_vec();
+        for s in strings {
+            for c in s.encode_utf16().chain(std::iter::once(0)) {
+                data.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        let mut out = tag.to_le_bytes().to_vec();
+        out.extend_from_slice(&(data.len() as u16).to_le_bytes());
+        out.extend_from_slice(&[0, 0]);
+        out.extend(data);
+        out
+    }
+    let tag = IO_REPARSE_TAG_APPEXECLINK as u32;
+    let target = r#"C:\Program Files\WindowsApps\Python_x64\python.exe"#;
+
+    assert_eq!(
+        parse_app_exec_link(&buffer(
+            tag,
+            3,
+            &["Python_qbz5n2kfra8p0", "Python!Python", target, "0"]
+        )),
+        Some(target.to_string())
+    );
+    // wrong tag, wrong version, truncated
+    assert_eq!(
+        parse_app_exec_link(&buffer(0xA000000C, 3, &["a", "b", target])),
+        None
+    );
+    assert_eq!(
+        parse_app_exec_link(&buffer(tag, 2, &["a", "b", target])),
+        None
+    );
+    assert_eq!(parse_app_exec_link(&buffer(tag, 3, &["a", "b"])), None);
+    assert_eq!(parse_app_exec_link(&[0u8; 6]), None);
+}
+
+/// The executable an app execution alias (the `python.exe` stubs in
+/// `WindowsApps`) launches, `None` if `path` is not such an alias
+pub(crate) fn app_exec_link_target(path: &str) -> Result<Option<String>, CanonicalizeError> {
+    let handle = open_reparse_point(path)?;
+    let mut buffer = vec![0u8; MAX_REPARSE_BUFFER];
+    let mut returned = 0u32;
+    let ok = unsafe {
+        DeviceIoControl(
+            handle.0,
+            FSCTL_GET_REPARSE_POINT,
+            std::ptr::null_mut(),
+            0,
+            buffer.as_mut_ptr() as *mut std::ffi::c_void,
+            buffer.len() as u32,
+            &mut returned,
+            std::ptr::null_mut(),
+        )
+    };
+    if !ok.as_bool() {
+        let code = HRESULT::from_thread();
+        if code == HRESULT::from_win32(ERROR_NOT_A_REPARSE_POINT) {
+            return Ok(None);
+        }
+        return Err(CanonicalizeError::Win32(code));
+    }
+    Ok(parse_app_exec_link(&buffer[..returned as usize]))
+}
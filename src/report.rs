@@ -0,0 +1,380 @@
+use std::borrow::Cow;
+
+use crate::{
+    compare::fold_case, fix_root, normalize_slash, stage::enabled_stages, strip_cur_dir,
+    with_defaults, CanonicalizeError, CanonicalizeOptions, DEFAULT_OPTIONS,
+};
+
+/// A normalization which `canonicalize_report` saw take effect
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// a mingw/cygwin `/c/...` root was converted to `C:\...`
+    MingwRoot,
+    /// a leading `~` or `~user` was expanded to a home directory
+    Tilde,
+    /// `/` was converted to `\`, or duplicate separators collapsed
+    Separators,
+    /// `.`/`..` segments were resolved (or `PathCchCanonicalizeEx` otherwise rewrote the path)
+    DotSegments,
+    /// any other stage, by the name `preview` gives it, e.g. `"trailing_slash"`
+    Other(&'static str),
+}
+
+impl Change {
+    fn of_stage(name: &'static str) -> Change {
+        match name {
+            "fix_root" => Change::MingwRoot,
+            "fix_tilde_dirs" | "fix_tilde" | "fix_tilde_anywhere" => Change::Tilde,
+            "normalize_slash" => Change::Separators,
+            "strip_cur_dir" | "path_cch_canonicalize_ex" => Change::DotSegments,
+            other => Change::Other(other),
+        }
+    }
+}
+
+/// Canonicalizes like `canonicalize`, also listing which stages changed the path
+pub fn canonicalize_report(path: &str) -> Result<(String, Vec<Change>), CanonicalizeError> {
+    with_defaults(&DEFAULT_OPTIONS, |options| report_with(path, options))
+}
+
+/// runs the stages of `canonicalize_with`, noting each one which altered the text
+fn report_with(
+    path: &str,
+    options: &CanonicalizeOptions,
+) -> Result<(String, Vec<Change>), CanonicalizeError> {
+    let mut changes = Vec::new();
+    let mut path = path.to_string();
+    for stage in enabled_stages(options) {
+        let after = (stage.run)(Cow::Borrowed(path.as_str()), options)?.into_owned();
+        let change = Change::of_stage(stage.name);
+        if after != path && !changes.contains(&change) {
+            changes.push(change);
+        }
+        path = after;
+    }
+    Ok((path, changes))
+}
+
+#[test]
+fn test_report_with() {
+    use crate::{canonicalize_with, TrailingSlash};
+
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .trim_input(true)
+        .trailing_slash(TrailingSlash::Always);
+    for path in [
+        "/c//a/./b",
+        "\u{feff}C:\\a",
+        "  /c/a/  ",
+        r#"relative\.\path"#,
+    ]
+    .iter()
+    {
+        assert_eq!(
+            report_with(path, &options).unwrap().0,
+            canonicalize_with(path, &options).unwrap()
+        );
+    }
+    assert_eq!(
+        report_with("/c//a/./b", &options).unwrap().1,
+        [
+            Change::MingwRoot,
+            Change::Separators,
+            Change::DotSegments,
+            Change::Other("trailing_slash")
+        ]
+    );
+    // fails where canonicalize_with would
+    assert!(report_with("C:\\a\0b", &options).is_err());
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_report() {
+    use crate::canonicalize;
+
+    assert_eq!(
+        canonicalize_report("/c//a/../b").unwrap(),
+        (
+            r#"C:\b"#.to_string(),
+            vec![Change::MingwRoot, Change::Separators, Change::DotSegments]
+        )
+    );
+    assert_eq!(
+        canonicalize_report(r#"C:\Users\Valarauca"#).unwrap(),
+        (r#"C:\Users\Valarauca"#.to_string(), vec![])
+    );
+    for path in [
+        "/c//a/../b",
+        "\u{feff}C:/a/./b/",
+        r#"C:\a\b::$DATA"#,
+        r#"\\server\share\a\..\b"#,
+    ]
+    .iter()
+    {
+        assert_eq!(
+            canonicalize_report(path).unwrap().0,
+            canonicalize(path).unwrap()
+        );
+    }
+}
+
+/// How much canonicalizing changed a path, see `classify_change`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeClass {
+    /// the text is identical
+    None,
+    /// the same location spelled differently: separators, casing, `.`
+    /// segments, or a mingw `/c/` root for `C:\`
+    Cosmetic,
+    /// anything else, e.g. `..` collapsed (which may not follow a link the way
+    /// the OS would), `~` expanded, or a relative path made absolute
+    Semantic,
+}
+
+/// Compares a path with what `canonicalize` made of it, so a tool can apply
+/// cosmetic fixes on its own and ask before semantic ones.
+///
+/// Purely textual, both sides are reduced to a spelling-independent form and
+/// compared. Anything not known to be cosmetic, including an added or dropped
+/// `\\?\` prefix or trailing separator, counts as `Semantic`.
+pub fn classify_change(input: &str, output: &str) -> ChangeClass {
+    if input == output {
+        return ChangeClass::None;
+    }
+    let spelling = |path: &str| -> Result<String, CanonicalizeError> {
+        let path = strip_cur_dir(normalize_slash(fix_root(path)?)?)?;
+        Ok(fold_case(&path))
+    };
+    match (spelling(input), spelling(output)) {
+        (Ok(a), Ok(b)) if a == b => ChangeClass::Cosmetic,
+        _ => ChangeClass::Semantic,
+    }
+}
+
+#[test]
+fn test_classify_change() {
+    assert_eq!(classify_change(r#"C:\a"#, r#"C:\a"#), ChangeClass::None);
+
+    // a mingw root names the same drive, so it's cosmetic
+    assert_eq!(classify_change("/c/a", r#"C:\a"#), ChangeClass::Cosmetic);
+    assert_eq!(
+        classify_change("C:/a//b", r#"C:\a\b"#),
+        ChangeClass::Cosmetic
+    );
+    assert_eq!(
+        classify_change(r#"c:\A\b"#, r#"C:\a\B"#),
+        ChangeClass::Cosmetic
+    );
+    assert_eq!(
+        classify_change(r#"C:\a\.\b"#, r#"C:\a\b"#),
+        ChangeClass::Cosmetic
+    );
+
+    assert_eq!(
+        classify_change(r#"C:\a\..\b"#, r#"C:\b"#),
+        ChangeClass::Semantic
+    );
+    assert_eq!(
+        classify_change(r#"~\a"#, r#"C:\Users\Valarauca\a"#),
+        ChangeClass::Semantic
+    );
+    assert_eq!(
+        classify_change("C:a", r#"C:\work\a"#),
+        ChangeClass::Semantic
+    );
+    // a local device turned into a network path
+    assert_eq!(
+        classify_change(r#"\\.\pipe\x"#, r#"\\pipe\x"#),
+        ChangeClass::Semantic
+    );
+    assert_eq!(
+        classify_change(r#"C:\a\"#, r#"C:\a"#),
+        ChangeClass::Semantic
+    );
+    assert_eq!(
+        classify_change(r#"C:\a"#, r#"\\?\C:\a"#),
+        ChangeClass::Semantic
+    );
+}
+
+/// One stage of `canonicalize_with` as seen by `preview`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PreviewStage {
+    /// name of the stage, e.g. `"fix_root"`
+    pub name: &'static str,
+    pub before: String,
+    /// the same as `before` when the stage failed
+    pub after: String,
+    /// why the stage failed, only ever set on the last stage
+    pub error: Option<String>,
+}
+
+/// What `preview` expects `canonicalize_with` to do, stage by stage
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CanonicalizePreview {
+    /// every stage which ran, in order, including those which changed nothing
+    /// and ending early at one which failed
+    pub stages: Vec<PreviewStage>,
+    /// output of the last stage which succeeded
+    pub output: String,
+}
+
+impl CanonicalizePreview {
+    fn push(&mut self, name: &'static str, after: Cow<str>) {
+        self.stages.push(PreviewStage {
+            name,
+            before: std::mem::replace(&mut self.output, after.to_string()),
+            after: after.into_owned(),
+            error: None,
+        });
+    }
+
+    fn fail(&mut self, name: &'static str, error: CanonicalizeError) {
+        self.stages.push(PreviewStage {
+            name,
+            before: self.output.clone(),
+            after: self.output.clone(),
+            error: Some(error.to_string()),
+        });
+    }
+
+    /// the error of the stage which failed, if one did
+    pub fn error(&self) -> Option<&str> {
+        self.stages.last().and_then(|stage| stage.error.as_deref())
+    }
+}
+
+/// Dry run of `canonicalize_with`, stage by stage.
+///
+/// `PathCchCanonicalizeEx` (unless `only_cur_dir` replaces it) and any
+/// `post_process` are skipped, so COM is never initialized and the user's
+/// code never runs. The component length and `assert_no_dot_dot` checks,
+/// which judge what `PathCchCanonicalizeEx` left, are skipped along with it.
+/// Every other validation runs, a failing one ends the preview with its error.
+///
+/// Reading `HOME`, `OLDPWD` and the current directory for tilde expansion
+/// still happens, as does checking for a `name` directory next to `HOME` for
+/// a leading `~name`, the drive lookup for `MingwRootPolicy::OnlyIfDriveExists`
+/// and `GetFullPathNameW` for `resolve_relative`.
+pub fn preview(path: &str, options: &CanonicalizeOptions) -> CanonicalizePreview {
+    let mut preview = CanonicalizePreview {
+        stages: Vec::new(),
+        output: path.to_string(),
+    };
+    for stage in enabled_stages(options).filter(|stage| (stage.in_preview)(options)) {
+        match (stage.run)(Cow::Borrowed(preview.output.as_str()), options) {
+            Ok(after) => {
+                let after = after.into_owned();
+                preview.push(stage.name, Cow::Owned(after));
+            }
+            Err(e) => {
+                preview.fail(stage.name, e);
+                break;
+            }
+        }
+    }
+    preview
+}
+
+#[test]
+fn test_preview() {
+    use crate::{canonicalize_with, TrailingSlash, UncCase};
+
+    // with only_cur_dir every stage of canonicalize_with is textual
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .unc_case(UncCase::Upper)
+        .trailing_slash(TrailingSlash::Always);
+    for path in [
+        "/c/Users//Valarauca/./Documents",
+        r#"C:\Users\.\Valarauca"#,
+        "//server/share/./a",
+        r#"relative\.\path\"#,
+    ]
+    .iter()
+    {
+        assert_eq!(
+            preview(path, &options).output,
+            canonicalize_with(path, &options).unwrap()
+        );
+    }
+
+    let stages = preview(
+        "/c/a//./b",
+        &CanonicalizeOptions::default().only_cur_dir(true),
+    )
+    .stages;
+    let names: Vec<&str> = stages.iter().map(|s| s.name).collect();
+    assert_eq!(
+        names,
+        [
+            "reject_long_input",
+            "strip_bom",
+            "reject_interior_nul",
+            "fix_root",
+            "fix_tilde",
+            "normalize_slash",
+            "strip_cur_dir",
+            "unc_case",
+            "case",
+            "trailing_slash",
+            "reject_long_components"
+        ]
+    );
+    assert_eq!(stages[3].before, "/c/a//./b");
+    assert_eq!(stages[3].after, r#"C:\a//./b"#);
+    assert_eq!(stages[5].after, r#"C:\a\.\b"#);
+    assert_eq!(stages[6].after, r#"C:\a\b"#);
+    assert!(stages.iter().all(|stage| stage.error.is_none()));
+}
+
+#[test]
+fn test_preview_failing_stage() {
+    use crate::canonicalize_with;
+
+    // a failing validation is where the preview ends, like canonicalize_with
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .deny_unc(true);
+    let path = "//server/share/./a";
+    let failed = preview(path, &options);
+    let last = failed.stages.last().unwrap();
+    assert_eq!(last.name, "reject_unc");
+    assert_eq!(last.before, r#"\\server\share\.\a"#);
+    assert_eq!(last.after, last.before);
+    assert_eq!(
+        failed.error().unwrap(),
+        canonicalize_with(path, &options).unwrap_err().to_string()
+    );
+    assert_eq!(failed.output, last.before);
+
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+    let failed = preview("C:\\a\0b", &options);
+    assert_eq!(failed.stages.last().unwrap().name, "reject_interior_nul");
+    assert!(failed.error().is_some());
+
+    // the stages PathCchCanonicalizeEx would have run before are left out
+    let options = CanonicalizeOptions::default().assert_no_dot_dot(true);
+    let skipped = preview(r#"C:\a\..\b"#, &options);
+    assert_eq!(skipped.error(), None);
+    let names: Vec<&str> = skipped.stages.iter().map(|s| s.name).collect();
+    assert!(!names.contains(&"path_cch_canonicalize_ex"));
+    assert!(!names.contains(&"assert_no_dot_dot"));
+}
+
+#[cfg(windows)]
+#[test]
+fn test_preview_resolve_relative() {
+    use crate::canonicalize_with;
+
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .resolve_relative(true);
+    for path in ["a/./b", "C:a", r#"\a"#].iter() {
+        let preview = preview(path, &options);
+        assert_eq!(preview.output, canonicalize_with(path, &options).unwrap());
+        assert!(preview.stages.iter().any(|s| s.name == "resolve_relative"));
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{
+    canonicalize_with, device::is_reserved_name, CanonicalizeError, CanonicalizeOptions,
+    TrailingSlash,
+};
+
+fn is_sep(c: char) -> bool {
+    c == '/' || c == '\\'
+}
+
+/// Joins `member` onto an already canonical `root`, collapsing `..` but never
+/// above `root`. Absolute members, anything with a `:`, device names and
+/// components Win32 would strip a trailing `.` from are rejected.
+fn join_sandboxed(root: &str, member: &str) -> Result<String, CanonicalizeError> {
+    // `C:x` is relative to C's current directory and `x:stream` is an
+    // alternate data stream, neither belongs inside the sandbox
+    if member.starts_with(is_sep) || member.contains(':') {
+        return Err(CanonicalizeError::NotRelative(member.to_string()));
+    }
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in member.split(is_sep) {
+        // Win32 drops trailing spaces, `.. ` would otherwise sneak out as `..`
+        match component.trim_end_matches(' ') {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            // `a.` opens `a`, `...` opens the directory itself
+            trimmed if trimmed.ends_with('.') => {
+                return Err(CanonicalizeError::TrailingDotOrSpace(component.to_string()));
+            }
+            // `a/nul.txt` opens the NUL device, wherever it sits
+            _ if is_reserved_name(component) => {
+                return Err(CanonicalizeError::ReservedName(component.to_string()));
+            }
+            trimmed => components.push(trimmed),
+        }
+    }
+
+    let mut out = root.to_string();
+    for component in components.iter() {
+        if !out.ends_with('\\') {
+            out.push('\\');
+        }
+        out.push_str(component);
+    }
+    if !components.is_empty() && member.ends_with(is_sep) {
+        out.push('\\');
+    }
+    Ok(out)
+}
+
+#[test]
+fn test_join_sandboxed() {
+    let root = r#"C:\extract"#;
+    assert_eq!(
+        join_sandboxed(root, "docs/readme.txt").unwrap(),
+        r#"C:\extract\docs\readme.txt"#
+    );
+    assert_eq!(
+        join_sandboxed(root, r#"docs\.\img\..\a.png"#).unwrap(),
+        r#"C:\extract\docs\a.png"#
+    );
+    assert_eq!(
+        join_sandboxed(root, "docs/").unwrap(),
+        r#"C:\extract\docs\"#
+    );
+    assert_eq!(join_sandboxed(r#"C:\"#, "a/b").unwrap(), r#"C:\a\b"#);
+
+    // `..` is clamped at the root
+    assert_eq!(
+        join_sandboxed(root, r#"..\..\escape"#).unwrap(),
+        r#"C:\extract\escape"#
+    );
+    assert_eq!(
+        join_sandboxed(root, "a/../../../b").unwrap(),
+        r#"C:\extract\b"#
+    );
+    assert_eq!(join_sandboxed(root, "..").unwrap(), root);
+    assert_eq!(
+        join_sandboxed(root, ".. /.. /escape").unwrap(),
+        r#"C:\extract\escape"#
+    );
+
+    for member in &[
+        r#"\Windows\System32"#,
+        "/etc/passwd",
+        r#"\\server\share\x"#,
+        r#"C:\Windows"#,
+        "C:Windows",
+        "file.txt:hidden",
+    ] {
+        match join_sandboxed(root, member) {
+            Err(CanonicalizeError::NotRelative(m)) => assert_eq!(m, *member),
+            other => panic!("unexpected {:?} for {}", other, member),
+        }
+    }
+
+    for member in &["CON", "a/nul.txt", r#"docs\Com1 .log"#, "CONIN$"] {
+        match join_sandboxed(root, member) {
+            Err(CanonicalizeError::ReservedName(_)) => {}
+            other => panic!("unexpected {:?} for {}", other, member),
+        }
+    }
+    for (member, component) in &[
+        ("docs./a.txt", "docs."),
+        (r#"a\b. \c"#, "b. "),
+        ("...", "..."),
+        ("a/..../b", "...."),
+    ] {
+        match join_sandboxed(root, member) {
+            Err(CanonicalizeError::TrailingDotOrSpace(c)) => assert_eq!(c, *component),
+            other => panic!("unexpected {:?} for {}", other, member),
+        }
+    }
+    // a dot which isn't trailing is fine
+    assert_eq!(
+        join_sandboxed(root, ".git/config").unwrap(),
+        r#"C:\extract\.git\config"#
+    );
+}
+
+/// Resolves an archive member (e.g. a zip entry name) inside `root`.
+///
+/// `root` is canonicalized and treated as the top of the filesystem, `..` in
+/// `member` collapses as usual but is clamped at `root`, so `..\..\escape`
+/// becomes `root\escape`. A member which is absolute, names a drive, or
+/// contains a `:` fails with `CanonicalizeError::NotRelative`, one with a
+/// device name like `nul.txt` with `ReservedName`, and one with a component
+/// ending in `.` with `TrailingDotOrSpace`.
+pub fn canonicalize_sandboxed(member: &str, root: &str) -> Result<String, CanonicalizeError> {
+    let options = CanonicalizeOptions::default().trailing_slash(TrailingSlash::Never);
+    let root = canonicalize_with(root, &options)?;
+    join_sandboxed(&root, member)
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_sandboxed() {
+    assert_eq!(
+        canonicalize_sandboxed("docs/readme.txt", "/c/extract/").unwrap(),
+        r#"C:\extract\docs\readme.txt"#
+    );
+    assert_eq!(
+        canonicalize_sandboxed(r#"..\..\escape"#, r#"C:\extract"#).unwrap(),
+        r#"C:\extract\escape"#
+    );
+    assert!(canonicalize_sandboxed(r#"C:\Windows\win.ini"#, r#"C:\extract"#).is_err());
+}
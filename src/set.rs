@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+
+use crate::{
+    canonicalize_with, compare::fold_case, CanonicalizeError, CanonicalizeOptions, TrailingSlash,
+};
+
+/// case folds a canonical path into the key `PathSet` stores
+fn fold_key(canonical: &str) -> String {
+    fold_case(canonical)
+}
+
+#[test]
+fn test_fold_key() {
+    assert_eq!(fold_key(r#"C:\a\B"#), fold_key(r#"c:\A\b"#));
+    assert_eq!(fold_key(r#"C:\straße"#), fold_key(r#"c:\STRAßE"#));
+    // NTFS keeps these apart, `ß` has no single unit uppercase
+    assert_ne!(fold_key(r#"C:\straße"#), fold_key(r#"c:\STRASSE"#));
+    assert_ne!(fold_key(r#"C:\a"#), fold_key(r#"C:\b"#));
+}
+
+/// A set of paths compared with Windows semantics.
+///
+/// Each path is canonicalized and case folded once, on the way in, so
+/// `/c/a`, `C:\A` and `c:\a\` are all the same entry and lookups after
+/// that are plain hash lookups.
+#[derive(Clone, Debug, Default)]
+pub struct PathSet {
+    keys: HashSet<String>,
+}
+
+impl PathSet {
+    pub fn new() -> PathSet {
+        PathSet::default()
+    }
+
+    fn key(path: &str) -> Result<String, CanonicalizeError> {
+        let options = CanonicalizeOptions::default().trailing_slash(TrailingSlash::Never);
+        Ok(fold_key(&canonicalize_with(path, &options)?))
+    }
+
+    /// Adds a path, `false` if an equivalent path was already present
+    pub fn insert(&mut self, path: &str) -> Result<bool, CanonicalizeError> {
+        Ok(self.keys.insert(PathSet::key(path)?))
+    }
+
+    /// Reports if an equivalent path is present
+    pub fn contains(&self, path: &str) -> Result<bool, CanonicalizeError> {
+        Ok(self.keys.contains(&PathSet::key(path)?))
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+#[cfg(windows)]
+#[test]
+fn test_path_set() {
+    let mut set = PathSet::new();
+    assert!(set.is_empty());
+    assert!(set.insert("/c/a").unwrap());
+    assert!(!set.insert(r#"C:\A"#).unwrap());
+    assert!(!set.insert(r#"c:\x\..\a\"#).unwrap());
+    assert_eq!(set.len(), 1);
+
+    assert!(set.contains(r#"C:\A"#).unwrap());
+    assert!(!set.contains(r#"C:\b"#).unwrap());
+}
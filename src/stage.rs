@@ -0,0 +1,250 @@
+use std::borrow::Cow;
+
+use crate::{
+    apply_case, apply_mingw_root, apply_trailing_slash, fix_tilde, fix_tilde_anywhere,
+    fix_tilde_dirs, has_dot_dot, narrow_fullwidth, normalize_nfc, normalize_slash,
+    path_cch_canonicalize_ex, reject_control_chars, reject_interior_nul, reject_long_components,
+    reject_long_input, reject_unc, resolve_relative, strict, strip_bom, strip_cur_dir,
+    strip_default_stream, strip_extended_when_short, trim_input, unc::apply_unc_case,
+    unescape_shell, CanonicalizeError, CanonicalizeOptions, MingwRootPolicy,
+};
+
+type Run =
+    for<'a> fn(Cow<'a, str>, &CanonicalizeOptions) -> Result<Cow<'a, str>, CanonicalizeError>;
+
+/// One step of `canonicalize_with`. `canonicalize_report` and `preview` walk
+/// the same `STAGES`, so neither can drift from what canonicalizing does.
+pub(crate) struct Stage {
+    /// name of the stage, e.g. `"fix_root"`
+    pub(crate) name: &'static str,
+    /// `options` asks for this stage at all
+    pub(crate) enabled: fn(&CanonicalizeOptions) -> bool,
+    /// `preview` runs it, it doesn't initialize COM, run the caller's code or
+    /// judge text only `PathCchCanonicalizeEx` would have cleaned up
+    pub(crate) in_preview: fn(&CanonicalizeOptions) -> bool,
+    /// validation stages hand back their input unchanged, or fail
+    pub(crate) run: Run,
+}
+
+fn always(_: &CanonicalizeOptions) -> bool {
+    true
+}
+
+fn never(_: &CanonicalizeOptions) -> bool {
+    false
+}
+
+/// `PathCchCanonicalizeEx` doesn't run, so a preview sees the real text
+fn without_path_cch(options: &CanonicalizeOptions) -> bool {
+    options.only_cur_dir || options.keep_dot_segments
+}
+
+/// every stage of `canonicalize_with`, in the order they run
+pub(crate) static STAGES: &[Stage] = &[
+    Stage {
+        name: "reject_long_input",
+        enabled: always,
+        in_preview: always,
+        run: |path, options| {
+            reject_long_input(&path, options.input_limit())?;
+            Ok(path)
+        },
+    },
+    Stage {
+        name: "strip_bom",
+        enabled: always,
+        in_preview: always,
+        run: |path, _| strip_bom(path),
+    },
+    Stage {
+        name: "trim_input",
+        enabled: |options| options.trim_input,
+        in_preview: always,
+        run: |path, _| trim_input(path),
+    },
+    Stage {
+        name: "reject_interior_nul",
+        enabled: always,
+        in_preview: always,
+        run: |path, _| {
+            reject_interior_nul(&path)?;
+            Ok(path)
+        },
+    },
+    Stage {
+        name: "reject_control_chars",
+        enabled: |options| options.reject_control_chars,
+        in_preview: always,
+        run: |path, _| {
+            reject_control_chars(&path)?;
+            Ok(path)
+        },
+    },
+    Stage {
+        name: "normalize_nfc",
+        enabled: |options| options.unicode_normalize,
+        in_preview: always,
+        run: |path, _| normalize_nfc(path),
+    },
+    Stage {
+        name: "narrow_fullwidth",
+        enabled: |options| options.narrow_fullwidth,
+        in_preview: always,
+        run: |path, _| narrow_fullwidth(path),
+    },
+    Stage {
+        name: "unescape_shell",
+        enabled: |options| options.shell_escapes,
+        in_preview: always,
+        run: |path, _| unescape_shell(path),
+    },
+    Stage {
+        name: "fix_root",
+        enabled: |options| options.mingw_root != MingwRootPolicy::Never,
+        in_preview: always,
+        run: |path, options| apply_mingw_root(path, options.mingw_root),
+    },
+    Stage {
+        name: "fix_tilde_dirs",
+        enabled: |options| options.shell_tilde_dirs,
+        in_preview: always,
+        run: |path, _| fix_tilde_dirs(path),
+    },
+    Stage {
+        name: "fix_tilde",
+        enabled: always,
+        in_preview: always,
+        run: |path, _| fix_tilde(path),
+    },
+    Stage {
+        name: "fix_tilde_anywhere",
+        enabled: |options| options.expand_tilde_anywhere,
+        in_preview: always,
+        run: |path, _| fix_tilde_anywhere(path),
+    },
+    Stage {
+        name: "normalize_slash",
+        enabled: always,
+        in_preview: always,
+        run: |path, _| normalize_slash(path),
+    },
+    Stage {
+        name: "resolve_relative",
+        enabled: |options| options.resolve_relative,
+        in_preview: always,
+        run: |path, _| resolve_relative(path),
+    },
+    Stage {
+        name: "reject_unc",
+        enabled: |options| options.deny_unc,
+        in_preview: always,
+        run: |path, _| {
+            reject_unc(&path)?;
+            Ok(path)
+        },
+    },
+    Stage {
+        name: "strict",
+        enabled: |options| options.strict,
+        in_preview: always,
+        run: |path, _| {
+            strict::reject_invalid_names(&path)?;
+            Ok(path)
+        },
+    },
+    Stage {
+        name: "strip_cur_dir",
+        enabled: |options| options.only_cur_dir,
+        in_preview: always,
+        run: |path, _| strip_cur_dir(path),
+    },
+    Stage {
+        name: "path_cch_canonicalize_ex",
+        enabled: |options| !without_path_cch(options),
+        in_preview: never,
+        run: |path, _| {
+            let path = path_cch_canonicalize_ex(path)?;
+            // only drive paths are a firm guarantee, a relative or UNC input may
+            // legitimately keep a `..` PathCchCanonicalizeEx had nowhere to resolve to
+            debug_assert!(
+                path.get(1..3) != Some(":\\") || !has_dot_dot(&path),
+                "PathCchCanonicalizeEx left a `..` in {:?}",
+                path
+            );
+            Ok(path)
+        },
+    },
+    Stage {
+        name: "strip_default_stream",
+        enabled: |options| options.normalize_default_stream,
+        in_preview: always,
+        run: |path, _| strip_default_stream(path),
+    },
+    Stage {
+        name: "strip_extended_when_short",
+        enabled: |options| options.auto_strip_extended_when_short,
+        in_preview: always,
+        run: |path, _| strip_extended_when_short(path),
+    },
+    Stage {
+        name: "unc_case",
+        enabled: always,
+        in_preview: always,
+        run: |path, options| Ok(apply_unc_case(path, options.unc_case)),
+    },
+    Stage {
+        name: "case",
+        enabled: always,
+        in_preview: always,
+        run: |path, options| apply_case(path, options.case),
+    },
+    Stage {
+        name: "trailing_slash",
+        enabled: always,
+        in_preview: always,
+        run: |path, options| apply_trailing_slash(path, options.trailing_slash),
+    },
+    Stage {
+        name: "reject_long_components",
+        enabled: always,
+        in_preview: without_path_cch,
+        run: |path, options| {
+            reject_long_components(&path, options.component_limit())?;
+            Ok(path)
+        },
+    },
+    Stage {
+        name: "post_process",
+        enabled: |options| options.post_process.is_some(),
+        in_preview: never,
+        run: |path, options| match options.post_process.as_ref() {
+            Option::None => Ok(path),
+            Option::Some(post_process) => Ok(post_process(path)),
+        },
+    },
+    Stage {
+        name: "assert_no_dot_dot",
+        enabled: |options| options.assert_no_dot_dot,
+        in_preview: without_path_cch,
+        run: |path, _| {
+            if has_dot_dot(&path) {
+                return Err(CanonicalizeError::UnresolvedDotDot(path.to_string()));
+            }
+            Ok(path)
+        },
+    },
+];
+
+/// the stages `options` enables, in order
+pub(crate) fn enabled_stages(
+    options: &CanonicalizeOptions,
+) -> impl Iterator<Item = &'static Stage> + '_ {
+    STAGES.iter().filter(move |stage| (stage.enabled)(options))
+}
+
+#[test]
+fn test_stage_names_unique() {
+    for (i, stage) in STAGES.iter().enumerate() {
+        assert!(STAGES[..i].iter().all(|other| other.name != stage.name));
+    }
+}
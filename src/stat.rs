@@ -0,0 +1,119 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    bindings::Windows::Win32::{
+        Foundation::FILETIME,
+        Storage::FileSystem::{
+            GetFileAttributesExW, GetFileExInfoStandard, WIN32_FILE_ATTRIBUTE_DATA,
+        },
+    },
+    canonicalize_with, CanonicalizeError, CanonicalizeOptions,
+};
+
+/// What `canonicalize_and_stat` learned about a file, from one
+/// `GetFileAttributesExW` call
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileInfo {
+    /// the raw `FILE_ATTRIBUTE_*` flags
+    pub attributes: u32,
+    /// size in bytes, `0` for a directory
+    pub len: u64,
+    pub created: SystemTime,
+    pub accessed: SystemTime,
+    pub modified: SystemTime,
+}
+
+impl FileInfo {
+    /// `true` for a directory, including a directory symbolic link
+    pub fn is_dir(&self) -> bool {
+        const FILE_ATTRIBUTE_DIRECTORY: u32 = 0x10;
+        self.attributes & FILE_ATTRIBUTE_DIRECTORY != 0
+    }
+
+    /// `true` when the read-only attribute is set
+    pub fn is_readonly(&self) -> bool {
+        const FILE_ATTRIBUTE_READONLY: u32 = 0x1;
+        self.attributes & FILE_ATTRIBUTE_READONLY != 0
+    }
+}
+
+/// 100ns intervals between 1601-01-01, where `FILETIME` counts from, and 1970-01-01
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+/// converts a `FILETIME` into a `SystemTime`, the same way `std` does
+fn system_time(time: FILETIME) -> SystemTime {
+    let ticks = ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+    if ticks >= FILETIME_UNIX_EPOCH {
+        UNIX_EPOCH + Duration::from_nanos((ticks - FILETIME_UNIX_EPOCH) * 100)
+    } else {
+        UNIX_EPOCH - Duration::from_nanos((FILETIME_UNIX_EPOCH - ticks) * 100)
+    }
+}
+
+#[test]
+fn test_system_time() {
+    let filetime = |ticks: u64| FILETIME {
+        dwLowDateTime: ticks as u32,
+        dwHighDateTime: (ticks >> 32) as u32,
+    };
+    assert_eq!(system_time(filetime(FILETIME_UNIX_EPOCH)), UNIX_EPOCH);
+    assert_eq!(
+        system_time(filetime(FILETIME_UNIX_EPOCH + 10_000_000)),
+        UNIX_EPOCH + Duration::from_secs(1)
+    );
+    assert_eq!(
+        system_time(filetime(FILETIME_UNIX_EPOCH - 15)),
+        UNIX_EPOCH - Duration::from_nanos(1500)
+    );
+}
+
+/// Canonicalizes a path and reads its attributes, size and timestamps, with
+/// a single Win32 call touching the filesystem.
+///
+/// The path must exist, a missing file fails with `CanonicalizeError::Win32`.
+/// Symbolic links aren't followed, the link itself is described.
+pub fn canonicalize_and_stat(path: &str) -> Result<(String, FileInfo), CanonicalizeError> {
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    let mut data = WIN32_FILE_ATTRIBUTE_DATA::default();
+    unsafe {
+        GetFileAttributesExW(
+            path.as_str(),
+            GetFileExInfoStandard,
+            &mut data as *mut WIN32_FILE_ATTRIBUTE_DATA as *mut std::ffi::c_void,
+        )
+        .ok()?
+    };
+    let info = FileInfo {
+        attributes: data.dwFileAttributes,
+        len: ((data.nFileSizeHigh as u64) << 32) | data.nFileSizeLow as u64,
+        created: system_time(data.ftCreationTime),
+        accessed: system_time(data.ftLastAccessTime),
+        modified: system_time(data.ftLastWriteTime),
+    };
+    Ok((path, info))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_and_stat() {
+    use std::os::windows::fs::MetadataExt;
+
+    let dir = std::env::temp_dir().join("win_canonicalize_stat");
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("a.txt");
+    std::fs::write(&file, b"hello").unwrap();
+
+    for path in &[&file, &dir] {
+        let text = format!(r#"{}\.\"#, path.to_str().unwrap());
+        let (canonical, info) = canonicalize_and_stat(&text).unwrap();
+        assert_eq!(canonical, crate::canonicalize(&text).unwrap());
+
+        let meta = std::fs::metadata(path).unwrap();
+        assert_eq!(info.attributes, meta.file_attributes());
+        assert_eq!(info.len, meta.len());
+        assert_eq!(info.is_dir(), meta.is_dir());
+        assert_eq!(info.modified, meta.modified().unwrap());
+        assert_eq!(info.created, meta.created().unwrap());
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,94 @@
+use crate::{device::is_reserved_name, names::root_len, CanonicalizeError};
+
+/// characters Windows refuses in any name, besides control characters
+const FORBIDDEN: &[char] = &['<', '>', '"', '|', '?', '*'];
+
+/// Fails on the first component (after the root) `CreateFileW` would refuse
+/// or quietly change the meaning of.
+pub(crate) fn reject_invalid_names(path: &str) -> Result<(), CanonicalizeError> {
+    let parts: Vec<&str> = path.split('\\').collect();
+    for part in &parts[root_len(&parts)..] {
+        if let Option::Some(c) = part
+            .chars()
+            .find(|c| ('\u{1}'..='\u{1F}').contains(c) || FORBIDDEN.contains(c))
+        {
+            return Err(CanonicalizeError::ForbiddenChar(c));
+        }
+        if *part == "." || *part == ".." {
+            continue;
+        }
+        if part.ends_with('.') || part.ends_with(' ') {
+            return Err(CanonicalizeError::TrailingDotOrSpace(part.to_string()));
+        }
+        if is_reserved_name(part) {
+            return Err(CanonicalizeError::ReservedName(part.to_string()));
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_canonicalize_with_strict() {
+    use crate::{canonicalize_with, CanonicalizeOptions};
+
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .strict(true);
+    let long = format!(r#"C:\{}"#, "a".repeat(256));
+    type Expected = fn(&CanonicalizeError) -> bool;
+    let cases: &[(&str, Expected)] = &[
+        (
+            r#"C:\dir\CON"#,
+            |e| matches!(e, CanonicalizeError::ReservedName(n) if n == "CON"),
+        ),
+        (r#"C:\nul.txt\a"#, |e| {
+            matches!(e, CanonicalizeError::ReservedName(_))
+        }),
+        (r#"C:\dir\com1 .log"#, |e| {
+            matches!(e, CanonicalizeError::ReservedName(_))
+        }),
+        (
+            r#"C:\dir.\a"#,
+            |e| matches!(e, CanonicalizeError::TrailingDotOrSpace(n) if n == "dir."),
+        ),
+        (r#"C:\dir \a"#, |e| {
+            matches!(e, CanonicalizeError::TrailingDotOrSpace(_))
+        }),
+        (r#"C:\a<b"#, |e| {
+            matches!(e, CanonicalizeError::ForbiddenChar('<'))
+        }),
+        (r#"C:\a\b|c"#, |e| {
+            matches!(e, CanonicalizeError::ForbiddenChar('|'))
+        }),
+        (r#"C:\a\*.txt"#, |e| {
+            matches!(e, CanonicalizeError::ForbiddenChar('*'))
+        }),
+        (r#"C:\a\"b""#, |e| {
+            matches!(e, CanonicalizeError::ForbiddenChar('"'))
+        }),
+        ("C:\\a\tb", |e| {
+            matches!(e, CanonicalizeError::ForbiddenChar('\t'))
+        }),
+        (&long, |e| {
+            matches!(e, CanonicalizeError::ComponentTooLong(_))
+        }),
+        ("C:\\a\0b", |e| matches!(e, CanonicalizeError::InteriorNul)),
+    ];
+    for (path, expected) in cases {
+        match canonicalize_with(path, &options) {
+            Err(e) if expected(&e) => {}
+            other => panic!("unexpected result for {:?}: {:?}", path, other),
+        }
+    }
+
+    // valid names, including dot segments, streams and the `\\?\` root
+    for path in &[
+        r#"C:\Users\.\Valarauca\console.txt"#,
+        r#"C:\a\..\b.txt"#,
+        r#"C:\a\x.txt:notes"#,
+        r#"\\?\C:\a\b"#,
+        r#"\\server\share\a"#,
+    ] {
+        assert!(canonicalize_with(path, &options).is_ok(), "{:?}", path);
+    }
+}
@@ -0,0 +1,202 @@
+use std::borrow::Cow;
+
+use crate::{matchers, UncCase};
+
+/// Splits a UNC path into its server, share, and the remainder after the share.
+///
+/// Both `\\server\share\x` and the extended `\\?\UNC\server\share\x` forms are
+/// recognized. Device paths (`\\.\`, `\\?\C:\`) are not UNC paths.
+pub fn unc_parts(path: &str) -> Option<(String, String, String)> {
+    let [server, share, rest] = matchers::unc(path)?;
+    let server = &path[server];
+    if server == "?" || server == "." {
+        return None;
+    }
+    Some((
+        server.to_string(),
+        path[share].to_string(),
+        path[rest].to_string(),
+    ))
+}
+
+#[test]
+fn test_unc_parts() {
+    let parts = |a: &str, b: &str, c: &str| Some((a.to_string(), b.to_string(), c.to_string()));
+
+    assert_eq!(unc_parts(r#"\\server\share"#), parts("server", "share", ""));
+    assert_eq!(
+        unc_parts(r#"\\server\share\"#),
+        parts("server", "share", "")
+    );
+    assert_eq!(unc_parts(r#"\\SERVER\Share"#), parts("SERVER", "Share", ""));
+    assert_eq!(
+        unc_parts(r#"\\server\share\a\b.txt"#),
+        parts("server", "share", r#"a\b.txt"#)
+    );
+    assert_eq!(
+        unc_parts(r#"//server/share/a/b.txt"#),
+        parts("server", "share", "a/b.txt")
+    );
+    assert_eq!(
+        unc_parts(r#"\\?\UNC\server\share\a"#),
+        parts("server", "share", "a")
+    );
+
+    // not UNC
+    assert_eq!(unc_parts(r#"\\server"#), None);
+    assert_eq!(unc_parts(r#"\\?\C:\a"#), None);
+    assert_eq!(unc_parts(r#"\\.\PhysicalDrive0"#), None);
+    assert_eq!(unc_parts(r#"C:\server\share"#), None);
+}
+
+/// the servers Windows exposes WSL distros under, `\\wsl$\Ubuntu\home`
+const WSL_SERVERS: &[&str] = &["wsl$", "wsl.localhost"];
+
+/// Reports if a path points into a WSL distro, `\\wsl$\<distro>\...` or
+/// `\\wsl.localhost\<distro>\...`.
+///
+/// These are UNC paths with the distro as the share, whose casing is kept even
+/// under `UncCase::Upper` since WSL distro names are case sensitive.
+pub fn is_wsl_path(path: &str) -> bool {
+    match unc_parts(path) {
+        Option::None => false,
+        Option::Some((server, _, _)) => WSL_SERVERS
+            .iter()
+            .any(|wsl| wsl.eq_ignore_ascii_case(&server)),
+    }
+}
+
+#[test]
+fn test_is_wsl_path() {
+    assert!(is_wsl_path(r#"\\wsl$\Ubuntu\home\me"#));
+    assert!(is_wsl_path(r#"\\wsl.localhost\Ubuntu-22.04\home"#));
+    assert!(is_wsl_path("//wsl$/Ubuntu/home/me"));
+    assert!(is_wsl_path(r#"\\WSL.LOCALHOST\Ubuntu"#));
+    assert!(is_wsl_path(r#"\\?\UNC\wsl$\Ubuntu\home"#));
+
+    assert!(!is_wsl_path(r#"\\wsl$"#));
+    assert!(!is_wsl_path(r#"\\wsl\Ubuntu"#));
+    assert!(!is_wsl_path(r#"\\server\share\wsl$"#));
+    assert!(!is_wsl_path(r#"C:\wsl$\Ubuntu"#));
+}
+
+/// the prefix extended-length UNC paths use in place of `\\`
+const EXTENDED_UNC: &str = r#"\\?\UNC\"#;
+
+/// `true` for `\\?\UNC\`, with either separator
+fn is_extended_unc(path: &str) -> bool {
+    path.get(..EXTENDED_UNC.len())
+        .is_some_and(|prefix| prefix.replace('/', "\\") == EXTENDED_UNC)
+}
+
+/// Rewrites `\\server\share\x` as the extended-length `\\?\UNC\server\share\x`.
+///
+/// Anything which isn't a plain UNC path, including one already in the
+/// extended form, is returned untouched.
+pub fn unc_to_extended(path: &str) -> Cow<'_, str> {
+    if is_extended_unc(path) || unc_parts(path).is_none() {
+        return Cow::Borrowed(path);
+    }
+    // separators aren't normalized under `\\?\`, so do it here
+    Cow::Owned(format!("{}{}", EXTENDED_UNC, path[2..].replace('/', "\\")))
+}
+
+#[test]
+fn test_unc_to_extended() {
+    assert_eq!(
+        unc_to_extended(r#"\\server\share\x"#),
+        r#"\\?\UNC\server\share\x"#
+    );
+    assert_eq!(
+        unc_to_extended(r#"//server/share/x"#),
+        r#"\\?\UNC\server\share\x"#
+    );
+    assert_eq!(
+        unc_to_extended(r#"\\?\UNC\server\share\x"#),
+        r#"\\?\UNC\server\share\x"#
+    );
+    assert_eq!(unc_to_extended(r#"\\?\C:\x"#), r#"\\?\C:\x"#);
+    assert_eq!(unc_to_extended(r#"C:\x"#), r#"C:\x"#);
+}
+
+/// Rewrites the extended-length `\\?\UNC\server\share\x` as `\\server\share\x`.
+///
+/// Anything which isn't an extended UNC path is returned untouched.
+pub fn extended_to_unc(path: &str) -> Cow<'_, str> {
+    if !is_extended_unc(path) || unc_parts(path).is_none() {
+        return Cow::Borrowed(path);
+    }
+    Cow::Owned(format!(r#"\\{}"#, &path[EXTENDED_UNC.len()..]))
+}
+
+#[test]
+fn test_extended_to_unc() {
+    assert_eq!(
+        extended_to_unc(r#"\\?\UNC\server\share\x"#),
+        r#"\\server\share\x"#
+    );
+    assert_eq!(
+        extended_to_unc(r#"\\server\share\x"#),
+        r#"\\server\share\x"#
+    );
+    assert_eq!(extended_to_unc(r#"\\?\C:\x"#), r#"\\?\C:\x"#);
+
+    for path in &[
+        r#"\\server\share"#,
+        r#"\\server\share\"#,
+        r#"\\server\share\a\b.txt"#,
+    ] {
+        assert_eq!(extended_to_unc(&unc_to_extended(path)), *path);
+    }
+    let extended = r#"\\?\UNC\server\share\a\b.txt"#;
+    assert_eq!(unc_to_extended(&extended_to_unc(extended)), extended);
+}
+
+/// applies `UncCase` to the server and share of a UNC path, anything else is untouched
+pub(crate) fn apply_unc_case<'a>(path: Cow<'a, str>, case: UncCase) -> Cow<'a, str> {
+    if case == UncCase::Preserve || is_wsl_path(&path) {
+        return path;
+    }
+    let (server, share) = match matchers::unc(&path) {
+        Option::None => return path,
+        Option::Some([server, share, _]) => (server, share),
+    };
+    if &path[server.clone()] == "?" || &path[server.clone()] == "." {
+        return path;
+    }
+
+    let fold = |s: &str| match case {
+        UncCase::Upper => s.to_uppercase(),
+        _ => s.to_lowercase(),
+    };
+    let mut out = String::with_capacity(path.len());
+    out.push_str(&path[..server.start]);
+    out.push_str(&fold(&path[server.clone()]));
+    out.push_str(&path[server.end..share.start]);
+    out.push_str(&fold(&path[share.clone()]));
+    out.push_str(&path[share.end..]);
+    Cow::Owned(out)
+}
+
+#[test]
+fn test_apply_unc_case() {
+    let upper = |s: &'static str| apply_unc_case(Cow::Borrowed(s), UncCase::Upper);
+    let preserve = |s: &'static str| apply_unc_case(Cow::Borrowed(s), UncCase::Preserve);
+    let lower = |s: &'static str| apply_unc_case(Cow::Borrowed(s), UncCase::Lower);
+
+    assert_eq!(preserve(r#"\\server\Share\a"#), r#"\\server\Share\a"#);
+    assert_eq!(upper(r#"\\server\Share\a"#), r#"\\SERVER\SHARE\a"#);
+    assert_eq!(
+        upper(r#"\\?\UNC\server\share\a"#),
+        r#"\\?\UNC\SERVER\SHARE\a"#
+    );
+    assert_eq!(lower(r#"\\SERVER\Share\A"#), r#"\\server\share\A"#);
+    assert_eq!(
+        lower(r#"\\?\UNC\Server\SHARE\A"#),
+        r#"\\?\UNC\server\share\A"#
+    );
+    assert_eq!(upper(r#"C:\server\share"#), r#"C:\server\share"#);
+    assert_eq!(lower(r#"C:\SERVER\share"#), r#"C:\SERVER\share"#);
+    assert_eq!(upper(r#"\\?\c:\a"#), r#"\\?\c:\a"#);
+    assert_eq!(upper(r#"\\wsl$\Ubuntu\home"#), r#"\\wsl$\Ubuntu\home"#);
+}
@@ -0,0 +1,149 @@
+use crate::{canonicalize_with, CanonicalizeError, CanonicalizeOptions};
+
+/// decodes `%XX` escapes, a `%` not followed by two hex digits is kept as-is
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(b) = s
+                .get(i + 1..i + 3)
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+            {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(out).ok()
+}
+
+#[test]
+fn test_percent_decode() {
+    assert_eq!(percent_decode("a%20b").unwrap(), "a b");
+    assert_eq!(percent_decode("%41%42").unwrap(), "AB");
+    assert_eq!(percent_decode("100%").unwrap(), "100%");
+    assert_eq!(percent_decode("%zz").unwrap(), "%zz");
+    assert_eq!(percent_decode("%C3%A9").unwrap(), "\u{e9}");
+    assert!(percent_decode("%FF").is_none());
+}
+
+/// `/C:/x` or `/C|/x` as found after the authority of a url, to `C:/x`
+fn strip_drive_slash(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let drive_at = if bytes.first() == Some(&b'/') { 1 } else { 0 };
+    match bytes.get(drive_at..drive_at + 2) {
+        Option::Some([letter, b':']) | Option::Some([letter, b'|'])
+            if letter.is_ascii_alphabetic()
+                && matches!(bytes.get(drive_at + 2), None | Some(b'/')) =>
+        {
+            format!("{}:{}", *letter as char, &path[drive_at + 2..])
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Turns the assorted shapes of `file:` url into a native (but not yet
+/// canonical) path, without touching the filesystem.
+///
+/// * `file:C:/x`, `file:/C:/x` and `file:///C:/x` are all `C:\x`
+/// * `file://localhost/C:/x` is `C:\x`
+/// * `file://host/share/x` and `file:////host/share/x` are `\\host\share\x`
+fn file_url_to_path(url: &str) -> Result<String, CanonicalizeError> {
+    let not_file_url = || CanonicalizeError::NotFileUrl(url.to_string());
+    // `get` rather than indexing, byte 5 may fall inside a multibyte character
+    if !url
+        .get(..5)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("file:"))
+    {
+        return Err(not_file_url());
+    }
+    let rest = &url[5..];
+    let rest = &rest[..rest.find(&['?', '#'][..]).unwrap_or(rest.len())];
+
+    let path = match rest.strip_prefix("//") {
+        Option::None => strip_drive_slash(rest),
+        Option::Some(rest) => {
+            let split = rest.find('/').unwrap_or(rest.len());
+            let (host, path) = rest.split_at(split);
+            if host.is_empty() || host.eq_ignore_ascii_case("localhost") {
+                strip_drive_slash(path)
+            } else {
+                format!("//{}{}", host, path)
+            }
+        }
+    };
+    let path = percent_decode(&path).ok_or_else(not_file_url)?;
+    Ok(path.replace('/', "\\"))
+}
+
+#[test]
+fn test_file_url_to_path() {
+    let table = [
+        // zero, one and three slashes
+        ("file:C:/x/y.txt", r#"C:\x\y.txt"#),
+        ("file:/C:/x/y.txt", r#"C:\x\y.txt"#),
+        ("file:///C:/x/y.txt", r#"C:\x\y.txt"#),
+        ("FILE:///c:/x", r#"c:\x"#),
+        ("file:///C|/x", r#"C:\x"#),
+        ("file://localhost/C:/x", r#"C:\x"#),
+        // two slashes and a host, or the legacy four slash form
+        ("file://server/share/x", r#"\\server\share\x"#),
+        ("file:////server/share/x", r#"\\server\share\x"#),
+        // percent encoding, query and fragment
+        (
+            "file:///C:/Program%20Files/a%20b.txt",
+            r#"C:\Program Files\a b.txt"#,
+        ),
+        ("file://server/my%20share/x", r#"\\server\my share\x"#),
+        ("file:///C:/x.html#top", r#"C:\x.html"#),
+        ("file:///C:/x?y=1", r#"C:\x"#),
+    ];
+    for (url, path) in table.iter() {
+        assert_eq!(&file_url_to_path(url).unwrap(), path, "{}", url);
+    }
+
+    // found by fuzzing, the fifth byte lands inside a multibyte character
+    let multibyte = "\u{ff23}^\u{1F600}";
+    for url in [
+        "http://server/x",
+        "C:/x",
+        "file",
+        "file:///C:/%FF",
+        multibyte,
+    ]
+    .iter()
+    {
+        match file_url_to_path(url) {
+            Err(CanonicalizeError::NotFileUrl(bad)) => assert_eq!(&bad, url),
+            other => panic!("expected NotFileUrl for {}, got {:?}", url, other),
+        }
+    }
+}
+
+/// Converts a `file:` url into a canonical Windows path.
+///
+/// Accepts the zero, one, two and three slash spellings browsers and other
+/// tools emit, `localhost`, `|` drive separators, and `file://host/share` for
+/// UNC paths. Percent escapes are decoded, the query and fragment are dropped.
+pub fn from_file_url(url: &str) -> Result<String, CanonicalizeError> {
+    let path = file_url_to_path(url)?;
+    canonicalize_with(&path, &CanonicalizeOptions::default())
+}
+
+#[cfg(windows)]
+#[test]
+fn test_from_file_url() {
+    assert_eq!(
+        from_file_url("file:///C:/Program%20Files/../Windows").unwrap(),
+        r#"C:\Windows"#
+    );
+    assert_eq!(
+        from_file_url("file://server/share/a/./b").unwrap(),
+        r#"\\server\share\a\b"#
+    );
+}
@@ -0,0 +1,357 @@
+use windows::HRESULT;
+
+use crate::{
+    bindings::Windows::Win32::{
+        Foundation::PWSTR,
+        Storage::FileSystem::{
+            GetDiskFreeSpaceExW, GetDriveTypeW, GetVolumeNameForVolumeMountPointW,
+            GetVolumePathNameW, QueryDosDeviceW,
+        },
+        System::WindowsProgramming::{
+            DRIVE_CDROM, DRIVE_FIXED, DRIVE_NO_ROOT_DIR, DRIVE_RAMDISK, DRIVE_REMOTE,
+            DRIVE_REMOVABLE,
+        },
+    },
+    canonicalize_with, matchers,
+    wide::from_wide_nul,
+    CanonicalizeError, CanonicalizeOptions, KIB32,
+};
+
+const ERROR_FILE_NOT_FOUND: u32 = 2;
+
+/// Asks the object manager what a drive (`X:`) is mapped onto.
+///
+/// Physical volumes come back as `\Device\HarddiskVolumeN`, while
+/// `subst` drives come back as `\??\C:\some\dir`.
+fn query_dos_device(drive: &str) -> Result<String, CanonicalizeError> {
+    let mut v = vec![0u16; KIB32];
+    let len = unsafe { QueryDosDeviceW(drive, PWSTR(v.as_mut_ptr()), v.len() as u32) };
+    if len == 0 {
+        return Err(CanonicalizeError::Win32(HRESULT::from_thread()));
+    }
+
+    // buffer is a list of NUL terminated strings, the first is the active mapping
+    from_wide_nul(&v[0..len as usize])
+}
+
+/// glues the remainder of a path onto the directory a `subst` drive points at
+fn splice_subst_target(target: &str, rest: &str) -> String {
+    let target = match target.strip_prefix(r#"UNC\"#) {
+        Option::Some(unc) => format!(r#"\\{}"#, unc),
+        Option::None => target.to_string(),
+    };
+    let target = target.trim_end_matches('\\');
+    let rest = rest.trim_start_matches(&['\\', '/'][..]);
+    format!(r#"{}\{}"#, target, rest)
+}
+
+#[test]
+fn test_splice_subst_target() {
+    assert_eq!(splice_subst_target(r#"C:\dev"#, r#"\src"#), r#"C:\dev\src"#);
+    assert_eq!(splice_subst_target(r#"C:\dev"#, r#"/src"#), r#"C:\dev\src"#);
+    assert_eq!(splice_subst_target(r#"C:\dev"#, ""), r#"C:\dev\"#);
+    assert_eq!(splice_subst_target(r#"C:\"#, r#"\src"#), r#"C:\src"#);
+    assert_eq!(
+        splice_subst_target(r#"UNC\server\share"#, r#"\src"#),
+        r#"\\server\share\src"#
+    );
+}
+
+/// Expands a path on a `subst` drive to the directory it really lives in.
+///
+/// Paths on physical drives, on drive letters which aren't mapped at all, and
+/// paths without a drive letter, are returned unchanged.
+pub fn resolve_subst(path: &str) -> Result<String, CanonicalizeError> {
+    let mut path = path.to_string();
+
+    // a subst drive can point into another subst drive,
+    // but there are only 26 letters to chain through
+    for _ in 0..26 {
+        let next = match matchers::drive_prefix(&path) {
+            Option::None => break,
+            Option::Some((drive, rest)) => {
+                let target = match query_dos_device(drive) {
+                    Ok(target) => target,
+                    // no such drive, so certainly not a subst one
+                    Err(CanonicalizeError::Win32(code))
+                        if code == HRESULT::from_win32(ERROR_FILE_NOT_FOUND) =>
+                    {
+                        break
+                    }
+                    Err(e) => return Err(e),
+                };
+                match target.strip_prefix(r#"\??\"#) {
+                    Option::None => break,
+                    Option::Some(target) => splice_subst_target(target, rest),
+                }
+            }
+        };
+        path = next;
+    }
+    Ok(path)
+}
+
+#[cfg(windows)]
+#[test]
+fn test_resolve_subst() {
+    // physical drive passes through
+    assert_eq!(
+        resolve_subst(r#"C:\Users\Valarauca"#).unwrap(),
+        r#"C:\Users\Valarauca"#
+    );
+    // no drive letter passes through
+    assert_eq!(
+        resolve_subst(r#"Users\Valarauca"#).unwrap(),
+        r#"Users\Valarauca"#
+    );
+
+    // only exercised on machines with a subst drive
+    for letter in b'D'..=b'Z' {
+        let drive = format!("{}:", letter as char);
+        match query_dos_device(&drive) {
+            Ok(target) => {
+                if target.starts_with(r#"\??\"#) {
+                    let resolved = resolve_subst(&format!(r#"{}\a"#, drive)).unwrap();
+                    assert!(!resolved.starts_with(&drive));
+                    assert!(resolved.ends_with(r#"\a"#));
+                }
+            }
+            // an unmapped letter passes through
+            Err(_) => {
+                let path = format!(r#"{}\a"#, drive);
+                assert_eq!(resolve_subst(&path).unwrap(), path);
+            }
+        }
+    }
+}
+
+/// the mount point (`C:\`, or a mounted folder) a path lives under
+fn volume_path_name(path: &str) -> Result<String, CanonicalizeError> {
+    let mut v = vec![0u16; KIB32];
+    unsafe { GetVolumePathNameW(path, PWSTR(v.as_mut_ptr()), v.len() as u32).ok()? };
+    from_wide_nul(&v)
+}
+
+/// the `\\?\Volume{GUID}\` name of a mount point
+fn volume_name_for_mount_point(mount_point: &str) -> Result<String, CanonicalizeError> {
+    let mut v = vec![0u16; 64];
+    unsafe {
+        GetVolumeNameForVolumeMountPointW(mount_point, PWSTR(v.as_mut_ptr()), v.len() as u32)
+            .ok()?
+    };
+    from_wide_nul(&v)
+}
+
+/// Identity of the volume `path` lives on, the volume GUID name where
+/// Windows has one, otherwise (network shares) the mount point itself
+fn volume_identity(path: &str) -> Result<String, CanonicalizeError> {
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    let mount_point = volume_path_name(&path)?;
+    match volume_name_for_mount_point(&mount_point) {
+        Ok(name) => Ok(name),
+        Err(_) => Ok(mount_point),
+    }
+}
+
+/// Reports if two paths live on the same volume, e.g. to decide whether a
+/// rename can be atomic or a copy is required.
+///
+/// Neither path needs to exist.
+pub fn same_volume(a: &str, b: &str) -> Result<bool, CanonicalizeError> {
+    let a = volume_identity(a)?;
+    let b = volume_identity(b)?;
+    Ok(a.eq_ignore_ascii_case(&b))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_same_volume() {
+    let temp = std::env::temp_dir();
+    let a = temp.join("same_volume_a.txt");
+    let b = temp.join("nested").join("same_volume_b.txt");
+    assert!(same_volume(a.to_str().unwrap(), b.to_str().unwrap()).unwrap());
+    assert!(same_volume(r#"C:\Windows"#, r#"C:\Users"#).unwrap());
+}
+
+/// Reports if two drive letters are the same volume, e.g. one is mounted a
+/// second time, to dedup across drive letters.
+///
+/// Only drive letters Windows gave a volume GUID compare equal, a `subst`
+/// drive or one mapped onto a network share fails with `CanonicalizeError::Win32`.
+pub fn drives_same_volume(a: char, b: char) -> Result<bool, CanonicalizeError> {
+    let a = volume_name_for_mount_point(&format!(r#"{}:\"#, a))?;
+    let b = volume_name_for_mount_point(&format!(r#"{}:\"#, b))?;
+    Ok(a.eq_ignore_ascii_case(&b))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_drives_same_volume() {
+    assert!(drives_same_volume('C', 'C').unwrap());
+    assert!(drives_same_volume('C', 'c').unwrap());
+}
+
+/// swaps the mount point a canonical path starts with for its volume GUID name,
+/// both of which Windows reports with a trailing `\`
+fn splice_volume_guid(volume: &str, mount_point: &str, path: &str) -> String {
+    let rest = path.get(mount_point.len()..).unwrap_or("");
+    let rest = rest.trim_start_matches('\\');
+    format!(r#"{}\{}"#, volume.trim_end_matches('\\'), rest)
+}
+
+#[test]
+fn test_splice_volume_guid() {
+    let volume = r#"\\?\Volume{26a21bda-a627-11d7-9931-806e6f6e6963}\"#;
+    assert_eq!(
+        splice_volume_guid(volume, r#"C:\"#, r#"C:\Users\Valarauca"#),
+        r#"\\?\Volume{26a21bda-a627-11d7-9931-806e6f6e6963}\Users\Valarauca"#
+    );
+    assert_eq!(
+        splice_volume_guid(volume, r#"C:\mnt\data\"#, r#"C:\mnt\data\x.txt"#),
+        r#"\\?\Volume{26a21bda-a627-11d7-9931-806e6f6e6963}\x.txt"#
+    );
+    assert_eq!(splice_volume_guid(volume, r#"C:\"#, r#"C:\"#), volume);
+}
+
+/// Canonicalizes a path into the `\\?\Volume{GUID}\path` form, which keeps
+/// pointing at the same file if the volume is given a different drive letter.
+///
+/// The result is only meaningful while that volume is mounted, and network
+/// shares (which have no volume GUID) fail with `CanonicalizeError::Win32`.
+pub fn to_volume_guid_path(path: &str) -> Result<String, CanonicalizeError> {
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    let mount_point = volume_path_name(&path)?;
+    let volume = volume_name_for_mount_point(&mount_point)?;
+    Ok(splice_volume_guid(&volume, &mount_point, &path))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_to_volume_guid_path() {
+    let temp = std::env::temp_dir().join("volume_guid.txt");
+    let guid_path = to_volume_guid_path(temp.to_str().unwrap()).unwrap();
+    assert!(guid_path.starts_with(r#"\\?\Volume{"#));
+    assert_eq!(guid_path.find(r#"}\"#), Some(r#"\\?\Volume{"#.len() + 36));
+    assert!(guid_path.ends_with(r#"\volume_guid.txt"#));
+}
+
+/// Space on a volume, in bytes, as reported by `disk_space`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiskSpace {
+    /// free space on the whole volume
+    pub free: u64,
+    /// size of the whole volume
+    pub total: u64,
+    /// free space this process may use, smaller than `free` under disk quotas
+    pub available: u64,
+}
+
+/// Free and total space of the volume a path lives on, e.g. to check a copy
+/// will fit before starting it.
+///
+/// The path itself doesn't need to exist.
+pub fn disk_space(path: &str) -> Result<DiskSpace, CanonicalizeError> {
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    let mount_point = volume_path_name(&path)?;
+    let mut space = DiskSpace {
+        free: 0,
+        total: 0,
+        available: 0,
+    };
+    unsafe {
+        GetDiskFreeSpaceExW(
+            mount_point.as_str(),
+            &mut space.available,
+            &mut space.total,
+            &mut space.free,
+        )
+        .ok()?
+    };
+    Ok(space)
+}
+
+#[cfg(windows)]
+#[test]
+fn test_disk_space() {
+    let temp = std::env::temp_dir().join("disk_space_missing.txt");
+    let space = disk_space(temp.to_str().unwrap()).unwrap();
+    assert!(space.total > 0);
+    assert!(space.free <= space.total);
+    assert!(space.available <= space.total);
+}
+
+/// The kind of drive a path lives on, as reported by `drive_stability`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DriveKind {
+    /// a hard disk or SSD
+    Fixed,
+    /// media which can be pulled out, e.g. a USB stick or SD card
+    Removable,
+    /// a network share, or a drive letter mapped onto one
+    Network,
+    /// an optical drive
+    CdRom,
+    /// a RAM disk, gone on reboot
+    Ram,
+    /// anything Windows can't classify
+    Unknown,
+}
+
+/// maps a `GetDriveTypeW` result onto a `DriveKind`
+fn drive_kind(drive_type: u32) -> DriveKind {
+    match drive_type {
+        DRIVE_FIXED => DriveKind::Fixed,
+        DRIVE_REMOVABLE => DriveKind::Removable,
+        DRIVE_REMOTE => DriveKind::Network,
+        DRIVE_CDROM => DriveKind::CdRom,
+        DRIVE_RAMDISK => DriveKind::Ram,
+        _ => DriveKind::Unknown,
+    }
+}
+
+#[test]
+fn test_drive_kind() {
+    assert_eq!(drive_kind(DRIVE_FIXED), DriveKind::Fixed);
+    assert_eq!(drive_kind(DRIVE_REMOTE), DriveKind::Network);
+    // DRIVE_UNKNOWN and DRIVE_NO_ROOT_DIR
+    assert_eq!(drive_kind(0), DriveKind::Unknown);
+    assert_eq!(drive_kind(1), DriveKind::Unknown);
+}
+
+/// `true` when `path` starts with a drive letter mapped onto a network share
+pub(crate) fn is_network_drive(path: &str) -> bool {
+    match matchers::drive_prefix(path) {
+        Option::None => false,
+        Option::Some((drive, _)) => {
+            let root = format!(r#"{}\"#, drive);
+            unsafe { GetDriveTypeW(root.as_str()) == DRIVE_REMOTE }
+        }
+    }
+}
+
+/// whether drive `letter` is mapped at all, `GetDriveTypeW` reports a letter
+/// with nothing behind it as having no root directory
+pub(crate) fn drive_exists(letter: char) -> bool {
+    let root = format!(r#"{}:\"#, letter);
+    unsafe { GetDriveTypeW(root.as_str()) != DRIVE_NO_ROOT_DIR }
+}
+
+/// Reports what kind of drive a path lives on, e.g. to avoid caching files
+/// from removable or network media as if they could never change.
+///
+/// The path itself doesn't need to exist.
+pub fn drive_stability(path: &str) -> Result<DriveKind, CanonicalizeError> {
+    let path = canonicalize_with(path, &CanonicalizeOptions::default())?;
+    let mount_point = volume_path_name(&path)?;
+    Ok(drive_kind(unsafe { GetDriveTypeW(mount_point.as_str()) }))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_drive_stability() {
+    assert_eq!(drive_stability(r#"C:\"#).unwrap(), DriveKind::Fixed);
+    assert_eq!(
+        drive_stability(r#"/c/Windows/missing.txt"#).unwrap(),
+        DriveKind::Fixed
+    );
+}
@@ -0,0 +1,342 @@
+use crate::{
+    canonicalize_with, path_cch_canonicalize_in_place, CanonicalizeError, CanonicalizeOptions,
+};
+
+const SLASH: u16 = b'/' as u16;
+const BACKSLASH: u16 = b'\\' as u16;
+const TILDE: u16 = b'~' as u16;
+const COLON: u16 = b':' as u16;
+
+/// decodes a wide buffer Windows filled up to its first NUL
+pub(crate) fn from_wide_nul(v: &[u16]) -> Result<String, CanonicalizeError> {
+    let end = v.iter().position(|c| *c == 0).unwrap_or(v.len());
+    Ok(String::from_utf16(&v[0..end])?)
+}
+
+fn is_sep(c: u16) -> bool {
+    c == SLASH || c == BACKSLASH
+}
+
+fn is_ascii_letter(c: u16) -> bool {
+    c < 0x80 && (c as u8).is_ascii_alphabetic()
+}
+
+/// wide equivalent of `fix_root` and `fix_tilde`, copies `input` into `out`
+/// rewriting `/c/...` to `C:\...` and a leading `~` component to `HOME`. A
+/// `~name` is left alone, there's no looking it up on disk here.
+fn fix_root_tilde_wide(input: &[u16], out: &mut Vec<u16>) -> Result<(), CanonicalizeError> {
+    out.clear();
+    match input {
+        [SLASH, letter, SLASH, rest @ ..] if is_ascii_letter(*letter) => {
+            out.push((*letter as u8).to_ascii_uppercase() as u16);
+            out.push(COLON);
+            out.push(BACKSLASH);
+            out.extend_from_slice(rest);
+        }
+        [TILDE, rest @ ..] if rest.first().is_none_or(|c| is_sep(*c)) => {
+            let home = std::env::var("HOME").map_err(CanonicalizeError::Home)?;
+            out.extend(home.encode_utf16());
+            out.extend_from_slice(rest);
+        }
+        _ => out.extend_from_slice(input),
+    }
+    Ok(())
+}
+
+/// wide equivalent of `normalize_slash`, every run of `/` and `\` becomes one `\`
+/// except a leading UNC/device `\\`. Works in place.
+fn normalize_slash_wide(v: &mut Vec<u16>) {
+    let unc = v.len() >= 2 && is_sep(v[0]) && is_sep(v[1]);
+    for c in v.iter_mut().filter(|c| is_sep(**c)) {
+        *c = BACKSLASH;
+    }
+    v.dedup_by(|c, prev| *c == BACKSLASH && *prev == BACKSLASH);
+    if unc {
+        // the dedup above removed at least one unit, so this never reallocates
+        v.insert(0, BACKSLASH);
+    }
+}
+
+/// the textual stages of `canonicalize`, run over UTF-16 into `out`
+fn text_stages_wide(input: &[u16], out: &mut Vec<u16>) -> Result<(), CanonicalizeError> {
+    fix_root_tilde_wide(input, out)?;
+    normalize_slash_wide(out);
+    Ok(())
+}
+
+#[test]
+fn test_text_stages_wide() {
+    let wide = |s: &str| s.encode_utf16().collect::<Vec<u16>>();
+    let text_stages_wide = |input: &[u16]| {
+        let mut out = Vec::new();
+        text_stages_wide(input, &mut out).map(|_| out)
+    };
+
+    assert_eq!(text_stages_wide(&wide("/c/a")).unwrap(), wide(r#"C:\a"#));
+    assert_eq!(
+        text_stages_wide(&wide("/f/Users//Valarauca\\/")).unwrap(),
+        wide(r#"F:\Users\Valarauca\"#)
+    );
+    assert_eq!(
+        text_stages_wide(&wide(r#"C:\Users\Valarauca"#)).unwrap(),
+        wide(r#"C:\Users\Valarauca"#)
+    );
+    assert_eq!(
+        text_stages_wide(&wide("//server//share/a")).unwrap(),
+        wide(r#"\\server\share\a"#)
+    );
+
+    // only a whole `~` component is HOME
+    assert_eq!(
+        text_stages_wide(&wide(r#"~$x.docx"#)).unwrap(),
+        wide(r#"~$x.docx"#)
+    );
+    assert_eq!(
+        text_stages_wide(&wide(r#"~backup\a"#)).unwrap(),
+        wide(r#"~backup\a"#)
+    );
+
+    // unpaired surrogates are carried through untouched
+    let mut input = wide("/c/a/");
+    input.push(0xD800);
+    let mut expected = wide(r#"C:\a\"#);
+    expected.push(0xD800);
+    assert_eq!(text_stages_wide(&input).unwrap(), expected);
+}
+
+/// Canonicalizes a UTF-16 path (without a NUL terminator) into `out`.
+///
+/// Every stage runs directly on the wide buffer, so nothing is lost to a
+/// `String` round trip. `out` is cleared first, reusing it across calls
+/// means a steady stream of paths (without `~`) doesn't allocate.
+pub fn canonicalize_u16(input: &[u16], out: &mut Vec<u16>) -> Result<(), CanonicalizeError> {
+    text_stages_wide(input, out)?;
+    path_cch_canonicalize_in_place(out)
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_u16() {
+    let input: Vec<u16> = "/c/a".encode_utf16().collect();
+    let mut out = Vec::new();
+    canonicalize_u16(&input, &mut out).unwrap();
+    assert_eq!(String::from_utf16(&out).unwrap(), r#"C:\a"#);
+}
+
+/// the Basic Multilingual Plane's private use area, one UTF-16 unit each
+const PRIVATE_USE: std::ops::RangeInclusive<char> = '\u{E000}'..='\u{F8FF}';
+
+/// decodes UTF-16 into a `String`, each distinct unpaired surrogate replaced by
+/// a private use character the input doesn't already contain, and the
+/// surrogates those stand in for
+fn stand_in_surrogates(input: &[u16]) -> Result<(String, Vec<(char, u16)>), CanonicalizeError> {
+    let decoded = || std::char::decode_utf16(input.iter().cloned());
+    let used: Vec<char> = decoded()
+        .filter_map(Result::ok)
+        .filter(|c| PRIVATE_USE.contains(c))
+        .collect();
+    let mut free = PRIVATE_USE.filter(|c| !used.contains(c));
+    let mut stand_ins: Vec<(char, u16)> = Vec::new();
+    let mut text = String::with_capacity(input.len());
+    for c in decoded() {
+        let c = match c {
+            Ok(c) => c,
+            Err(e) => {
+                let unit = e.unpaired_surrogate();
+                match stand_ins.iter().find(|(_, u)| *u == unit) {
+                    Option::Some((c, _)) => *c,
+                    Option::None => {
+                        // out of stand ins, it can only be reported as bad UTF-16
+                        let c = free
+                            .next()
+                            .ok_or_else(|| String::from_utf16(input).unwrap_err())?;
+                        stand_ins.push((c, unit));
+                        c
+                    }
+                }
+            }
+        };
+        text.push(c);
+    }
+    Ok((text, stand_ins))
+}
+
+/// encodes `text` as UTF-16, putting back the surrogates `stand_in_surrogates` replaced
+fn restore_surrogates(text: &str, stand_ins: &[(char, u16)]) -> Vec<u16> {
+    let mut v = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        match stand_ins.iter().find(|(stand_in, _)| *stand_in == c) {
+            Option::Some((_, unit)) => v.push(*unit),
+            Option::None => v.extend_from_slice(c.encode_utf16(&mut [0u16; 2])),
+        }
+    }
+    v
+}
+
+/// `canonicalize_with` over UTF-16 which may hold unpaired surrogates.
+///
+/// A stand in is one code unit that is never a separator, dot, space or
+/// control character, just like the surrogate it replaces, so every option
+/// (`deny_unc`, `strict`, the length limits, `post_process`...) sees the same
+/// path it would with the surrogate there.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub(crate) fn canonicalize_wide_with(
+    input: &[u16],
+    options: &CanonicalizeOptions,
+) -> Result<Vec<u16>, CanonicalizeError> {
+    let (text, stand_ins) = stand_in_surrogates(input)?;
+    let canonical = canonicalize_with(&text, options)?;
+    Ok(restore_surrogates(&canonical, &stand_ins))
+}
+
+#[test]
+fn test_canonicalize_wide_with() {
+    let wide = |s: &str| s.encode_utf16().collect::<Vec<u16>>();
+    let with_surrogate = |s: &str| {
+        let mut v = wide(s);
+        v.push(0xD800);
+        v.extend(wide(".txt"));
+        v
+    };
+    let options = CanonicalizeOptions::default().only_cur_dir(true);
+
+    assert_eq!(
+        canonicalize_wide_with(&with_surrogate("/c/a//./"), &options).unwrap(),
+        with_surrogate(r#"C:\a\"#)
+    );
+    // a private use character already in the path is left as it was
+    assert_eq!(
+        canonicalize_wide_with(&with_surrogate("/c/\u{E000}/"), &options).unwrap(),
+        with_surrogate("C:\\\u{E000}\\")
+    );
+
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .deny_unc(true);
+    match canonicalize_wide_with(&with_surrogate(r#"\\server\share\"#), &options) {
+        Err(CanonicalizeError::UncNotAllowed(_)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .strict(true);
+    match canonicalize_wide_with(&with_surrogate(r#"C:\CON\"#), &options) {
+        Err(CanonicalizeError::ReservedName(name)) => assert_eq!(name, "CON"),
+        other => panic!("unexpected {:?}", other),
+    }
+    match canonicalize_wide_with(&with_surrogate(r#"C:\dir.\"#), &options) {
+        Err(CanonicalizeError::TrailingDotOrSpace(name)) => assert_eq!(name, "dir."),
+        other => panic!("unexpected {:?}", other),
+    }
+    // the surrogate counts as the one code unit it is
+    let options = CanonicalizeOptions::default()
+        .only_cur_dir(true)
+        .max_component_len(5);
+    assert!(canonicalize_wide_with(&with_surrogate(r#"C:\"#), &options).is_ok());
+    assert!(canonicalize_wide_with(&with_surrogate(r#"C:\a"#), &options).is_err());
+}
+
+/// Decodes WTF-8 (UTF-8 which may also encode lone surrogates) into UTF-16
+fn wtf8_to_wide(bytes: &[u8]) -> Result<Vec<u16>, CanonicalizeError> {
+    let mut v = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let lead = bytes[i];
+        let (len, min, bits) = match lead {
+            0x00..=0x7F => (1, 0, lead as u32),
+            0xC2..=0xDF => (2, 0x80, (lead & 0x1F) as u32),
+            0xE0..=0xEF => (3, 0x800, (lead & 0x0F) as u32),
+            0xF0..=0xF4 => (4, 0x10000, (lead & 0x07) as u32),
+            _ => return Err(CanonicalizeError::InvalidWtf8(i)),
+        };
+        let tail = bytes
+            .get(i + 1..i + len)
+            .ok_or(CanonicalizeError::InvalidWtf8(i))?;
+        let mut cp = bits;
+        for b in tail {
+            if b & 0xC0 != 0x80 {
+                return Err(CanonicalizeError::InvalidWtf8(i));
+            }
+            cp = (cp << 6) | (b & 0x3F) as u32;
+        }
+        // overlong encodings are rejected, surrogates (0xD800-0xDFFF) are not
+        if cp < min || cp > 0x10FFFF {
+            return Err(CanonicalizeError::InvalidWtf8(i));
+        }
+        if cp < 0x10000 {
+            v.push(cp as u16);
+        } else {
+            let cp = cp - 0x10000;
+            v.push(0xD800 | (cp >> 10) as u16);
+            v.push(0xDC00 | (cp & 0x3FF) as u16);
+        }
+        i += len;
+    }
+    Ok(v)
+}
+
+/// Encodes UTF-16 as WTF-8, an unpaired surrogate becomes its own 3 byte sequence
+fn wide_to_wtf8(wide: &[u16]) -> Vec<u8> {
+    let mut v = Vec::with_capacity(wide.len());
+    for c in std::char::decode_utf16(wide.iter().cloned()) {
+        match c {
+            Ok(c) => v.extend_from_slice(c.encode_utf8(&mut [0u8; 4]).as_bytes()),
+            Err(e) => {
+                let u = e.unpaired_surrogate();
+                v.push(0xE0 | (u >> 12) as u8);
+                v.push(0x80 | ((u >> 6) & 0x3F) as u8);
+                v.push(0x80 | (u & 0x3F) as u8);
+            }
+        }
+    }
+    v
+}
+
+#[test]
+fn test_wtf8() {
+    // "C:\a" then a lone U+D800, which no valid UTF-8 can express
+    let bytes = b"C:\\a\xED\xA0\x80.txt";
+    let wide = wtf8_to_wide(bytes).unwrap();
+    let mut expected: Vec<u16> = r#"C:\a"#.encode_utf16().collect();
+    expected.push(0xD800);
+    expected.extend(".txt".encode_utf16());
+    assert_eq!(wide, expected);
+    assert_eq!(wide_to_wtf8(&wide), bytes);
+
+    let text = "C:\\stra\u{df}e\\\u{1F600}";
+    assert_eq!(
+        wtf8_to_wide(text.as_bytes()).unwrap(),
+        text.encode_utf16().collect::<Vec<u16>>()
+    );
+    assert_eq!(
+        wide_to_wtf8(&wtf8_to_wide(text.as_bytes()).unwrap()),
+        text.as_bytes()
+    );
+
+    match wtf8_to_wide(b"C:\\\xFF") {
+        Err(CanonicalizeError::InvalidWtf8(3)) => {}
+        other => panic!("unexpected {:?}", other),
+    }
+    // overlong `/`
+    assert!(wtf8_to_wide(b"\xC0\xAF").is_err());
+    // truncated sequence
+    assert!(wtf8_to_wide(b"a\xED\xA0").is_err());
+}
+
+/// Canonicalizes a path held as WTF-8 bytes, e.g. read from an archive or git.
+///
+/// The bytes are decoded to UTF-16 and run through `canonicalize_u16`, so an
+/// unpaired surrogate survives into the WTF-8 output.
+pub fn canonicalize_bytes(input: &[u8]) -> Result<Vec<u8>, CanonicalizeError> {
+    let wide = wtf8_to_wide(input)?;
+    let mut out = Vec::new();
+    canonicalize_u16(&wide, &mut out)?;
+    Ok(wide_to_wtf8(&out))
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_bytes() {
+    let out = canonicalize_bytes(b"/c/a/../b/\xED\xA0\x80.txt").unwrap();
+    assert_eq!(out, b"C:\\b\\\xED\xA0\x80.txt");
+}
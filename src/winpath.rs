@@ -0,0 +1,417 @@
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, Div},
+};
+
+use crate::{
+    canonicalize_with, fs::parent_dir, is_home_tilde, matchers, names::root_len, CanonicalizeError,
+    CanonicalizeOptions,
+};
+
+/// A path which has already been through `canonicalize`.
+///
+/// Comparison follows Explorer: case-insensitive, one component at a time.
+#[derive(Clone, Debug)]
+pub struct WinPath(String);
+
+impl WinPath {
+    /// Canonicalizes `path` with the default options
+    pub fn new(path: &str) -> Result<WinPath, CanonicalizeError> {
+        Ok(WinPath(canonicalize_with(
+            path,
+            &CanonicalizeOptions::default(),
+        )?))
+    }
+
+    /// Wraps a string which is already canonical (e.g. it came out of
+    /// `canonicalize`) without running the pipeline again.
+    ///
+    /// Nothing is checked in release builds, a non-canonical string here will
+    /// compare and display wrongly. Debug builds assert it looks canonical.
+    pub fn from_trusted(s: String) -> WinPath {
+        debug_assert!(looks_canonical(&s), "not a canonical path: {:?}", s);
+        WinPath(s)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    /// Appends `rest` and canonicalizes the result, like `Path::join`.
+    ///
+    /// An absolute `rest` (`C:\x`, `\\server\share`, `/c/x`, `~\x`) replaces
+    /// the path, a rooted one (`\x`) keeps only its drive or share.
+    pub fn join(&self, rest: &str) -> Result<WinPath, CanonicalizeError> {
+        WinPath::new(&join_text(&self.0, rest))
+    }
+}
+
+/// `Path::join` over canonical text, left for `canonicalize` to tidy up
+fn join_text(base: &str, rest: &str) -> String {
+    let is_sep = |c: char| c == '\\' || c == '/';
+    let mut chars = rest.chars();
+    let absolute = is_home_tilde(rest)
+        || rest.get(1..2) == Some(":")
+        || (chars.next().is_some_and(is_sep) && chars.next().is_some_and(is_sep))
+        || matchers::mingw_root(rest).is_some();
+    if absolute {
+        return rest.to_string();
+    }
+    if rest.starts_with(is_sep) {
+        let components: Vec<&str> = base.split('\\').collect();
+        return format!("{}{}", components[..root_len(&components)].join("\\"), rest);
+    }
+    if rest.is_empty() || base.ends_with('\\') {
+        format!("{}{}", base, rest)
+    } else {
+        format!("{}\\{}", base, rest)
+    }
+}
+
+/// Canonicalizes `path` against the directory `anchor_file` is in, for a
+/// file referenced from a config file, rather than the working directory.
+///
+/// Like `WinPath::join`, an absolute `path` ignores the anchor and a rooted
+/// one (`\x`) keeps only its drive or share.
+pub fn canonicalize_relative_to_file(
+    path: &str,
+    anchor_file: &str,
+) -> Result<String, CanonicalizeError> {
+    let options = CanonicalizeOptions::default();
+    let anchor = canonicalize_with(anchor_file, &options)?;
+    canonicalize_with(&join_text(parent_dir(&anchor), path), &options)
+}
+
+#[cfg(windows)]
+#[test]
+fn test_canonicalize_relative_to_file() {
+    let anchor = r#"C:\proj\config.toml"#;
+    assert_eq!(
+        canonicalize_relative_to_file("./schema.json", anchor).unwrap(),
+        r#"C:\proj\schema.json"#
+    );
+    assert_eq!(
+        canonicalize_relative_to_file("../shared/base.toml", "/c/proj/sub/config.toml").unwrap(),
+        r#"C:\proj\shared\base.toml"#
+    );
+    assert_eq!(
+        canonicalize_relative_to_file("D:/schemas/./schema.json", anchor).unwrap(),
+        r#"D:\schemas\schema.json"#
+    );
+    assert_eq!(
+        canonicalize_relative_to_file("~$x.docx", anchor).unwrap(),
+        r#"C:\proj\~$x.docx"#
+    );
+}
+
+#[test]
+fn test_join_text() {
+    let base = r#"C:\Users\Valarauca"#;
+    assert_eq!(join_text(base, "a.txt"), r#"C:\Users\Valarauca\a.txt"#);
+    assert_eq!(join_text(base, "a/../b"), r#"C:\Users\Valarauca\a/../b"#);
+    assert_eq!(join_text(r#"C:\"#, "a"), r#"C:\a"#);
+    assert_eq!(join_text(base, ""), base);
+
+    // absolute replaces the base
+    assert_eq!(join_text(base, r#"D:\x"#), r#"D:\x"#);
+    assert_eq!(join_text(base, "/d/x"), "/d/x");
+    assert_eq!(join_text(base, r#"\\server\share"#), r#"\\server\share"#);
+    assert_eq!(join_text(base, r#"~\x"#), r#"~\x"#);
+
+    // a `~name` which isn't a home directory is a file name
+    assert_eq!(
+        join_text(base, "~backup.txt"),
+        r#"C:\Users\Valarauca\~backup.txt"#
+    );
+    assert_eq!(
+        join_text(base, "~$x.docx"),
+        r#"C:\Users\Valarauca\~$x.docx"#
+    );
+
+    // rooted keeps the drive or share
+    assert_eq!(join_text(base, r#"\Windows"#), r#"C:\Windows"#);
+    assert_eq!(
+        join_text(r#"\\server\share\a"#, "/x"),
+        r#"\\server\share/x"#
+    );
+}
+
+/// `root / "sub" / "file.txt"`, shorthand for `join`.
+///
+/// # Panics
+///
+/// When `join` would return an error, e.g. `rest` contains a NUL.
+impl Div<&str> for &WinPath {
+    type Output = WinPath;
+
+    fn div(self, rest: &str) -> WinPath {
+        match self.join(rest) {
+            Ok(path) => path,
+            Err(e) => panic!("cannot join {:?} onto {}: {}", rest, self, e),
+        }
+    }
+}
+
+impl Div<&str> for WinPath {
+    type Output = WinPath;
+
+    fn div(self, rest: &str) -> WinPath {
+        &self / rest
+    }
+}
+
+#[cfg(windows)]
+#[test]
+fn test_winpath_join() {
+    let root = WinPath::new("/c/Users/Valarauca").unwrap();
+    assert_eq!(
+        root.join(r#"Documents\..\Music"#).unwrap().as_str(),
+        r#"C:\Users\Valarauca\Music"#
+    );
+    assert_eq!(
+        (&root / "sub" / "file.txt").as_str(),
+        r#"C:\Users\Valarauca\sub\file.txt"#
+    );
+    assert_eq!((&root / "/d/x").as_str(), r#"D:\x"#);
+    assert_eq!((root / r#"\Windows"#).as_str(), r#"C:\Windows"#);
+}
+
+/// cheap textual check for things the pipeline would have rewritten,
+/// a mingw root, a leading `~`, `/`, repeated separators, or `.`/`..` segments
+fn looks_canonical(s: &str) -> bool {
+    let body = s.strip_prefix(r#"\\"#).unwrap_or(s);
+    !s.starts_with('~')
+        && !s.contains('/')
+        && !body.contains(r#"\\"#)
+        && !body.split('\\').any(|seg| seg == "." || seg == "..")
+}
+
+#[test]
+fn test_looks_canonical() {
+    assert!(looks_canonical(r#"C:\Users\Valarauca"#));
+    assert!(looks_canonical(r#"C:\Users\Valarauca\"#));
+    assert!(looks_canonical(r#"\\server\share\a"#));
+    assert!(looks_canonical(r#"\\?\C:\a"#));
+    assert!(!looks_canonical(r#"/c/Users"#));
+    assert!(!looks_canonical(r#"~\Documents"#));
+    assert!(!looks_canonical(r#"C:\Users\\Valarauca"#));
+    assert!(!looks_canonical(r#"C:\Users\..\Valarauca"#));
+    assert!(!looks_canonical(r#"C:\Users\.\Valarauca"#));
+}
+
+#[test]
+fn test_from_trusted() {
+    let path = WinPath::from_trusted(r#"C:\Users\Valarauca"#.to_string());
+    assert_eq!(path.as_str(), r#"C:\Users\Valarauca"#);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "not a canonical path")]
+fn test_from_trusted_debug_assert() {
+    WinPath::from_trusted("/c/Users/../Valarauca".to_string());
+}
+
+/// compares `\` separated paths component by component, ignoring case,
+/// so `a\b` sorts before `a.txt` like it does in Explorer
+fn cmp_components(a: &str, b: &str) -> Ordering {
+    let fold = |seg: &str| {
+        seg.chars()
+            .flat_map(char::to_uppercase)
+            .collect::<Vec<char>>()
+    };
+    a.split('\\').map(fold).cmp(b.split('\\').map(fold))
+}
+
+#[test]
+fn test_cmp_components() {
+    assert_eq!(cmp_components(r#"C:\a\b"#, r#"C:\a.txt"#), Ordering::Less);
+    assert_eq!(cmp_components(r#"C:\A\B"#, r#"c:\a\b"#), Ordering::Equal);
+    assert_eq!(cmp_components(r#"C:\a"#, r#"C:\a\b"#), Ordering::Less);
+    assert_eq!(cmp_components(r#"C:\b"#, r#"C:\A\z"#), Ordering::Greater);
+    assert_eq!(cmp_components(r#"C:\é"#, r#"C:\É"#), Ordering::Equal);
+}
+
+/// Borrowed form of `WinPath`, what `Path` is to `PathBuf`.
+///
+/// Comparison and hashing live here, `WinPath` derefs to this and delegates.
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct WinPathRef(str);
+
+impl WinPathRef {
+    /// `s` must already be canonical, same contract as `WinPath::from_trusted`
+    fn from_str(s: &str) -> &WinPathRef {
+        // SAFETY: `WinPathRef` is `#[repr(transparent)]` over `str`
+        unsafe { &*(s as *const str as *const WinPathRef) }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Ord for WinPathRef {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_components(&self.0, &other.0)
+    }
+}
+
+impl PartialOrd for WinPathRef {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for WinPathRef {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for WinPathRef {}
+
+/// hashes the case folded text so paths which compare equal hash equal
+impl Hash for WinPathRef {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for c in self.0.chars().flat_map(char::to_uppercase) {
+            c.hash(state);
+        }
+    }
+}
+
+impl fmt::Display for WinPathRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for WinPathRef {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl ToOwned for WinPathRef {
+    type Owned = WinPath;
+
+    fn to_owned(&self) -> WinPath {
+        WinPath(self.0.to_string())
+    }
+}
+
+impl Deref for WinPath {
+    type Target = WinPathRef;
+
+    fn deref(&self) -> &WinPathRef {
+        WinPathRef::from_str(&self.0)
+    }
+}
+
+impl Borrow<WinPathRef> for WinPath {
+    fn borrow(&self) -> &WinPathRef {
+        self
+    }
+}
+
+impl AsRef<WinPathRef> for WinPath {
+    fn as_ref(&self) -> &WinPathRef {
+        self
+    }
+}
+
+impl Ord for WinPath {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl PartialOrd for WinPath {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for WinPath {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for WinPath {}
+
+impl Hash for WinPath {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl fmt::Display for WinPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl AsRef<str> for WinPath {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[test]
+fn test_winpath_ref() {
+    fn leaf(path: &WinPathRef) -> &str {
+        path.as_str().rsplit('\\').next().unwrap()
+    }
+
+    let owned = WinPath::from_trusted(r#"C:\Users\Valarauca"#.to_string());
+    assert_eq!(leaf(&owned), "Valarauca");
+
+    let upper = WinPath::from_trusted(r#"C:\USERS\VALARAUCA"#.to_string());
+    assert_eq!(*owned, *upper);
+    assert_eq!(owned, upper);
+    assert_eq!(owned.to_string(), r#"C:\Users\Valarauca"#);
+
+    let mut set = std::collections::HashSet::new();
+    set.insert(owned.clone());
+    assert!(set.contains::<WinPathRef>(&upper));
+    assert!(!set.insert(upper));
+
+    let copy: WinPath = (*owned).to_owned();
+    assert_eq!(copy.as_str(), owned.as_str());
+}
+
+#[cfg(windows)]
+#[test]
+fn test_winpath_sort() {
+    let mut paths: Vec<WinPath> = [
+        r#"C:\Users\a.txt"#,
+        r#"C:\Users\B"#,
+        r#"C:\Users\a\z.txt"#,
+        r#"C:\Users\A\b"#,
+        r#"C:\Users\a"#,
+    ]
+    .iter()
+    .map(|p| WinPath::new(p).unwrap())
+    .collect();
+    paths.sort();
+
+    let sorted: Vec<&str> = paths.iter().map(WinPath::as_str).collect();
+    assert_eq!(
+        sorted,
+        vec![
+            r#"C:\Users\a"#,
+            r#"C:\Users\A\b"#,
+            r#"C:\Users\a\z.txt"#,
+            r#"C:\Users\a.txt"#,
+            r#"C:\Users\B"#,
+        ]
+    );
+}